@@ -0,0 +1,611 @@
+//! # Configuración del Grafo de Agentes
+//!
+//! Define los preambles, modelos, herramientas y parámetros de generación de
+//! cada agente. Por defecto se usan los `system_prompt.md` embebidos en el
+//! binario; si `AGENTS_CONFIG_DIR` apunta a un directorio, sus archivos tienen
+//! prioridad y pueden recargarse en caliente sin reiniciar el proceso.
+//!
+//! ## Estructura del directorio
+//!
+//! ```text
+//! {AGENTS_CONFIG_DIR}/
+//! ├── agents.toml                     # modelos, tools y parámetros (opcional)
+//! ├── orchestrator/system_prompt.md   # (opcional)
+//! ├── address/system_prompt.md        # (opcional)
+//! ├── damage/system_prompt.md         # (opcional)
+//! ├── dummy/system_prompt.md          # (opcional)
+//! └── variants/{nombre}/...           # overlays por variante A/B (opcional)
+//! ```
+
+use super::specialized::{
+    address::AddressSpecialist, damage::DamageSpecialist, dummy::DummySpecialist,
+};
+use super::tools::{
+    address_distance::AddressDistance, address_parser::AddressParser, cost_database::CostDatabase,
+    geocoding::GeoCoding, shipping_zones::ShippingZoneCalculator, text_reverser::TextReverser,
+};
+use super::AnyModel;
+use crate::config::merge_toml;
+use crate::infra::hash::stable_hash;
+use anyhow::{bail, Context, Result};
+use rig::agent::AgentBuilder;
+use rig::client::CompletionClient;
+use rig::completion::CompletionModel;
+use rig::providers::gemini::completion::gemini_api_types::{
+    AdditionalParameters, GenerationConfig,
+};
+use rig::providers::{anthropic, gemini, openai};
+use rig::tool::Tool;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const SETTINGS_FILE: &str = "agents.toml";
+const PROMPT_FILE: &str = "system_prompt.md";
+const VARIANTS_DIR: &str = "variants";
+const DEFAULT_VARIANT: &str = "default";
+
+// ============================================================================
+// 1. AJUSTES POR AGENTE
+// ============================================================================
+
+/// Proveedor LLM que respalda a un agente.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    #[default]
+    Gemini,
+    OpenAi,
+    Anthropic,
+}
+
+impl Provider {
+    /// Nombre del proveedor tal como aparece en la configuración.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provider::Gemini => "gemini",
+            Provider::OpenAi => "openai",
+            Provider::Anthropic => "anthropic",
+        }
+    }
+
+    /// API key del proveedor (vacía si no está configurada).
+    pub fn api_key(&self) -> &'static str {
+        let config = crate::config::get();
+        match self {
+            Provider::Gemini => config.models.gemini_api_key.expose(),
+            Provider::OpenAi => config.models.openai_api_key.expose(),
+            Provider::Anthropic => config.models.anthropic_api_key.expose(),
+        }
+    }
+}
+
+/// Ajustes de un agente individual (orquestador o especialista).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentSettings {
+    pub provider: Provider,
+    pub model: String,
+    /// Herramientas habilitadas, por su `Tool::NAME`.
+    pub tools: Vec<String>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u64>,
+    pub top_k: Option<i32>,
+    pub top_p: Option<f64>,
+    /// System prompt. Se carga desde `system_prompt.md`, nunca desde el TOML.
+    #[serde(skip)]
+    pub preamble: String,
+}
+
+impl Default for AgentSettings {
+    fn default() -> Self {
+        Self {
+            provider: Provider::Gemini,
+            model: "gemini-2.5-flash".to_string(),
+            tools: Vec::new(),
+            temperature: None,
+            max_tokens: None,
+            top_k: None,
+            top_p: None,
+            preamble: String::new(),
+        }
+    }
+}
+
+impl AgentSettings {
+    /// Instancia el modelo configurado con las API keys de la configuración.
+    pub fn build_model(&self) -> AnyModel {
+        let api_key = self.provider.api_key();
+
+        match self.provider {
+            Provider::Gemini => {
+                let client = gemini::client::Client::new(api_key);
+                AnyModel::new(
+                    self.provider.as_str(),
+                    &self.model,
+                    Box::new(client.completion_model(&self.model)),
+                )
+            }
+            Provider::OpenAi => {
+                let client = openai::client::Client::new(api_key);
+                AnyModel::new(
+                    self.provider.as_str(),
+                    &self.model,
+                    Box::new(client.completion_model(&self.model)),
+                )
+            }
+            Provider::Anthropic => {
+                let client = anthropic::client::Client::new(api_key);
+                AnyModel::new(
+                    self.provider.as_str(),
+                    &self.model,
+                    Box::new(client.completion_model(&self.model)),
+                )
+            }
+        }
+    }
+
+    /// Crea un `AgentBuilder` con el preamble y los parámetros de generación
+    /// ya aplicados. El llamador sólo debe registrar sus herramientas.
+    pub fn agent_builder<M: CompletionModel>(&self, model: M) -> AgentBuilder<M> {
+        let mut builder = AgentBuilder::new(model).preamble(&self.preamble);
+
+        if let Some(temperature) = self.temperature {
+            builder = builder.temperature(temperature);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            builder = builder.max_tokens(max_tokens);
+        }
+        if self.provider == Provider::Gemini && (self.top_k.is_some() || self.top_p.is_some()) {
+            let gen_cfg = GenerationConfig {
+                top_k: self.top_k,
+                top_p: self.top_p,
+                candidate_count: Some(1),
+                ..Default::default()
+            };
+            let cfg = AdditionalParameters::default().with_config(gen_cfg);
+            builder = builder.additional_params(
+                serde_json::to_value(cfg).expect("Failed to serialize generation config"),
+            );
+        }
+
+        builder
+    }
+
+    /// Indica si la herramienta `name` está habilitada para este agente.
+    pub fn has_tool(&self, name: &str) -> bool {
+        self.tools.iter().any(|tool| tool == name)
+    }
+}
+
+// ============================================================================
+// 2. CONFIGURACIÓN COMPLETA
+// ============================================================================
+
+/// Configuración de todo el grafo de agentes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentsConfig {
+    pub orchestrator: AgentSettings,
+    pub address: AgentSettings,
+    pub damage: AgentSettings,
+    pub dummy: AgentSettings,
+    /// Variantes para experimentos A/B (sólo en la configuración base).
+    pub variants: Vec<VariantSpec>,
+}
+
+impl Default for AgentsConfig {
+    /// Configuración embebida en el binario (equivalente al comportamiento original).
+    fn default() -> Self {
+        Self {
+            orchestrator: AgentSettings {
+                tools: vec![
+                    "address_specialist".to_string(),
+                    "damage_specialist".to_string(),
+                ],
+                top_k: Some(1),
+                top_p: Some(0.95),
+                preamble: include_str!("orchestrator/system_prompt.md").to_string(),
+                ..Default::default()
+            },
+            address: AgentSettings {
+                tools: vec![
+                    "address_parser".to_string(),
+                    "geocoding_service".to_string(),
+                    "address_distance".to_string(),
+                    "shipping_zone_calculator".to_string(),
+                ],
+                preamble: include_str!("specialized/address/system_prompt.md").to_string(),
+                ..Default::default()
+            },
+            damage: AgentSettings {
+                tools: vec!["cost_database".to_string()],
+                preamble: include_str!("specialized/damage/system_prompt.md").to_string(),
+                ..Default::default()
+            },
+            dummy: AgentSettings {
+                tools: vec!["text_reverser".to_string()],
+                preamble: include_str!("specialized/dummy/system_prompt.md").to_string(),
+                ..Default::default()
+            },
+            variants: Vec::new(),
+        }
+    }
+}
+
+impl AgentsConfig {
+    /// Carga las variantes configuradas desde `AGENTS_CONFIG_DIR`, o la
+    /// configuración embebida (como única variante) si no está definido.
+    pub fn load() -> Result<Vec<AgentVariant>> {
+        match Self::config_dir() {
+            Some(dir) => Self::load_variants(&dir),
+            None => Ok(vec![AgentVariant::single(Self::default())]),
+        }
+    }
+
+    /// Carga la configuración base y sus variantes desde un directorio.
+    ///
+    /// Cada variante declarada en `[[variants]]` parte de la configuración base
+    /// y aplica encima lo que haya en `variants/{nombre}/` (mismo formato que
+    /// el directorio raíz). Sin variantes declaradas se usa sólo la base.
+    pub fn load_variants(dir: &Path) -> Result<Vec<AgentVariant>> {
+        let base = Self::load_from_dir(dir)?;
+
+        if base.variants.is_empty() {
+            return Ok(vec![AgentVariant::single(base)]);
+        }
+
+        let mut variants = Vec::with_capacity(base.variants.len());
+        for spec in &base.variants {
+            if !is_valid_variant_name(&spec.name) {
+                bail!("Invalid variant name '{}'", spec.name);
+            }
+            if variants.iter().any(|v: &AgentVariant| v.name == spec.name) {
+                bail!("Duplicated variant '{}'", spec.name);
+            }
+
+            let variant_dir = dir.join(VARIANTS_DIR).join(&spec.name);
+            let mut config = Self::load_layer(&base, &variant_dir)
+                .with_context(|| format!("Failed to load variant '{}'", spec.name))?;
+            config.variants.clear();
+            config.validate()?;
+
+            variants.push(AgentVariant {
+                name: spec.name.clone(),
+                weight: spec.weight,
+                config,
+            });
+        }
+
+        if variants.iter().all(|v| v.weight == 0) {
+            bail!("At least one variant must have a weight greater than 0");
+        }
+
+        Ok(variants)
+    }
+
+    /// Carga la configuración base desde un directorio. Los archivos y claves
+    /// ausentes conservan el valor embebido correspondiente.
+    pub fn load_from_dir(dir: &Path) -> Result<Self> {
+        let config = Self::load_layer(&Self::default(), dir)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Aplica el `agents.toml` y los `system_prompt.md` de `dir` sobre `base`.
+    fn load_layer(base: &Self, dir: &Path) -> Result<Self> {
+        let settings_path = dir.join(SETTINGS_FILE);
+        let mut config: Self = if settings_path.exists() {
+            let raw = std::fs::read_to_string(&settings_path)
+                .with_context(|| format!("Failed to read {}", settings_path.display()))?;
+            let overrides: toml::Value = toml::from_str(&raw)
+                .with_context(|| format!("Invalid agents config {}", settings_path.display()))?;
+
+            let mut merged = toml::Value::try_from(base)?;
+            merge_toml(&mut merged, overrides);
+            merged
+                .try_into()
+                .with_context(|| format!("Invalid agents config {}", settings_path.display()))?
+        } else {
+            base.clone()
+        };
+
+        for (name, settings, fallback) in [
+            ("orchestrator", &mut config.orchestrator, &base.orchestrator),
+            ("address", &mut config.address, &base.address),
+            ("damage", &mut config.damage, &base.damage),
+            ("dummy", &mut config.dummy, &base.dummy),
+        ] {
+            let prompt_path = dir.join(name).join(PROMPT_FILE);
+            settings.preamble = if prompt_path.exists() {
+                std::fs::read_to_string(&prompt_path)
+                    .with_context(|| format!("Failed to read {}", prompt_path.display()))?
+            } else {
+                fallback.preamble.clone()
+            };
+
+            if settings.preamble.trim().is_empty() {
+                bail!("Agent '{}' has an empty system prompt", name);
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Versión corta (hash estable) de los prompts y ajustes de todos los agentes.
+    /// Cambia cada vez que se edita cualquier `system_prompt.md` o `agents.toml`.
+    pub fn version(&self) -> String {
+        let mut bytes = serde_json::to_vec(self).unwrap_or_default();
+        for settings in [&self.orchestrator, &self.address, &self.damage, &self.dummy] {
+            bytes.extend_from_slice(settings.preamble.as_bytes());
+        }
+        format!("{:08x}", stable_hash(&bytes) as u32)
+    }
+
+    /// Verifica que cada agente sólo habilite herramientas que sabe construir.
+    pub fn validate(&self) -> Result<()> {
+        let allowed: [(&str, &AgentSettings, &[&str]); 4] = [
+            (
+                "orchestrator",
+                &self.orchestrator,
+                &[
+                    AddressSpecialist::<AnyModel>::NAME,
+                    DamageSpecialist::<AnyModel>::NAME,
+                    DummySpecialist::<AnyModel>::NAME,
+                ],
+            ),
+            (
+                "address",
+                &self.address,
+                &[
+                    AddressParser::NAME,
+                    GeoCoding::NAME,
+                    AddressDistance::NAME,
+                    ShippingZoneCalculator::NAME,
+                ],
+            ),
+            ("damage", &self.damage, &[CostDatabase::NAME]),
+            ("dummy", &self.dummy, &[TextReverser::NAME]),
+        ];
+
+        for (name, settings, known) in allowed {
+            if let Some(unknown) = settings.tools.iter().find(|t| !known.contains(&t.as_str())) {
+                bail!("Agent '{}' does not support tool '{}'", name, unknown);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Proveedores usados por algún agente, sin repetir.
+    pub fn providers(&self) -> Vec<Provider> {
+        let mut providers = Vec::new();
+        for settings in [&self.orchestrator, &self.address, &self.damage, &self.dummy] {
+            if !providers.contains(&settings.provider) {
+                providers.push(settings.provider);
+            }
+        }
+        providers
+    }
+
+    /// Directorio de configuración en disco, si está definido.
+    pub fn config_dir() -> Option<PathBuf> {
+        let dir = &crate::config::get().agents.config_dir;
+        (!dir.is_empty()).then(|| PathBuf::from(dir))
+    }
+}
+
+// ============================================================================
+// 3. VARIANTES (EXPERIMENTOS A/B)
+// ============================================================================
+
+/// Declaración de una variante en `agents.toml`:
+///
+/// ```toml
+/// [[variants]]
+/// name = "control"
+/// weight = 90
+///
+/// [[variants]]
+/// name = "damage-v2"   # prompts en variants/damage-v2/damage/system_prompt.md
+/// weight = 10
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct VariantSpec {
+    pub name: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// Variante ya resuelta: nombre, peso y configuración completa del grafo.
+#[derive(Debug, Clone)]
+pub struct AgentVariant {
+    pub name: String,
+    pub weight: u32,
+    pub config: AgentsConfig,
+}
+
+impl AgentVariant {
+    /// Variante única usada cuando no hay experimentos configurados.
+    pub fn single(config: AgentsConfig) -> Self {
+        Self {
+            name: DEFAULT_VARIANT.to_string(),
+            weight: 1,
+            config,
+        }
+    }
+}
+
+fn is_valid_variant_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// ============================================================================
+// 4. DETECCIÓN DE CAMBIOS
+// ============================================================================
+
+/// Huella de los archivos de configuración (ruta + fecha de modificación),
+/// incluyendo los de `variants/`. Dos huellas distintas indican que hay que recargar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFingerprint(Vec<(PathBuf, Option<SystemTime>)>);
+
+impl ConfigFingerprint {
+    pub fn of(dir: &Path) -> Self {
+        let mut entries = Vec::new();
+        collect_files(dir, &mut entries);
+        entries.sort();
+        Self(entries)
+    }
+}
+
+fn collect_files(dir: &Path, entries: &mut Vec<(PathBuf, Option<SystemTime>)>) {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, entries);
+        } else {
+            let modified = entry.metadata().and_then(|m| m.modified()).ok();
+            entries.push((path, modified));
+        }
+    }
+}
+
+// ============================================================================
+// 5. TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("agents-config-{}-{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_default_uses_embedded_prompts() {
+        let config = AgentsConfig::default();
+        assert!(config.orchestrator.preamble.contains("address_specialist"));
+        assert!(config.orchestrator.has_tool("damage_specialist"));
+        assert!(config.damage.has_tool("cost_database"));
+    }
+
+    #[test]
+    fn test_load_from_dir_overrides_files() {
+        let dir = temp_dir("override");
+        std::fs::create_dir_all(dir.join("damage")).unwrap();
+        std::fs::write(
+            dir.join("damage").join(PROMPT_FILE),
+            "Nuevo prompt de daños",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(SETTINGS_FILE),
+            "[orchestrator]\nmodel = \"gemini-2.5-pro\"\ntools = [\"damage_specialist\"]\n",
+        )
+        .unwrap();
+
+        let config = AgentsConfig::load_from_dir(&dir).unwrap();
+        assert_eq!(config.damage.preamble, "Nuevo prompt de daños");
+        assert_eq!(config.orchestrator.model, "gemini-2.5-pro");
+        assert!(!config.orchestrator.has_tool("address_specialist"));
+        // El prompt no definido en disco conserva el embebido.
+        assert_eq!(
+            config.address.preamble,
+            AgentsConfig::default().address.preamble
+        );
+        // Las claves no definidas en el TOML conservan su valor por defecto.
+        assert_eq!(config.orchestrator.top_k, Some(1));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_load_from_dir_rejects_unknown_keys() {
+        let dir = temp_dir("unknown");
+        for settings in [
+            "[damage]\ntemprature = 0.2\n",
+            "[dammage]\nmodel = \"gemini-2.5-pro\"\n",
+        ] {
+            std::fs::write(dir.join(SETTINGS_FILE), settings).unwrap();
+            let error = AgentsConfig::load_from_dir(&dir).unwrap_err();
+            assert!(
+                format!("{:#}", error).contains("unknown field"),
+                "{:#}",
+                error
+            );
+        }
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_load_variants_overlays_base() {
+        let dir = temp_dir("variants");
+        std::fs::write(
+            dir.join(SETTINGS_FILE),
+            "[[variants]]\nname = \"control\"\nweight = 90\n\n[[variants]]\nname = \"damage-v2\"\nweight = 10\n",
+        )
+        .unwrap();
+        let variant_prompt = dir.join(VARIANTS_DIR).join("damage-v2").join("damage");
+        std::fs::create_dir_all(&variant_prompt).unwrap();
+        std::fs::write(variant_prompt.join(PROMPT_FILE), "Prompt de daños v2").unwrap();
+
+        let variants = AgentsConfig::load_variants(&dir).unwrap();
+        assert_eq!(variants.len(), 2);
+        assert_eq!(variants[0].name, "control");
+        assert_eq!(
+            variants[0].config.damage.preamble,
+            AgentsConfig::default().damage.preamble
+        );
+        assert_eq!(variants[1].config.damage.preamble, "Prompt de daños v2");
+        assert_ne!(variants[0].config.version(), variants[1].config.version());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_load_variants_without_declaration_is_single() {
+        let dir = temp_dir("single");
+        let variants = AgentsConfig::load_variants(&dir).unwrap();
+        assert_eq!(variants.len(), 1);
+        assert_eq!(variants[0].name, DEFAULT_VARIANT);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_tool() {
+        let mut config = AgentsConfig::default();
+        config.damage.tools.push("geocoding_service".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_fingerprint_changes_when_file_appears() {
+        let dir = temp_dir("fingerprint");
+        let before = ConfigFingerprint::of(&dir);
+        let variant_dir = dir.join(VARIANTS_DIR).join("v2");
+        std::fs::create_dir_all(&variant_dir).unwrap();
+        std::fs::write(variant_dir.join(SETTINGS_FILE), "").unwrap();
+        let after = ConfigFingerprint::of(&dir);
+        assert_ne!(before, after);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
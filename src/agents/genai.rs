@@ -0,0 +1,80 @@
+//! # Spans GenAI
+//!
+//! Spans con las convenciones semánticas de OpenTelemetry para GenAI:
+//!
+//! | Span | Dónde | Atributos principales |
+//! |------|-------|-----------------------|
+//! | `invoke_agent orchestrator` | `Orchestrator::chat` | `gen_ai.agent.name`, `gen_ai.request.model`, `session.id` |
+//! | `execute_tool {tool}` | `Instrumented::call` (especialistas y herramientas) | `gen_ai.tool.name`, `session.id` |
+//! | `chat {model}` | `AnyModel::completion` | `gen_ai.provider.name`, `gen_ai.request.model`, `gen_ai.usage.*`, `gen_ai.response.finish_reasons` |
+//!
+//! El contenido (prompts, respuestas, argumentos y resultados de herramientas)
+//! sólo se adjunta con `GENAI_CAPTURE_CONTENT=true` y pasa antes por el
+//! redactor de PII si `PII_REDACT_LOGS` está activo.
+
+use crate::infra::redaction;
+use rig::message::AssistantContent;
+use rig::OneOrMany;
+use serde::Serialize;
+use tracing::Span;
+
+/// Indica si los spans deben incluir prompts, respuestas y argumentos.
+pub fn capture_content() -> bool {
+    crate::config::get().telemetry.genai_capture_content
+}
+
+/// Adjunta `value` (serializado como JSON) al campo `field` del span.
+/// No hace nada si la captura de contenido está desactivada.
+pub fn record_content<T: Serialize + ?Sized>(span: &Span, field: &'static str, value: &T) {
+    if !capture_content() {
+        return;
+    }
+
+    let Ok(json) = serde_json::to_string(value) else {
+        return;
+    };
+    let redactor = redaction::get();
+    if redactor.redact_logs {
+        span.record(field, redactor.mask(&json).as_ref());
+    } else {
+        span.record(field, json.as_str());
+    }
+}
+
+/// Marca el span como fallido. `error_type` debe ser de baja cardinalidad
+/// (un código de error o un nombre de tipo, nunca el mensaje).
+pub fn record_error(span: &Span, error_type: &str) {
+    span.record("error.type", error_type);
+    span.record("otel.status_code", "ERROR");
+}
+
+/// Motivo de fin de la respuesta. `CompletionModelDyn` no expone la respuesta
+/// cruda del proveedor, así que se deduce del contenido.
+pub fn finish_reason(choice: &OneOrMany<AssistantContent>) -> &'static str {
+    if choice
+        .iter()
+        .any(|content| matches!(content, AssistantContent::ToolCall(_)))
+    {
+        "tool_calls"
+    } else {
+        "stop"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finish_reason() {
+        let text = OneOrMany::one(AssistantContent::text("Hola"));
+        assert_eq!(finish_reason(&text), "stop");
+
+        let tool_call = OneOrMany::many(vec![
+            AssistantContent::text("Consultando..."),
+            AssistantContent::tool_call("call-1", "cost_database", serde_json::json!({})),
+        ])
+        .unwrap();
+        assert_eq!(finish_reason(&tool_call), "tool_calls");
+    }
+}
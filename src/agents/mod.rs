@@ -1,20 +1,144 @@
+pub mod config;
+pub mod genai;
 pub mod orchestrator;
 pub mod specialized;
+pub mod summary;
 pub mod tools;
+pub mod turn;
+pub mod variants;
 
+use crate::infra::errors::{ErrorKind, LlmKind};
+use crate::infra::metrics;
+use config::AgentSettings;
 use rig::client::builder::FinalCompletionResponse;
 use rig::completion::{
     CompletionError, CompletionModel, CompletionModelDyn, CompletionRequest, CompletionResponse,
+    Prompt, PromptError,
 };
 use rig::streaming::StreamingCompletionResponse;
+use serde::de::DeserializeOwned;
 use std::sync::Arc;
+use std::time::Instant;
+use tracing::{field::Empty, Instrument, Span};
+use turn::TurnContext;
 
+/// Modelo de cualquier proveedor detrás de una interfaz común. Conserva el
+/// proveedor y el nombre del modelo para etiquetar métricas y spans.
 #[derive(Clone)]
-pub struct AnyModel(Arc<Box<dyn CompletionModelDyn>>);
+pub struct AnyModel {
+    inner: Arc<Box<dyn CompletionModelDyn>>,
+    provider: &'static str,
+    name: Arc<str>,
+    turn: Option<Arc<TurnContext>>,
+}
 
 impl AnyModel {
-    pub fn new(model: Box<dyn CompletionModelDyn>) -> Self {
-        Self(Arc::new(model))
+    pub fn new(provider: &'static str, name: &str, model: Box<dyn CompletionModelDyn>) -> Self {
+        Self {
+            inner: Arc::new(model),
+            provider,
+            name: name.into(),
+            turn: None,
+        }
+    }
+
+    /// Copia del modelo asociada a un turno: sus spans llevan el `session.id`
+    /// y su consumo de tokens se acumula en el `TurnContext`.
+    pub fn with_turn(&self, turn: &Arc<TurnContext>) -> Self {
+        Self {
+            turn: Some(turn.clone()),
+            ..self.clone()
+        }
+    }
+
+    pub fn provider(&self) -> &'static str {
+        self.provider
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn span(&self, request: &CompletionRequest) -> Span {
+        let span = tracing::info_span!(
+            "chat",
+            otel.name = %format_args!("chat {}", self.name),
+            otel.kind = "client",
+            otel.status_code = Empty,
+            gen_ai.operation.name = "chat",
+            gen_ai.provider.name = self.provider,
+            gen_ai.request.model = &*self.name,
+            gen_ai.request.temperature = request.temperature,
+            gen_ai.request.max_tokens = request.max_tokens,
+            gen_ai.usage.input_tokens = Empty,
+            gen_ai.usage.output_tokens = Empty,
+            gen_ai.response.finish_reasons = Empty,
+            gen_ai.system_instructions = Empty,
+            gen_ai.input.messages = Empty,
+            gen_ai.output.messages = Empty,
+            error.type = Empty,
+            session.id = self.turn.as_deref().map(TurnContext::session_id),
+        );
+        genai::record_content(&span, "gen_ai.system_instructions", &request.preamble);
+        genai::record_content(&span, "gen_ai.input.messages", &request.chat_history);
+        span
+    }
+
+    fn record<T>(
+        &self,
+        span: &Span,
+        result: &Result<CompletionResponse<T>, CompletionError>,
+        start: Instant,
+    ) {
+        let metrics = metrics::get();
+        let labels = [self.provider, &*self.name];
+
+        metrics
+            .llm_duration
+            .with_label_values(&labels)
+            .observe(start.elapsed().as_secs_f64());
+
+        match result {
+            Ok(response) => {
+                metrics
+                    .llm_calls
+                    .with_label_values(&[self.provider, &self.name, "ok", ""])
+                    .inc();
+                metrics
+                    .llm_tokens
+                    .with_label_values(&[self.provider, &self.name, "input"])
+                    .inc_by(response.usage.input_tokens);
+                metrics
+                    .llm_tokens
+                    .with_label_values(&[self.provider, &self.name, "output"])
+                    .inc_by(response.usage.output_tokens);
+
+                if let Some(turn) = &self.turn {
+                    turn.record_usage(
+                        self.provider,
+                        &self.name,
+                        response.usage.input_tokens,
+                        response.usage.output_tokens,
+                    );
+                }
+
+                span.record("gen_ai.usage.input_tokens", response.usage.input_tokens);
+                span.record("gen_ai.usage.output_tokens", response.usage.output_tokens);
+                span.record(
+                    "gen_ai.response.finish_reasons",
+                    genai::finish_reason(&response.choice),
+                );
+                genai::record_content(span, "gen_ai.output.messages", &response.choice);
+            }
+            Err(e) => {
+                let error_code = ErrorKind::Llm(LlmKind::from_completion_error(e)).error_code();
+                metrics
+                    .llm_calls
+                    .with_label_values(&[self.provider, &self.name, "error", error_code])
+                    .inc();
+                genai::record_error(span, error_code);
+            }
+        }
     }
 }
 
@@ -26,13 +150,59 @@ impl CompletionModel for AnyModel {
         &self,
         request: CompletionRequest,
     ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
-        self.0.completion(request).await
+        let span = self.span(&request);
+        let start = Instant::now();
+        let result = self
+            .inner
+            .completion(request)
+            .instrument(span.clone())
+            .await;
+        self.record(&span, &result, start);
+        result
     }
 
     async fn stream(
         &self,
         request: CompletionRequest,
     ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
-        self.0.stream(request).await
+        self.inner.stream(request).await
     }
 }
+
+/// Agente auxiliar sin herramientas (clasificador de entrada, reescritura de
+/// salida, resúmenes). Se instancia en cada llamada con el modelo asociado al
+/// turno, igual que el orquestador, para que su consumo cuente en el turno.
+#[derive(Clone)]
+pub struct TurnAgent {
+    settings: AgentSettings,
+    model: AnyModel,
+}
+
+impl TurnAgent {
+    pub fn new(settings: AgentSettings) -> Self {
+        Self {
+            model: settings.build_model(),
+            settings,
+        }
+    }
+
+    pub async fn prompt(
+        &self,
+        turn: &Arc<TurnContext>,
+        prompt: &str,
+    ) -> Result<String, PromptError> {
+        self.settings
+            .agent_builder(self.model.with_turn(turn))
+            .build()
+            .prompt(prompt)
+            .await
+    }
+}
+
+/// Objeto JSON de la respuesta de un agente auxiliar, tolerando texto alrededor
+/// y bloques ```json. `None` si no hay uno válido para `T`.
+pub fn parse_json_reply<T: DeserializeOwned>(raw: &str) -> Option<T> {
+    let start = raw.find('{')?;
+    let end = raw.rfind('}')?;
+    serde_json::from_str(raw.get(start..=end)?).ok()
+}
@@ -1,53 +1,115 @@
-use super::specialized::{address::AddressSpecialist, damage::DamageSpecialist};
+use super::config::AgentsConfig;
+use super::genai;
+use super::specialized::{
+    address::AddressSpecialist, damage::DamageSpecialist, dummy::DummySpecialist,
+};
+use super::tools::instrumented::Instrumented;
+use super::turn::TurnContext;
 use super::AnyModel;
 use crate::api::request::FileAttachment;
+use crate::infra::audit::AuditAction;
 use crate::infra::redis::{ChatMessage, Role};
-use rig::agent::{Agent, AgentBuilder};
-use rig::client::CompletionClient;
+use rig::agent::Agent;
 use rig::completion::{Chat, Message};
 use rig::message::{
     AssistantContent, Document, DocumentMediaType, DocumentSourceKind, ImageMediaType, UserContent,
 };
-use rig::providers::gemini;
-use rig::providers::gemini::completion::gemini_api_types::{
-    AdditionalParameters, GenerationConfig,
-};
+use rig::tool::{server::ToolServer, Tool};
 use rig::OneOrMany;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{field::Empty, Instrument};
+
+/// Modelos de cada agente. Se crean una sola vez por configuración
+/// (los clientes HTTP son costosos) y se clonan en cada turno.
+struct AgentModels {
+    orchestrator: AnyModel,
+    address: AnyModel,
+    damage: AnyModel,
+    dummy: AnyModel,
+}
 
 pub struct Orchestrator {
-    pub agent: Agent<AnyModel>,
+    config: AgentsConfig,
+    models: AgentModels,
 }
 
 impl Orchestrator {
-    pub fn new() -> Self {
-        let config = crate::envs::get();
-        let gemini_client = gemini::client::Client::new(&config.gemini_api_key);
-        let gemini_model = gemini_client.completion_model("gemini-2.5-flash");
-        let gemini_model = AnyModel::new(Box::new(gemini_model));
-
-        let address_tool = AddressSpecialist::new(gemini_model.clone());
-        let damage_tool = DamageSpecialist::new(gemini_model.clone());
-
-        let gen_cfg = GenerationConfig {
-            top_k: Some(1),
-            top_p: Some(0.95),
-            candidate_count: Some(1),
-            ..Default::default()
+    /// Prepara el grafo de agentes (orquestador + especialistas) a partir de
+    /// la configuración. Se invoca al arrancar y en cada recarga en caliente.
+    pub fn new(config: &AgentsConfig) -> Self {
+        let models = AgentModels {
+            orchestrator: config.orchestrator.build_model(),
+            address: config.address.build_model(),
+            damage: config.damage.build_model(),
+            dummy: config.dummy.build_model(),
         };
-        let cfg = AdditionalParameters::default().with_config(gen_cfg);
 
-        let agent = AgentBuilder::new(gemini_model.clone())
-            .preamble(include_str!("system_prompt.md"))
-            .tool(address_tool)
-            .tool(damage_tool)
-            .additional_params(serde_json::to_value(cfg).unwrap())
-            .build();
+        Self {
+            config: config.clone(),
+            models,
+        }
+    }
+
+    /// Configuración con la que se construyó el grafo (prompts incluidos).
+    pub fn config(&self) -> &AgentsConfig {
+        &self.config
+    }
+
+    /// Construye los agentes del turno: las herramientas se ejecutan en la
+    /// tarea de su `ToolServer`, así que reciben el `TurnContext` al crearse.
+    /// Debe llamarse dentro del span `invoke_agent` para que los spans de las
+    /// herramientas cuelguen de él.
+    fn build_agent(&self, turn: &Arc<TurnContext>) -> Agent<AnyModel> {
+        let config = &self.config;
+        let settings = &config.orchestrator;
+        let specialist_timeout =
+            Duration::from_secs(crate::config::get().limits.specialist_timeout_secs);
+
+        let mut tools = ToolServer::new();
+        if settings.has_tool(AddressSpecialist::<AnyModel>::NAME) {
+            tools = tools.tool(
+                Instrumented::new(
+                    AddressSpecialist::new(
+                        self.models.address.with_turn(turn),
+                        &config.address,
+                        turn,
+                    ),
+                    turn,
+                )
+                .with_timeout(specialist_timeout)
+                .audited(AuditAction::AddressChange, self.models.address.name()),
+            );
+        }
+        if settings.has_tool(DamageSpecialist::<AnyModel>::NAME) {
+            tools = tools.tool(
+                Instrumented::new(
+                    DamageSpecialist::new(self.models.damage.with_turn(turn), &config.damage, turn),
+                    turn,
+                )
+                .with_timeout(specialist_timeout)
+                .audited(AuditAction::DamageClaim, self.models.damage.name()),
+            );
+        }
+        if settings.has_tool(DummySpecialist::<AnyModel>::NAME) {
+            tools = tools.tool(
+                Instrumented::new(
+                    DummySpecialist::new(self.models.dummy.with_turn(turn), &config.dummy, turn),
+                    turn,
+                )
+                .with_timeout(specialist_timeout),
+            );
+        }
 
-        Self { agent }
+        settings
+            .agent_builder(self.models.orchestrator.with_turn(turn))
+            .tool_server_handle(tools.run())
+            .build()
     }
 
     pub async fn chat(
         &self,
+        turn: &Arc<TurnContext>,
         prompt: &str,
         history: Vec<ChatMessage>,
         files: Vec<FileAttachment>,
@@ -73,10 +135,36 @@ impl Orchestrator {
 
         let user_message: Message = Self::build_user_content(prompt, files).into();
 
-        match self.agent.chat(user_message, rig_history).await {
-            Ok(response) => response,
+        let model = &self.models.orchestrator;
+        let span = tracing::info_span!(
+            "invoke_agent",
+            otel.name = "invoke_agent orchestrator",
+            otel.status_code = Empty,
+            gen_ai.operation.name = "invoke_agent",
+            gen_ai.agent.name = "orchestrator",
+            gen_ai.provider.name = model.provider(),
+            gen_ai.request.model = model.name(),
+            gen_ai.input.messages = Empty,
+            gen_ai.output.messages = Empty,
+            error.type = Empty,
+            session.id = turn.session_id(),
+        );
+        genai::record_content(&span, "gen_ai.input.messages", prompt);
+
+        let agent = span.in_scope(|| self.build_agent(turn));
+
+        match agent
+            .chat(user_message, rig_history)
+            .instrument(span.clone())
+            .await
+        {
+            Ok(response) => {
+                genai::record_content(&span, "gen_ai.output.messages", &response);
+                response
+            }
             Err(e) => {
-                tracing::error!("Orchestrator chat failed: {}", e);
+                genai::record_error(&span, "PromptError");
+                tracing::error!(parent: &span, "Orchestrator chat failed: {}", e);
                 "Lo siento, ocurrió un error procesando tu solicitud. Intenta de nuevo.".to_string()
             }
         }
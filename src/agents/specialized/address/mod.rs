@@ -1,8 +1,15 @@
+use crate::agents::config::AgentSettings;
+use crate::agents::tools::address_distance::AddressDistance;
+use crate::agents::tools::address_parser::AddressParser;
 use crate::agents::tools::geocoding::GeoCoding;
+use crate::agents::tools::instrumented::Instrumented;
+use crate::agents::tools::shipping_zones::ShippingZoneCalculator;
+use crate::agents::turn::TurnContext;
+use crate::infra::redaction;
 use rig::{
-    agent::{Agent, AgentBuilder},
+    agent::Agent,
     completion::{CompletionModel, Prompt},
-    tool::Tool,
+    tool::{server::ToolServer, Tool},
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -25,6 +32,10 @@ pub struct AddressChangeArgs {
     /// La nueva dirección completa incluyendo calle, número, ciudad y código postal.
     pub new_address: String,
 
+    /// La dirección registrada actualmente, si el cliente la indicó.
+    #[serde(default)]
+    pub current_address: Option<String>,
+
     /// Motivo del cambio de dirección (ej. "mudanza", "error en registro", "temporal").
     pub reason: String,
 }
@@ -47,15 +58,22 @@ pub struct AddressError(String);
 /// Especialista en cambios de dirección y logística de envíos.
 ///
 /// Este agente se encarga de:
-/// - Validar direcciones usando el servicio de geocodificación
-/// - Calcular costos adicionales por cambio de zona
+/// - Validar y normalizar direcciones, pidiendo los datos que falten
+/// - Ubicarlas con el servicio de geocodificación
+/// - Calcular costos adicionales por cambio de zona, a partir de la distancia
+///   entre la dirección actual y la nueva
 /// - Procesar solicitudes de cambio de dirección
 ///
 /// # Herramientas Disponibles
+/// - `AddressParser`: Separa la dirección en componentes y valida el código postal.
 /// - `GeoCoding`: Obtiene coordenadas y código postal de una dirección.
+/// - `AddressDistance`: Distancia y relación (misma ciudad, otra ciudad,
+///   internacional) entre la dirección actual y la nueva.
+/// - `ShippingZoneCalculator`: Recargo y tiempo de entrega del cambio de zona.
 #[derive(Clone)]
 pub struct AddressSpecialist<M: CompletionModel + Clone + Send + Sync + 'static> {
     agent: Arc<Agent<M>>,
+    turn: Arc<TurnContext>,
 }
 
 impl<M: CompletionModel + Clone + Send + Sync + 'static> AddressSpecialist<M> {
@@ -63,14 +81,34 @@ impl<M: CompletionModel + Clone + Send + Sync + 'static> AddressSpecialist<M> {
     ///
     /// # Argumentos
     /// * `model` - El modelo de lenguaje a usar (inyectado por el Orquestador).
-    pub fn new(model: M) -> Self {
-        let agent = AgentBuilder::new(model)
-            .preamble(include_str!("system_prompt.md"))
-            .tool(GeoCoding)
+    /// * `settings` - Preamble, herramientas y parámetros de generación.
+    /// * `turn` - Contexto del turno en curso, compartido con las herramientas.
+    pub fn new(model: M, settings: &AgentSettings, turn: &Arc<TurnContext>) -> Self {
+        let mut tools = ToolServer::new();
+        if settings.has_tool(AddressParser::NAME) {
+            tools = tools.tool(Instrumented::new(AddressParser::new(turn.clone()), turn));
+        }
+        if settings.has_tool(GeoCoding::NAME) {
+            tools = tools.tool(Instrumented::new(GeoCoding::new(turn.clone()), turn));
+        }
+        if settings.has_tool(AddressDistance::NAME) {
+            tools = tools.tool(Instrumented::new(AddressDistance::new(turn.clone()), turn));
+        }
+        if settings.has_tool(ShippingZoneCalculator::NAME) {
+            tools = tools.tool(Instrumented::new(
+                ShippingZoneCalculator::new(turn.clone()),
+                turn,
+            ));
+        }
+
+        let agent = settings
+            .agent_builder(model)
+            .tool_server_handle(tools.run())
             .build();
 
         Self {
             agent: Arc::new(agent),
+            turn: turn.clone(),
         }
     }
 }
@@ -99,12 +137,25 @@ impl<M: CompletionModel + Clone + Send + Sync + 'static> Tool for AddressSpecial
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        // La sesión queda indexada bajo el cliente para exportarla o borrarla
+        // a pedido suyo (`/subjects/{id}`). El modelo a veces rellena el campo
+        // con "N/A" o "desconocido": eso no es un titular.
+        if redaction::is_customer_id(&args.customer_id) {
+            self.turn.record_subject(&args.customer_id);
+        } else {
+            tracing::debug!("Address change without a valid customer id; subject not recorded");
+        }
+
         let prompt = format!(
             "Procesa la siguiente solicitud de cambio de dirección:\n\
              - Cliente: {}\n\
              - Nueva dirección: {}\n\
+             - Dirección actual: {}\n\
              - Motivo: {}",
-            args.customer_id, args.new_address, args.reason
+            args.customer_id,
+            args.new_address,
+            args.current_address.as_deref().unwrap_or("no indicada"),
+            args.reason
         );
 
         self.agent
@@ -1,12 +1,16 @@
+use crate::agents::config::AgentSettings;
 use crate::agents::tools::cost_database::CostDatabase;
+use crate::agents::tools::instrumented::Instrumented;
+use crate::agents::turn::TurnContext;
+use regex::Regex;
 use rig::{
-    agent::{Agent, AgentBuilder},
+    agent::Agent,
     completion::{CompletionModel, Prompt},
-    tool::Tool,
+    tool::{server::ToolServer, Tool},
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
 // ================================================================
 // 1. Definición de Argumentos (Input/Output)
@@ -45,6 +49,7 @@ pub struct DamageError(String);
 #[derive(Clone)]
 pub struct DamageSpecialist<M: CompletionModel + Clone + Send + Sync + 'static> {
     agent: Arc<Agent<M>>,
+    turn: Arc<TurnContext>,
 }
 
 impl<M: CompletionModel + Clone + Send + Sync + 'static> DamageSpecialist<M> {
@@ -52,14 +57,22 @@ impl<M: CompletionModel + Clone + Send + Sync + 'static> DamageSpecialist<M> {
     ///
     /// # Argumentos
     /// * `model` - El modelo de lenguaje a usar (inyectado por el Orquestador).
-    pub fn new(model: M) -> Self {
-        let agent = AgentBuilder::new(model)
-            .preamble(include_str!("system_prompt.md"))
-            .tool(CostDatabase)
+    /// * `settings` - Preamble, herramientas y parámetros de generación.
+    /// * `turn` - Contexto del turno en curso, compartido con las herramientas.
+    pub fn new(model: M, settings: &AgentSettings, turn: &Arc<TurnContext>) -> Self {
+        let mut tools = ToolServer::new();
+        if settings.has_tool(CostDatabase::NAME) {
+            tools = tools.tool(Instrumented::new(CostDatabase::new(turn.clone()), turn));
+        }
+
+        let agent = settings
+            .agent_builder(model)
+            .tool_server_handle(tools.run())
             .build();
 
         Self {
             agent: Arc::new(agent),
+            turn: turn.clone(),
         }
     }
 }
@@ -92,9 +105,37 @@ impl<M: CompletionModel + Clone + Send + Sync + 'static> Tool for DamageSpeciali
             args.item_name, args.description_of_damage
         );
 
-        self.agent
+        let response = self
+            .agent
             .prompt(&prompt)
             .await
-            .map_err(|e| DamageError(e.to_string()))
+            .map_err(|e| DamageError(e.to_string()))?;
+
+        for ticket_id in ticket_ids(&response) {
+            self.turn.record_ticket(ticket_id);
+        }
+        Ok(response)
+    }
+}
+
+/// Ids de ticket (`DMG-XXXX`, ver `system_prompt.md`) presentes en la respuesta.
+fn ticket_ids(response: &str) -> impl Iterator<Item = &str> {
+    static TICKET: OnceLock<Regex> = OnceLock::new();
+    TICKET
+        .get_or_init(|| Regex::new(r"\bDMG-\d+\b").expect("Invalid ticket pattern"))
+        .find_iter(response)
+        .map(|m| m.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ticket_ids() {
+        let response =
+            "[DAMAGE] Reporte #DMG-4821\nReferencia: DMG-4821, anterior DMG-17. XDMG-1 no.";
+        let tickets: Vec<&str> = ticket_ids(response).collect();
+        assert_eq!(tickets, ["DMG-4821", "DMG-4821", "DMG-17"]);
     }
 }
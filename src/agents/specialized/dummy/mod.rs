@@ -18,15 +18,19 @@
 //! - [ ] Definir los argumentos que necesita en `Args`
 //! - [ ] Definir la respuesta en `Output`
 //! - [ ] Editar `system_prompt.md` con las instrucciones del especialista
-//! - [ ] Agregar las tools necesarias en `AgentBuilder::new().tool(...)`
+//! - [ ] Agregar las tools necesarias en el `ToolServer` de `new()`
 //! - [ ] Registrar en `specialized/mod.rs`: `pub mod mi_especialista;`
-//! - [ ] Registrar en el Orquestador como `.tool(MiEspecialista::new(model))`
+//! - [ ] Añadir su sección de ajustes en `agents/config.rs` (`AgentsConfig`)
+//! - [ ] Registrar en el Orquestador como herramienta en `Orchestrator::build_agent` (envuelto en `Instrumented`)
 
+use crate::agents::config::AgentSettings;
+use crate::agents::tools::instrumented::Instrumented;
 use crate::agents::tools::text_reverser::TextReverser;
+use crate::agents::turn::TurnContext;
 use rig::{
-    agent::{Agent, AgentBuilder},
+    agent::Agent,
     completion::{CompletionModel, Prompt},
-    tool::Tool,
+    tool::{server::ToolServer, Tool},
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -100,6 +104,7 @@ pub struct DummyMetadata {
 
 /// Errores que pueden ocurrir durante la ejecución del especialista.
 #[derive(Debug, thiserror::Error)]
+#[allow(clippy::enum_variant_names)]
 pub enum DummyError {
     /// Error al comunicarse con el modelo de lenguaje.
     #[error("Error de comunicación con el LLM: {0}")]
@@ -110,6 +115,8 @@ pub enum DummyError {
     ValidationError(String),
 
     /// Error al ejecutar una herramienta.
+    /// (Variante de ejemplo: el dummy no la produce, pero sirve de referencia.)
+    #[allow(dead_code)]
     #[error("Error en herramienta '{tool}': {message}")]
     ToolError { tool: String, message: String },
 }
@@ -147,23 +154,34 @@ where
     ///
     /// * `model` - El modelo de lenguaje a usar. Es inyectado por el Orquestador,
     ///   lo que permite cambiar modelos sin modificar este código.
+    /// * `settings` - Preamble, herramientas habilitadas y parámetros de generación.
+    ///   Provienen de `AgentsConfig`, por lo que pueden recargarse en caliente.
+    /// * `turn` - Contexto del turno en curso. Se pasa a las herramientas que
+    ///   necesitan registrar sus resultados (ver `agents/turn.rs`).
     ///
     /// # Ejemplo
     ///
     /// ```ignore
-    /// let specialist = DummySpecialist::new(gemini_model.clone());
+    /// let specialist = DummySpecialist::new(gemini_model.clone(), &config.dummy, &turn);
     /// ```
-    pub fn new(model: M) -> Self {
-        let agent = AgentBuilder::new(model)
-            // El system prompt define la personalidad y reglas del agente.
-            // Se carga desde un archivo .md para facilitar edición.
-            .preamble(include_str!("system_prompt.md"))
-            // Registra las herramientas disponibles para este agente.
-            // Puedes encadenar múltiples `.tool()` según necesites.
-            .tool(TextReverser)
-            // Aquí podrías agregar más herramientas:
-            // .tool(OtraHerramienta)
-            // .tool(MasHerramientas)
+    pub fn new(model: M, settings: &AgentSettings, turn: &Arc<TurnContext>) -> Self {
+        // Registra las herramientas habilitadas en la configuración.
+        // Cada herramienta se agrega sólo si su `NAME` aparece en `settings.tools`,
+        // envuelta en `Instrumented` para que aparezca en las métricas.
+        let mut tools = ToolServer::new();
+        if settings.has_tool(TextReverser::NAME) {
+            tools = tools.tool(Instrumented::new(TextReverser::new(turn.clone()), turn));
+        }
+        // Aquí podrías agregar más herramientas:
+        // if settings.has_tool(OtraHerramienta::NAME) {
+        //     tools = tools.tool(Instrumented::new(OtraHerramienta::new(turn.clone()), turn));
+        // }
+
+        let agent = settings
+            // El builder ya trae el system prompt (cargado desde `system_prompt.md`)
+            // y los parámetros de generación (temperature, top_k, etc.).
+            .agent_builder(model)
+            .tool_server_handle(tools.run())
             .build();
 
         Self {
@@ -254,20 +272,22 @@ where
         // 2. Construir el prompt para el LLM interno
         let prompt = Self::build_prompt(&args);
 
-        // 3. Ejecutar el agente
+        // 3. Ejecutar el agente. `extended_details()` devuelve además el
+        //    consumo de tokens de todas las llamadas al modelo del especialista.
         let response = self
             .agent
             .prompt(&prompt)
+            .extended_details()
             .await
             .map_err(|e| DummyError::LlmError(e.to_string()))?;
 
         // 4. Construir y devolver la respuesta
         Ok(DummyOutput {
-            reply: response,
+            reply: response.output,
             success: true,
             metadata: Some(DummyMetadata {
                 tools_used: 0,
-                tokens_used: None,
+                tokens_used: u32::try_from(response.total_usage.total_tokens).ok(),
             }),
         })
     }
@@ -280,6 +300,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agents::AnyModel;
 
     #[test]
     fn test_validate_empty_message() {
@@ -288,7 +309,7 @@ mod tests {
             detail_level: "normal".to_string(),
         };
 
-        let result = DummySpecialist::<()>::validate_args(&args);
+        let result = DummySpecialist::<AnyModel>::validate_args(&args);
         assert!(result.is_err());
     }
 
@@ -299,7 +320,7 @@ mod tests {
             detail_level: "invalid".to_string(),
         };
 
-        let result = DummySpecialist::<()>::validate_args(&args);
+        let result = DummySpecialist::<AnyModel>::validate_args(&args);
         assert!(result.is_err());
     }
 
@@ -310,7 +331,7 @@ mod tests {
             detail_level: "brief".to_string(),
         };
 
-        let result = DummySpecialist::<()>::validate_args(&args);
+        let result = DummySpecialist::<AnyModel>::validate_args(&args);
         assert!(result.is_ok());
     }
 
@@ -321,7 +342,7 @@ mod tests {
             detail_level: "detailed".to_string(),
         };
 
-        let prompt = DummySpecialist::<()>::build_prompt(&args);
+        let prompt = DummySpecialist::<AnyModel>::build_prompt(&args);
         assert!(prompt.contains("detailed"));
         assert!(prompt.contains("Test message"));
     }
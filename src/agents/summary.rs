@@ -0,0 +1,183 @@
+//! # Títulos y resúmenes de sesiones
+//!
+//! Un modelo económico (`SUMMARY_MODEL`) genera en segundo plano el título,
+//! un resumen, la intención y el sentimiento de cada sesión:
+//!
+//! - al completar `SUMMARY_AFTER_TURNS` turnos, y
+//! - al cerrarla (`PATCH /sessions/{id}` con `status: closed`).
+//!
+//! Se resume el historial tal como está almacenado: con
+//! `PII_REDACT_HISTORY` activo el modelo sólo ve los tokens de PII. Una falla
+//! queda en los logs y la sesión sigue sin resumen; nunca afecta al turno.
+//! El consumo del modelo se carga al caller de la sesión.
+
+use super::config::AgentSettings;
+use super::turn::TurnContext;
+use super::{parse_json_reply, TurnAgent};
+use crate::infra::redis::sessions::{Intent, Sentiment, SessionSummary};
+use crate::infra::redis::{unix_now, ChatMessage, RedisProvider, Role};
+use crate::infra::shutdown::Lifecycle;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+pub struct Summarizer {
+    agent: TurnAgent,
+    after_turns: u64,
+}
+
+/// Respuesta esperada del modelo (ver `summary_prompt.md`).
+#[derive(Deserialize, Debug)]
+struct SummaryResponse {
+    title: String,
+    summary: String,
+    intent: Intent,
+    sentiment: Sentiment,
+}
+
+impl Summarizer {
+    /// `None` si `SUMMARY_MODEL` está vacío.
+    pub fn from_config() -> Option<Self> {
+        let config = &crate::config::get().agents;
+        if config.summary_model.is_empty() {
+            return None;
+        }
+
+        let settings = AgentSettings {
+            model: config.summary_model.clone(),
+            temperature: Some(0.0),
+            preamble: include_str!("summary_prompt.md").to_string(),
+            ..Default::default()
+        };
+        Some(Self {
+            agent: TurnAgent::new(settings),
+            after_turns: config.summary_after_turns,
+        })
+    }
+
+    /// El turno que llevó la sesión de `before` a `after` mensajes completó
+    /// los turnos configurados (cada turno son dos mensajes).
+    pub fn is_due(&self, before: u64, after: u64) -> bool {
+        crosses_threshold(self.after_turns, before, after)
+    }
+
+    /// Resume la sesión en una tarea de fondo que el drain espera.
+    pub fn spawn(
+        self: &Arc<Self>,
+        lifecycle: &Lifecycle,
+        redis: RedisProvider,
+        session_id: String,
+    ) {
+        let summarizer = self.clone();
+        lifecycle.spawn(async move {
+            let timeout = Duration::from_secs(crate::config::get().limits.specialist_timeout_secs);
+            let result = tokio::time::timeout(timeout, summarizer.summarize(&redis, &session_id))
+                .await
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("timed out after {:?}", timeout)));
+
+            match result {
+                Ok(()) => tracing::info!(session_id, "Session summary generated"),
+                Err(e) => tracing::warn!(session_id, "Failed to summarize session: {:#}", e),
+            }
+        });
+    }
+
+    async fn summarize(&self, redis: &RedisProvider, session_id: &str) -> Result<()> {
+        // La sesión pudo expirar mientras la tarea esperaba.
+        let Some(meta) = redis.get_session(session_id).await? else {
+            return Ok(());
+        };
+        let history = redis.get_stored_history(session_id).await?;
+        if history.is_empty() {
+            return Ok(());
+        }
+
+        let turn = TurnContext::new(session_id);
+        let result = self.agent.prompt(&turn, &transcript(&history)).await;
+        // El consumo se registra aunque la respuesta no sirva.
+        let request_id = Uuid::new_v4().to_string();
+        if let Err(e) = redis
+            .record_turn_usage(&request_id, session_id, &meta.caller, &turn.usage())
+            .await
+        {
+            tracing::warn!(session_id, "Failed to record summary token usage: {}", e);
+        }
+        let raw = result.context("summary model failed")?;
+        let response =
+            parse_json_reply::<SummaryResponse>(&raw).context("unreadable summary response")?;
+
+        let summary = SessionSummary {
+            text: response.summary.trim().to_string(),
+            intent: response.intent,
+            sentiment: response.sentiment,
+            message_count: meta.message_count,
+            generated_at: unix_now(),
+        };
+        redis
+            .save_summary(session_id, response.title.trim(), &summary)
+            .await?;
+        Ok(())
+    }
+}
+
+fn crosses_threshold(after_turns: u64, before: u64, after: u64) -> bool {
+    let threshold = after_turns * 2;
+    after_turns > 0 && before < threshold && after >= threshold
+}
+
+fn transcript(history: &[ChatMessage]) -> String {
+    history
+        .iter()
+        .filter_map(|message| {
+            let speaker = match message.role {
+                Role::User => "Cliente",
+                Role::Assistant => "Asistente",
+                Role::System => return None,
+            };
+            Some(format!("{}: {}", speaker, message.content))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_summary_response() {
+        let raw = "```json\n{\"title\": \"Daño en envío #99\", \"summary\": \"Caja rota.\", \"intent\": \"damage\", \"sentiment\": \"negative\"}\n```";
+        let response: SummaryResponse = parse_json_reply(raw).unwrap();
+        assert_eq!(response.title, "Daño en envío #99");
+        assert_eq!(response.intent, Intent::Damage);
+        assert_eq!(response.sentiment, Sentiment::Negative);
+
+        assert!(
+            parse_json_reply::<SummaryResponse>("{\"title\": \"x\", \"intent\": \"refund\"}")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_summary_due_once_after_turns() {
+        assert!(crosses_threshold(3, 4, 6));
+        assert!(!crosses_threshold(3, 2, 4));
+        assert!(!crosses_threshold(3, 6, 8));
+        assert!(!crosses_threshold(0, 0, 2));
+    }
+
+    #[test]
+    fn test_transcript_skips_system_messages() {
+        let history = vec![
+            ChatMessage::new(Role::System, "instrucciones"),
+            ChatMessage::new(Role::User, "hola"),
+            ChatMessage::new(Role::Assistant, "¿en qué te ayudo?"),
+        ];
+        assert_eq!(
+            transcript(&history),
+            "Cliente: hola\n\nAsistente: ¿en qué te ayudo?"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Compara la dirección actual del cliente con la nueva.
+//!
+//! Ubica ambas con el geocodificador configurado y devuelve la distancia y
+//! la relación entre ellas (`same_city`, `other_city`, `international`), que
+//! deciden las reglas de costo del especialista de direcciones en lugar de
+//! dejarlas al criterio del modelo.
+
+use super::gazetteer::same_name;
+use super::geocoding::{self, GeoError, GeoOutput};
+use crate::agents::turn::TurnContext;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Sin ciudad conocida en alguna de las dos, se consideran la misma ciudad
+/// si están a menos de esta distancia.
+const SAME_CITY_RADIUS_KM: f64 = 20.0;
+
+/// Por debajo de esta confianza la ubicación se confirma con el cliente.
+const LOW_CONFIDENCE: f64 = 0.6;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressRelation {
+    SameCity,
+    OtherCity,
+    International,
+}
+
+impl AddressRelation {
+    pub fn between(current: &GeoOutput, new: &GeoOutput) -> Self {
+        if current.country != new.country {
+            return Self::International;
+        }
+        let same_city = match (&current.city, &new.city) {
+            (Some(a), Some(b)) => same_name(a, b),
+            _ => current.point().distance_km(&new.point()) <= SAME_CITY_RADIUS_KM,
+        };
+        if same_city {
+            Self::SameCity
+        } else {
+            Self::OtherCity
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct DistanceArgs {
+    /// Dirección registrada actualmente.
+    pub current_address: String,
+    /// Dirección a la que se quiere cambiar.
+    pub new_address: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DistanceError {
+    #[error("Dirección actual: {0}")]
+    Current(GeoError),
+
+    #[error("Dirección nueva: {0}")]
+    New(GeoError),
+}
+
+#[derive(Serialize, Debug)]
+pub struct DistanceOutput {
+    pub current: GeoOutput,
+    pub new: GeoOutput,
+    pub distance_km: f64,
+    pub relation: AddressRelation,
+    /// Alguna de las ubicaciones es aproximada: confirmar la dirección con el
+    /// cliente antes de aplicar las reglas de costo.
+    pub needs_confirmation: bool,
+}
+
+impl DistanceOutput {
+    pub fn new(current: GeoOutput, new: GeoOutput) -> Self {
+        let distance_km = current.point().distance_km(&new.point());
+        Self {
+            distance_km: (distance_km * 10.0).round() / 10.0,
+            relation: AddressRelation::between(&current, &new),
+            needs_confirmation: current.confidence.min(new.confidence) < LOW_CONFIDENCE,
+            current,
+            new,
+        }
+    }
+}
+
+pub struct AddressDistance {
+    turn: Arc<TurnContext>,
+}
+
+impl AddressDistance {
+    pub fn new(turn: Arc<TurnContext>) -> Self {
+        Self { turn }
+    }
+}
+
+impl rig::tool::Tool for AddressDistance {
+    const NAME: &'static str = "address_distance";
+
+    type Error = DistanceError;
+    type Args = DistanceArgs;
+    type Output = DistanceOutput;
+
+    async fn definition(&self, _prompt: String) -> rig::completion::ToolDefinition {
+        rig::completion::ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Calcula la distancia entre la dirección actual y la nueva e indica si \
+                          es la misma ciudad, otra ciudad o un cambio internacional."
+                .to_string(),
+            parameters: serde_json::to_value(schemars::schema_for!(DistanceArgs)).unwrap(),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let (current, new) = tokio::join!(
+            geocoding::locate(&args.current_address),
+            geocoding::locate(&args.new_address)
+        );
+        let output = DistanceOutput::new(
+            current.map_err(DistanceError::Current)?,
+            new.map_err(DistanceError::New)?,
+        );
+        self.turn.record_tool(Self::NAME, &output);
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn compare(current: &str, new: &str) -> DistanceOutput {
+        DistanceOutput::new(
+            geocoding::locate(current).await.unwrap(),
+            geocoding::locate(new).await.unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_relation_from_gazetteer() {
+        let same = compare(
+            "Orizaba 10, Roma Norte, CDMX, C.P. 06700",
+            "Av. Juárez 50, Centro, C.P. 06000",
+        )
+        .await;
+        assert_eq!(same.relation, AddressRelation::SameCity);
+        assert!(same.distance_km > 2.0 && same.distance_km < 5.0);
+        assert!(!same.needs_confirmation);
+
+        let other = compare(
+            "Orizaba 10, Roma Norte, CDMX, C.P. 06700",
+            "Av. Vallarta 100, Centro, Guadalajra",
+        )
+        .await;
+        assert_eq!(other.relation, AddressRelation::OtherCity);
+        assert!(other.needs_confirmation);
+
+        let abroad = compare(
+            "Orizaba 10, Roma Norte, CDMX, C.P. 06700",
+            "Congress Ave 100, Downtown, Austin, Texas, 78701, USA",
+        )
+        .await;
+        assert_eq!(abroad.relation, AddressRelation::International);
+        assert!(abroad.distance_km > 1_000.0);
+    }
+}
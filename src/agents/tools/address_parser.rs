@@ -0,0 +1,626 @@
+//! Análisis de direcciones escritas a mano, sin red.
+//!
+//! Separa una dirección en el formato habitual en México (calle y número
+//! primero, luego colonia, municipio, ciudad, estado, código postal y país,
+//! separados por comas), expande abreviaturas (`Av.`, `Col.`, `CDMX`...),
+//! completa ciudad y estado a partir del código postal y valida este contra
+//! el gazetteer (`gazetteer.rs`). Los componentes que siguen
+//! faltando se devuelven en `missing` para que el especialista los pida.
+
+use super::fuzzy::fold;
+use super::gazetteer::{self, same_name};
+use crate::agents::turn::TurnContext;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+
+/// País que se asume cuando la dirección no lo indica.
+pub const DEFAULT_COUNTRY: &str = "MX";
+
+/// Código, nombre y formas en que suele escribirse cada país.
+const COUNTRIES: &[(&str, &str, &[&str])] = &[
+    (
+        "MX",
+        "México",
+        &["mexico", "mx", "mex", "republica mexicana"],
+    ),
+    (
+        "US",
+        "Estados Unidos",
+        &[
+            "estados unidos",
+            "eua",
+            "eeuu",
+            "usa",
+            "us",
+            "united states",
+        ],
+    ),
+    ("CA", "Canadá", &["canada", "ca"]),
+    ("GT", "Guatemala", &["guatemala", "gt"]),
+    ("ES", "España", &["espana", "es", "spain"]),
+    ("CO", "Colombia", &["colombia", "co"]),
+    ("AR", "Argentina", &["argentina", "ar"]),
+    ("CL", "Chile", &["chile", "cl"]),
+];
+
+/// Estados de México y sus abreviaturas (sin acentos ni puntos). `Col` no se
+/// incluye para Colima porque se confunde con colonia.
+const STATES: &[(&str, &[&str])] = &[
+    ("Aguascalientes", &["aguascalientes", "ags"]),
+    ("Baja California", &["baja california", "bc"]),
+    ("Baja California Sur", &["baja california sur", "bcs"]),
+    ("Campeche", &["campeche", "camp"]),
+    ("Chiapas", &["chiapas", "chis"]),
+    ("Chihuahua", &["chihuahua", "chih"]),
+    (
+        "Ciudad de México",
+        &[
+            "ciudad de mexico",
+            "cdmx",
+            "df",
+            "distrito federal",
+            "mexico df",
+        ],
+    ),
+    ("Coahuila", &["coahuila", "coah"]),
+    ("Colima", &["colima"]),
+    ("Durango", &["durango", "dgo"]),
+    (
+        "Estado de México",
+        &["estado de mexico", "edomex", "edo mex", "edo de mexico"],
+    ),
+    ("Guanajuato", &["guanajuato", "gto"]),
+    ("Guerrero", &["guerrero", "gro"]),
+    ("Hidalgo", &["hidalgo", "hgo"]),
+    ("Jalisco", &["jalisco", "jal"]),
+    ("Michoacán", &["michoacan", "mich"]),
+    ("Morelos", &["morelos", "mor"]),
+    ("Nayarit", &["nayarit", "nay"]),
+    ("Nuevo León", &["nuevo leon", "nl"]),
+    ("Oaxaca", &["oaxaca", "oax"]),
+    ("Puebla", &["puebla", "pue"]),
+    ("Querétaro", &["queretaro", "qro"]),
+    ("Quintana Roo", &["quintana roo", "q roo", "qroo"]),
+    ("San Luis Potosí", &["san luis potosi", "slp"]),
+    ("Sinaloa", &["sinaloa", "sin"]),
+    ("Sonora", &["sonora", "son"]),
+    ("Tabasco", &["tabasco", "tab"]),
+    ("Tamaulipas", &["tamaulipas", "tamps"]),
+    ("Tlaxcala", &["tlaxcala", "tlax"]),
+    ("Veracruz", &["veracruz", "ver"]),
+    ("Yucatán", &["yucatan", "yuc"]),
+    ("Zacatecas", &["zacatecas", "zac"]),
+];
+
+/// Abreviaturas del tipo de vialidad (sólo al inicio de la calle).
+const STREET_TYPES: &[(&str, &str)] = &[
+    ("av", "Avenida"),
+    ("ave", "Avenida"),
+    ("avda", "Avenida"),
+    ("blvd", "Boulevard"),
+    ("blv", "Boulevard"),
+    ("c", "Calle"),
+    ("cll", "Calle"),
+    ("calz", "Calzada"),
+    ("carr", "Carretera"),
+    ("cda", "Cerrada"),
+    ("circ", "Circuito"),
+    ("priv", "Privada"),
+    ("prol", "Prolongación"),
+];
+
+/// Abreviaturas frecuentes en cualquier posición.
+const WORDS: &[(&str, &str)] = &[
+    ("gral", "General"),
+    ("sta", "Santa"),
+    ("sto", "Santo"),
+    ("lic", "Licenciado"),
+    ("ing", "Ingeniero"),
+    ("dr", "Doctor"),
+    ("pte", "Poniente"),
+    ("ote", "Oriente"),
+    ("nte", "Norte"),
+    ("fracc", "Fraccionamiento"),
+];
+
+/// Prefijos de la colonia que se descartan (el tipo no aporta al envío).
+const NEIGHBORHOOD_PREFIXES: &[&str] = &["colonia", "col"];
+
+/// Tipos de asentamiento que se conservan como parte del nombre.
+const SETTLEMENT_TYPES: &[&str] = &[
+    "fraccionamiento",
+    "fracc",
+    "barrio",
+    "residencial",
+    "unidad habitacional",
+    "ejido",
+];
+
+const MUNICIPALITY_PREFIXES: &[&str] = &["alcaldia", "alc", "municipio", "mpio", "delegacion"];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressComponent {
+    Street,
+    Number,
+    Neighborhood,
+    City,
+    State,
+    PostalCode,
+    Country,
+}
+
+/// Componentes de una dirección, ya normalizados.
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct ParsedAddress {
+    pub street: Option<String>,
+    /// Número exterior (`S/N` si la dirección no tiene).
+    pub number: Option<String>,
+    pub interior: Option<String>,
+    pub neighborhood: Option<String>,
+    /// Alcaldía o municipio.
+    pub municipality: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+    /// ISO 3166-1 alfa-2.
+    pub country: Option<String>,
+}
+
+impl ParsedAddress {
+    pub fn country(&self) -> &str {
+        self.country.as_deref().unwrap_or(DEFAULT_COUNTRY)
+    }
+
+    fn has(&self, component: AddressComponent) -> bool {
+        match component {
+            AddressComponent::Street => self.street.is_some(),
+            AddressComponent::Number => self.number.is_some(),
+            AddressComponent::Neighborhood => self.neighborhood.is_some(),
+            AddressComponent::City => self.city.is_some(),
+            AddressComponent::State => self.state.is_some(),
+            AddressComponent::PostalCode => self.postal_code.is_some(),
+            AddressComponent::Country => self.country.is_some(),
+        }
+    }
+
+    /// Componentes necesarios para entregar en el país de la dirección.
+    fn required(&self) -> &'static [AddressComponent] {
+        use AddressComponent::*;
+        if self.country() == "MX" {
+            &[Street, Number, Neighborhood, City, State, PostalCode]
+        } else {
+            &[Street, Number, City, State, PostalCode]
+        }
+    }
+
+    /// La dirección en una línea, con los componentes en orden.
+    pub fn to_line(&self) -> String {
+        let street = match (&self.street, &self.number, &self.interior) {
+            (Some(s), Some(n), Some(i)) => Some(format!("{} {} Int. {}", s, n, i)),
+            (Some(s), Some(n), None) => Some(format!("{} {}", s, n)),
+            (street, _, _) => street.clone(),
+        };
+        let country = COUNTRIES
+            .iter()
+            .find(|(code, _, _)| *code == self.country())
+            .map(|(_, name, _)| name.to_string())
+            .or_else(|| self.country.clone());
+
+        [
+            street,
+            self.neighborhood.clone(),
+            self.municipality.clone(),
+            self.city.clone(),
+            self.state.clone().filter(|s| Some(s) != self.city.as_ref()),
+            self.postal_code.as_ref().map(|cp| format!("C.P. {}", cp)),
+            country,
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+/// Resultado de validar el código postal contra el dataset.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PostalCodeCheck {
+    /// Existe y coincide con la ciudad y el estado indicados.
+    Valid,
+    /// Existe, pero corresponde a otra ciudad o estado.
+    Mismatch,
+    /// No está en el dataset (puede existir: el dataset no es exhaustivo).
+    Unknown,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AddressCheck {
+    pub components: ParsedAddress,
+    /// Dirección normalizada en una línea.
+    pub normalized: String,
+    /// Componentes requeridos que no se encontraron.
+    pub missing: Vec<AddressComponent>,
+    /// Componentes completados a partir del código postal o del país por defecto.
+    pub inferred: Vec<AddressComponent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub postal_code_check: Option<PostalCodeCheck>,
+}
+
+// ================================================================
+// Análisis
+// ================================================================
+
+fn postal_code_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)\bc\.?\s?p\.?\s*:?\s*(\d{4,5})\b").unwrap())
+}
+
+fn bare_postal_code_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\b(\d{5})\b").unwrap())
+}
+
+fn street_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"(?i)^(?P<street>.+?)\s+(?:(?:#|no\.?|n[úu]m\.?|n[úu]mero)\s*)?(?P<number>\d+[a-z]?(?:-\d+)?|s/?n)(?:\s+(?:int\.?|interior|depto\.?|dpto\.?|departamento)\s*(?P<interior>[\w-]+))?$",
+        )
+        .unwrap()
+    })
+}
+
+/// Separa la dirección en componentes. No consulta el dataset (ver `check`).
+pub fn parse(text: &str) -> ParsedAddress {
+    let mut segments: Vec<String> = text
+        .split([',', ';', '\n'])
+        .map(|s| s.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|s| !s.is_empty())
+        .collect();
+    let mut address = ParsedAddress::default();
+    if segments.is_empty() {
+        return address;
+    }
+
+    // El código postal: primero el marcado con "C.P.", luego un número de
+    // cinco dígitos fuera de la calle (que lleva el número exterior).
+    for segment in segments.iter_mut() {
+        if let Some(m) = postal_code_re().captures(segment) {
+            address.postal_code = Some(m[1].to_string());
+            *segment = segment.replace(&m[0], "").trim().to_string();
+            break;
+        }
+    }
+    if address.postal_code.is_none() {
+        for segment in segments.iter_mut().skip(1).rev() {
+            if let Some(m) = bare_postal_code_re().captures(segment) {
+                address.postal_code = Some(m[1].to_string());
+                *segment = segment.replace(&m[0], "").trim().to_string();
+                break;
+            }
+        }
+    }
+
+    let first = segments.remove(0);
+    match street_re().captures(&first) {
+        Some(m) => {
+            address.street = Some(expand(&m["street"], true));
+            let number = &m["number"];
+            address.number = Some(if fold(number).replace('/', "") == "sn" {
+                "S/N".to_string()
+            } else {
+                number.to_uppercase()
+            });
+            address.interior = m.name("interior").map(|i| i.as_str().to_uppercase());
+        }
+        None if !first.is_empty() => address.street = Some(expand(&first, true)),
+        None => {}
+    }
+
+    // Del final hacia el inicio: país, estado y código postal van al final,
+    // así que en "Puebla, Pue." el primer "Puebla" queda como ciudad.
+    let mut unclassified = Vec::new();
+    for segment in segments.iter().rev().filter(|s| !s.is_empty()) {
+        let folded = fold(segment);
+        if address.country.is_none() {
+            if let Some((code, _, _)) = COUNTRIES.iter().find(|(_, _, a)| a.contains(&&*folded)) {
+                address.country = Some(code.to_string());
+                continue;
+            }
+        }
+        if address.state.is_none() {
+            if let Some((state, _)) = STATES.iter().find(|(_, a)| a.contains(&&*folded)) {
+                address.state = Some(state.to_string());
+                continue;
+            }
+        }
+        if address.neighborhood.is_none() {
+            if let Some(name) = strip_prefix(segment, NEIGHBORHOOD_PREFIXES) {
+                address.neighborhood = Some(expand(name, false));
+                continue;
+            }
+            if SETTLEMENT_TYPES
+                .iter()
+                .any(|t| starts_with_word(&folded, t))
+            {
+                address.neighborhood = Some(expand(segment, false));
+                continue;
+            }
+        }
+        if address.municipality.is_none() {
+            if let Some(name) = strip_prefix(segment, MUNICIPALITY_PREFIXES) {
+                address.municipality = Some(expand(name, false));
+                continue;
+            }
+        }
+        unclassified.push(expand(segment, false));
+    }
+    unclassified.reverse();
+
+    // Sin prefijos, el orden habitual es colonia, municipio, ciudad.
+    let mut unclassified = unclassified.into_iter();
+    if let Some(city) = unclassified.next_back() {
+        address.city = Some(city);
+    }
+    if address.neighborhood.is_none() {
+        address.neighborhood = unclassified.next();
+    }
+    if address.municipality.is_none() {
+        address.municipality = unclassified.next();
+    }
+
+    // En la capital la "ciudad" que se escribe suele ser la alcaldía.
+    if address.state.as_deref() == Some("Ciudad de México") {
+        let city = address.city.replace("Ciudad de México".to_string());
+        if city
+            .as_deref()
+            .is_some_and(|c| fold(c) != "ciudad de mexico")
+        {
+            address.municipality = address.municipality.or(city);
+        }
+    }
+
+    if let Some(code) = &address.postal_code {
+        if address.country() == "MX" && code.len() == 4 {
+            address.postal_code = Some(format!("0{}", code));
+        }
+    }
+
+    address
+}
+
+/// Analiza la dirección y la valida contra el gazetteer.
+pub fn check(text: &str) -> AddressCheck {
+    let gazetteer = gazetteer::get();
+    let mut components = parse(text);
+    let mut inferred = Vec::new();
+
+    let postal_code_check = components.postal_code.clone().map(|code| {
+        let Some(entry) = gazetteer.lookup(components.country(), &code) else {
+            return PostalCodeCheck::Unknown;
+        };
+        let city_matches = components
+            .city
+            .as_ref()
+            .is_none_or(|c| same_name(c, &entry.city) || same_name(c, &entry.place));
+        let state_matches = components
+            .state
+            .as_ref()
+            .is_none_or(|s| entry.state.is_empty() || same_name(s, &entry.state));
+
+        if components.city.is_none() {
+            components.city = Some(entry.city.clone());
+            inferred.push(AddressComponent::City);
+        }
+        if components.state.is_none() && !entry.state.is_empty() {
+            components.state = Some(entry.state.clone());
+            inferred.push(AddressComponent::State);
+        }
+        if city_matches && state_matches {
+            PostalCodeCheck::Valid
+        } else {
+            PostalCodeCheck::Mismatch
+        }
+    });
+
+    // Una ciudad escrita a mano se reemplaza por su nombre en el gazetteer
+    // (corrige acentos y errores de tipeo) y aporta el estado si falta.
+    if !inferred.contains(&AddressComponent::City) {
+        if let Some(city) = components
+            .city
+            .as_ref()
+            .and_then(|c| gazetteer.find_city(components.country(), c))
+        {
+            components.city = Some(city.city.to_string());
+            if components.state.is_none() && !city.state.is_empty() {
+                components.state = Some(city.state.to_string());
+                inferred.push(AddressComponent::State);
+            }
+        }
+    }
+
+    if components.country.is_none() {
+        components.country = Some(DEFAULT_COUNTRY.to_string());
+        inferred.push(AddressComponent::Country);
+    }
+    let missing = components
+        .required()
+        .iter()
+        .copied()
+        .filter(|c| !components.has(*c))
+        .collect();
+
+    AddressCheck {
+        normalized: components.to_line(),
+        components,
+        missing,
+        inferred,
+        postal_code_check,
+    }
+}
+
+/// Expande las abreviaturas conocidas; `street` habilita las de vialidad
+/// en la primera palabra.
+fn expand(text: &str, street: bool) -> String {
+    text.split_whitespace()
+        .enumerate()
+        .map(|(i, word)| {
+            let key = fold(word);
+            let street_type = STREET_TYPES.iter().filter(|_| street && i == 0);
+            street_type
+                .chain(WORDS)
+                .find(|(abbr, _)| *abbr == key)
+                .map(|(_, full)| full.to_string())
+                .unwrap_or_else(|| word.to_string())
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// El texto sin el prefijo (ej. "Col. Roma" → "Roma"), si lo tiene.
+fn strip_prefix<'a>(segment: &'a str, prefixes: &[&str]) -> Option<&'a str> {
+    let (first, rest) = segment.split_once(|c: char| c.is_whitespace() || c == '.')?;
+    let rest = rest.trim_start_matches(['.', ' ']);
+    (prefixes.contains(&fold(first).as_str()) && !rest.is_empty()).then_some(rest)
+}
+
+fn starts_with_word(folded: &str, word: &str) -> bool {
+    folded
+        .strip_prefix(word)
+        .is_some_and(|rest| rest.starts_with(' '))
+}
+
+// ================================================================
+// Herramienta
+// ================================================================
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct AddressParserArgs {
+    /// Dirección tal como la escribió el cliente.
+    pub address: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("La dirección está vacía")]
+pub struct AddressParserError;
+
+pub struct AddressParser {
+    turn: Arc<TurnContext>,
+}
+
+impl AddressParser {
+    pub fn new(turn: Arc<TurnContext>) -> Self {
+        Self { turn }
+    }
+}
+
+impl rig::tool::Tool for AddressParser {
+    const NAME: &'static str = "address_parser";
+
+    type Error = AddressParserError;
+    type Args = AddressParserArgs;
+    type Output = AddressCheck;
+
+    async fn definition(&self, _prompt: String) -> rig::completion::ToolDefinition {
+        rig::completion::ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Separa una dirección en calle, número, colonia, ciudad, estado, código \
+                          postal y país; normaliza abreviaturas, indica los datos que faltan y \
+                          valida el código postal."
+                .to_string(),
+            parameters: serde_json::to_value(schemars::schema_for!(AddressParserArgs)).unwrap(),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if args.address.trim().is_empty() {
+            return Err(AddressParserError);
+        }
+        let output = check(&args.address);
+        self.turn.record_tool(Self::NAME, &output);
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_address() {
+        let address = parse(
+            "Av. Insurgentes Sur #1234 Int. 5b, Col. Del Valle, Benito Juárez, CDMX, C.P. 03100",
+        );
+        assert_eq!(address.street.as_deref(), Some("Avenida Insurgentes Sur"));
+        assert_eq!(address.number.as_deref(), Some("1234"));
+        assert_eq!(address.interior.as_deref(), Some("5B"));
+        assert_eq!(address.neighborhood.as_deref(), Some("Del Valle"));
+        assert_eq!(address.municipality.as_deref(), Some("Benito Juárez"));
+        assert_eq!(address.city.as_deref(), Some("Ciudad de México"));
+        assert_eq!(address.state.as_deref(), Some("Ciudad de México"));
+        assert_eq!(address.postal_code.as_deref(), Some("03100"));
+    }
+
+    #[test]
+    fn test_parse_without_prefixes() {
+        let address = parse("Calle 5 de Mayo s/n, Centro, Puebla, Pue., 72000, México");
+        assert_eq!(address.street.as_deref(), Some("Calle 5 de Mayo"));
+        assert_eq!(address.number.as_deref(), Some("S/N"));
+        assert_eq!(address.neighborhood.as_deref(), Some("Centro"));
+        assert_eq!(address.city.as_deref(), Some("Puebla"));
+        assert_eq!(address.state.as_deref(), Some("Puebla"));
+        assert_eq!(address.postal_code.as_deref(), Some("72000"));
+        assert_eq!(address.country.as_deref(), Some("MX"));
+    }
+
+    #[test]
+    fn test_check_infers_from_postal_code() {
+        let result = check("Priv. Gral. Anaya 12, Col. Roma Norte, CP 6700");
+        assert_eq!(result.postal_code_check, Some(PostalCodeCheck::Valid));
+        assert_eq!(
+            result.normalized,
+            "Privada General Anaya 12, Roma Norte, Ciudad de México, C.P. 06700, México"
+        );
+        assert!(result.missing.is_empty());
+        assert_eq!(
+            result.inferred,
+            [
+                AddressComponent::City,
+                AddressComponent::State,
+                AddressComponent::Country
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_flags_missing_and_mismatch() {
+        let result = check("Reforma, Monterrey, N.L.");
+        assert_eq!(
+            result.missing,
+            [
+                AddressComponent::Number,
+                AddressComponent::Neighborhood,
+                AddressComponent::PostalCode
+            ]
+        );
+        assert_eq!(result.postal_code_check, None);
+
+        let result = check("Juárez 100, Centro, Guadalajara, Jal., C.P. 64000");
+        assert_eq!(result.postal_code_check, Some(PostalCodeCheck::Mismatch));
+        let result = check("Juárez 100, Centro, Guadalajara, Jal., C.P. 44999");
+        assert_eq!(result.postal_code_check, Some(PostalCodeCheck::Unknown));
+    }
+
+    #[test]
+    fn test_check_corrects_city_from_gazetteer() {
+        let result = check("Hidalgo 5, Centro, Guadalajra");
+        assert_eq!(result.components.city.as_deref(), Some("Guadalajara"));
+        assert_eq!(result.components.state.as_deref(), Some("Jalisco"));
+        assert_eq!(result.missing, [AddressComponent::PostalCode]);
+    }
+}
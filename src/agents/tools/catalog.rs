@@ -0,0 +1,371 @@
+//! # Catálogo de artículos
+//!
+//! Precios de reparación y reemplazo que cotiza `cost_database`.
+//!
+//! Al arrancar se carga `CATALOG_FILE` o, si no se indica, el CSV embebido
+//! (`catalog.csv`); luego lo reemplaza la última importación guardada en Redis
+//! (`POST /admin/catalog`, ver `infra/redis/catalog.rs`), que cada instancia
+//! revisa cada `CATALOG_REFRESH_INTERVAL` segundos.
+//!
+//! El CSV lleva encabezado y columnas separadas por comas (sin comillas), en
+//! cualquier orden:
+//!
+//! - obligatorias: `sku`, `name`, `replacement_price`, `currency` (ISO 4217)
+//!   y `stock`;
+//! - opcionales: `aliases` (separados por `|`) y `repair_price` (vacío = el
+//!   artículo no se repara).
+//!
+//! En JSON, un arreglo de `CatalogItem`.
+//!
+//! Los artículos se buscan por nombre o alias sin acentos ni mayúsculas,
+//! tolerando errores de tipeo y palabras de más (ver `Catalog::search`).
+
+use super::csv;
+use super::fuzzy::{fold, similarity};
+use crate::infra::hash::stable_hash;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, OnceLock, RwLock};
+
+const BUNDLED: &str = include_str!("catalog.csv");
+
+/// Puntaje mínimo (0–1) para proponer un artículo.
+pub const MIN_SCORE: f64 = 0.5;
+
+/// Candidatos devueltos por búsqueda.
+pub const MAX_CANDIDATES: usize = 3;
+
+/// Similitud mínima para que dos palabras se consideren la misma.
+const MIN_WORD_SIMILARITY: f64 = 0.8;
+
+/// Palabras que no distinguen artículos.
+const STOPWORDS: &[&str] = &[
+    "de", "del", "la", "el", "las", "los", "un", "una", "con", "para", "por", "mi", "y", "en",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CatalogItem {
+    pub sku: String,
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// `None` si el artículo no se repara, sólo se reemplaza.
+    #[serde(default)]
+    pub repair_price: Option<f64>,
+    pub replacement_price: f64,
+    pub currency: String,
+    pub stock: u32,
+}
+
+/// Artículo encontrado por `Catalog::search`.
+#[derive(Debug)]
+pub struct Candidate<'a> {
+    pub item: &'a CatalogItem,
+    /// 1.0 si el nombre o un alias coincide exactamente.
+    pub score: f64,
+}
+
+#[derive(Debug)]
+pub struct Catalog {
+    /// Hash del contenido: dos catálogos con los mismos artículos tienen la
+    /// misma versión.
+    version: String,
+    items: Vec<CatalogItem>,
+    /// Nombre y alias de cada artículo, sin acentos ni mayúsculas.
+    names: Vec<Vec<String>>,
+}
+
+static CATALOG: OnceLock<RwLock<Arc<Catalog>>> = OnceLock::new();
+
+/// Carga el catálogo de `CATALOG_FILE`. Debe llamarse al arrancar para que
+/// un archivo inválido detenga el proceso en lugar del primer turno.
+pub fn init() -> Result<Arc<Catalog>> {
+    if let Some(catalog) = CATALOG.get() {
+        return Ok(read(catalog));
+    }
+    let catalog = Catalog::from_config()?;
+    tracing::info!(
+        items = catalog.items.len(),
+        version = %catalog.version,
+        "Catalog loaded"
+    );
+    Ok(read(CATALOG.get_or_init(|| RwLock::new(Arc::new(catalog)))))
+}
+
+/// Catálogo vigente. Sin `init` previo (tests) se carga el de la configuración.
+/// Cada búsqueda conserva su `Arc`, así que una importación no la afecta.
+pub fn get() -> Arc<Catalog> {
+    read(CATALOG.get_or_init(|| {
+        RwLock::new(Arc::new(
+            Catalog::from_config().unwrap_or_else(|e| panic!("{:#}", e)),
+        ))
+    }))
+}
+
+/// Reemplaza el catálogo vigente de forma atómica.
+pub fn replace(catalog: Catalog) {
+    let slot = CATALOG.get_or_init(|| RwLock::new(Arc::new(Catalog::empty())));
+    *slot.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(catalog);
+}
+
+fn read(slot: &RwLock<Arc<Catalog>>) -> Arc<Catalog> {
+    slot.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+impl Catalog {
+    /// Valida los artículos e indexa sus nombres.
+    pub fn new(items: Vec<CatalogItem>) -> Result<Self, String> {
+        if items.is_empty() {
+            return Err("the catalog has no items".to_string());
+        }
+        let mut skus = HashSet::new();
+        for item in &items {
+            let sku = &item.sku;
+            if sku.trim().is_empty() {
+                return Err(format!("item '{}' has an empty sku", item.name));
+            }
+            if !skus.insert(sku.as_str()) {
+                return Err(format!("duplicate sku '{}'", sku));
+            }
+            if item.name.trim().is_empty() {
+                return Err(format!("{}: empty name", sku));
+            }
+            let valid_price = |price: f64| price.is_finite() && price >= 0.0;
+            if !valid_price(item.replacement_price) || !item.repair_price.is_none_or(valid_price) {
+                return Err(format!("{}: prices must be non-negative numbers", sku));
+            }
+            if item.currency.len() != 3 || !item.currency.chars().all(|c| c.is_ascii_uppercase()) {
+                return Err(format!("{}: invalid currency '{}'", sku, item.currency));
+            }
+        }
+
+        let version = format!(
+            "{:016x}",
+            stable_hash(&serde_json::to_vec(&items).map_err(|e| e.to_string())?)
+        );
+        let names = items
+            .iter()
+            .map(|item| {
+                std::iter::once(&item.name)
+                    .chain(&item.aliases)
+                    .map(|name| fold(name))
+                    .collect()
+            })
+            .collect();
+        Ok(Self {
+            version,
+            items,
+            names,
+        })
+    }
+
+    fn empty() -> Self {
+        Self {
+            version: String::new(),
+            items: Vec::new(),
+            names: Vec::new(),
+        }
+    }
+
+    fn from_config() -> Result<Self> {
+        let path = crate::config::get().agents.catalog_file.trim();
+        if path.is_empty() {
+            return Self::from_csv(BUNDLED).map_err(|e| anyhow::anyhow!("catalog.csv: {}", e));
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read catalog {}", path))?;
+        let catalog = if path.ends_with(".json") {
+            Self::from_json(&content)
+        } else {
+            Self::from_csv(&content)
+        };
+        catalog.map_err(|e| anyhow::anyhow!("Invalid catalog {}: {}", path, e))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let items: Vec<CatalogItem> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        Self::new(items)
+    }
+
+    pub fn from_csv(csv: &str) -> Result<Self, String> {
+        let (header, rows) = csv::parse(csv)?;
+        let (sku, name, replacement_price, currency, stock) = (
+            header.required("sku")?,
+            header.required("name")?,
+            header.required("replacement_price")?,
+            header.required("currency")?,
+            header.required("stock")?,
+        );
+        let (aliases, repair_price) = (header.column("aliases"), header.column("repair_price"));
+
+        let mut items = Vec::new();
+        for csv::Row { line, fields } in rows {
+            let price = |i: usize| {
+                fields[i]
+                    .parse::<f64>()
+                    .map_err(|_| format!("line {}: invalid price '{}'", line, fields[i]))
+            };
+
+            items.push(CatalogItem {
+                sku: fields[sku].to_string(),
+                name: fields[name].to_string(),
+                aliases: aliases
+                    .map(|i| {
+                        fields[i]
+                            .split('|')
+                            .map(str::trim)
+                            .filter(|a| !a.is_empty())
+                            .map(String::from)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                repair_price: match repair_price {
+                    Some(i) if !fields[i].is_empty() => Some(price(i)?),
+                    _ => None,
+                },
+                replacement_price: price(replacement_price)?,
+                currency: fields[currency].to_uppercase(),
+                stock: fields[stock]
+                    .parse()
+                    .map_err(|_| format!("line {}: invalid stock '{}'", line, fields[stock]))?,
+            });
+        }
+
+        Self::new(items)
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn items(&self) -> &[CatalogItem] {
+        &self.items
+    }
+
+    pub fn find(&self, sku: &str) -> Option<&CatalogItem> {
+        self.items
+            .iter()
+            .find(|item| item.sku.eq_ignore_ascii_case(sku.trim()))
+    }
+
+    /// Hasta `MAX_CANDIDATES` artículos con puntaje de al menos `MIN_SCORE`,
+    /// del más al menos parecido.
+    ///
+    /// El puntaje de un nombre es el mayor entre su similitud con el texto
+    /// completo y la proporción de sus palabras que aparecen en el texto
+    /// (ponderada con la proporción de palabras del texto que explica), para
+    /// que "se rompió mi lámpara de buró" encuentre "Lámpara de buró".
+    pub fn search(&self, query: &str) -> Vec<Candidate<'_>> {
+        let query = fold(query);
+        let query_words = words(&query);
+
+        let mut candidates: Vec<Candidate<'_>> = self
+            .items
+            .iter()
+            .zip(&self.names)
+            .map(|(item, names)| Candidate {
+                item,
+                score: names
+                    .iter()
+                    .map(|name| score(&query, &query_words, name))
+                    .fold(0.0, f64::max),
+            })
+            .filter(|c| c.score >= MIN_SCORE)
+            .collect();
+        candidates.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.item.sku.cmp(&b.item.sku))
+        });
+        candidates.truncate(MAX_CANDIDATES);
+        candidates
+    }
+}
+
+fn words(folded: &str) -> Vec<&str> {
+    folded
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty() && !STOPWORDS.contains(w))
+        .collect()
+}
+
+fn score(query: &str, query_words: &[&str], name: &str) -> f64 {
+    let name_words = words(name);
+    if query_words.is_empty() || name_words.is_empty() {
+        return similarity(query, name);
+    }
+    // Proporción de `from` con alguna palabra parecida en `to`.
+    let covered = |from: &[&str], to: &[&str]| {
+        from.iter()
+            .filter(|w| to.iter().any(|t| similarity(w, t) >= MIN_WORD_SIMILARITY))
+            .count() as f64
+            / from.len() as f64
+    };
+    let by_words =
+        0.7 * covered(&name_words, query_words) + 0.3 * covered(query_words, &name_words);
+    similarity(query, name).max(by_words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skus(catalog: &Catalog, query: &str) -> Vec<String> {
+        catalog
+            .search(query)
+            .iter()
+            .map(|c| c.item.sku.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_search_ranks_candidates() {
+        let catalog = get();
+
+        let exact = catalog.search("Licuadora");
+        assert_eq!(exact[0].item.sku, "LIC-001");
+        assert_eq!(exact[0].score, 1.0);
+
+        // Alias con error de tipeo y palabras de más.
+        let typo = catalog.search("se rompió mi lampra de buró");
+        assert_eq!(typo[0].item.sku, "LMP-001");
+        assert!(typo[0].score < 1.0);
+
+        let tv = skus(&catalog, "televisor de 55 pulgadas");
+        assert_eq!(tv[0], "TV-055");
+        assert!(tv.contains(&"TV-032".to_string()));
+
+        let chairs = catalog.search("silla");
+        assert!(chairs.len() > 1 && chairs.len() <= MAX_CANDIDATES);
+        assert!(chairs.windows(2).all(|w| w[0].score >= w[1].score));
+
+        assert!(catalog.search("bicicleta").is_empty());
+    }
+
+    #[test]
+    fn test_parse_csv_and_json() {
+        let csv = "name,sku,replacement_price,currency,stock,repair_price,aliases\n\
+                   Lámpara,L-1,100,mxn,2,,lampara de buro|buro\n";
+        let catalog = Catalog::from_csv(csv).unwrap();
+        let item = &catalog.items()[0];
+        assert_eq!(item.currency, "MXN");
+        assert_eq!(item.repair_price, None);
+        assert_eq!(item.aliases, vec!["lampara de buro", "buro"]);
+
+        let json = serde_json::to_string(catalog.items()).unwrap();
+        let same = Catalog::from_json(&json).unwrap();
+        assert_eq!(same.version(), catalog.version());
+
+        assert!(Catalog::from_csv("sku,name\n")
+            .unwrap_err()
+            .contains("replacement_price"));
+        let bad = "sku,name,replacement_price,currency,stock\nL-1,Lámpara,x,MXN,1\n";
+        assert!(Catalog::from_csv(bad).unwrap_err().contains("line 2"));
+        let duplicate = "sku,name,replacement_price,currency,stock\nL-1,A,1,MXN,1\nL-1,B,1,MXN,1\n";
+        assert!(Catalog::from_csv(duplicate)
+            .unwrap_err()
+            .contains("duplicate"));
+        assert!(Catalog::from_json("[]").is_err());
+    }
+}
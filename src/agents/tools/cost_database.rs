@@ -1,23 +1,112 @@
+//! Cotiza reparaciones y reemplazos con el catálogo de artículos
+//! (`catalog.rs`).
+//!
+//! El nombre que da el cliente rara vez coincide con el del catálogo, así que
+//! la herramienta devuelve varios candidatos con su puntaje y el modelo
+//! confirma con el cliente cuando no hay uno claro. Con el artículo elegido,
+//! se vuelve a consultar por `sku` para cotizar exactamente ese.
+
+use super::catalog::{self, Candidate};
+use crate::agents::turn::TurnContext;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 #[derive(Deserialize, Serialize, JsonSchema)]
 pub struct CostArgs {
     pub item_name: String,
+    /// SKU de un candidato ya confirmado con el cliente.
+    #[serde(default)]
+    pub sku: Option<String>,
 }
 
 #[derive(Debug, thiserror::Error)]
-#[error("Error db")]
-pub struct CostError;
+pub enum CostError {
+    #[error(
+        "No hay artículos en el catálogo parecidos a '{0}'; pide al cliente el modelo o una \
+         descripción más precisa"
+    )]
+    NotFound(String),
+
+    #[error("El SKU '{0}' no existe en el catálogo")]
+    UnknownSku(String),
+}
+
+#[derive(Serialize, Debug)]
+pub struct CostCandidate {
+    pub sku: String,
+    pub name: String,
+    /// De 0 a 1: qué tan parecido es al artículo que nombró el cliente.
+    pub score: f64,
+    /// `None` si el artículo no se repara.
+    pub repair_price: Option<f64>,
+    pub replacement_price: f64,
+    pub currency: String,
+    pub stock: u32,
+}
+
+impl From<&Candidate<'_>> for CostCandidate {
+    fn from(candidate: &Candidate<'_>) -> Self {
+        let item = candidate.item;
+        Self {
+            sku: item.sku.clone(),
+            name: item.name.clone(),
+            score: (candidate.score * 100.0).round() / 100.0,
+            repair_price: item.repair_price,
+            replacement_price: item.replacement_price,
+            currency: item.currency.clone(),
+            stock: item.stock,
+        }
+    }
+}
 
-#[derive(Serialize)]
+#[derive(Serialize, Debug)]
 pub struct CostOutput {
+    /// Precio de reemplazo del mejor candidato.
     pub price: f64,
     pub currency: String,
     pub in_stock: bool,
+    /// Del más al menos parecido.
+    pub candidates: Vec<CostCandidate>,
+}
+
+/// Cotización de `item_name` (o del artículo `sku`) con el catálogo vigente.
+pub fn quote(item_name: &str, sku: Option<&str>) -> Result<CostOutput, CostError> {
+    let catalog = catalog::get();
+    let candidates: Vec<CostCandidate> = match sku {
+        Some(sku) => {
+            let item = catalog
+                .find(sku)
+                .ok_or_else(|| CostError::UnknownSku(sku.to_string()))?;
+            vec![CostCandidate::from(&Candidate { item, score: 1.0 })]
+        }
+        None => catalog
+            .search(item_name)
+            .iter()
+            .map(CostCandidate::from)
+            .collect(),
+    };
+    let best = candidates
+        .first()
+        .ok_or_else(|| CostError::NotFound(item_name.to_string()))?;
+
+    Ok(CostOutput {
+        price: best.replacement_price,
+        currency: best.currency.clone(),
+        in_stock: best.stock > 0,
+        candidates,
+    })
 }
 
-pub struct CostDatabase;
+pub struct CostDatabase {
+    turn: Arc<TurnContext>,
+}
+
+impl CostDatabase {
+    pub fn new(turn: Arc<TurnContext>) -> Self {
+        Self { turn }
+    }
+}
 
 impl rig::tool::Tool for CostDatabase {
     const NAME: &'static str = "cost_database";
@@ -29,17 +118,58 @@ impl rig::tool::Tool for CostDatabase {
     async fn definition(&self, _prompt: String) -> rig::completion::ToolDefinition {
         rig::completion::ToolDefinition {
             name: Self::NAME.to_string(),
-            description: "Consulta una base de datos de precios para estimar costos de reparación."
+            description: "Busca un artículo en el catálogo y devuelve hasta 3 candidatos con su \
+                          puntaje de coincidencia, precios de reparación y reemplazo, moneda y \
+                          stock. Con `sku`, cotiza sólo ese artículo."
                 .to_string(),
             parameters: serde_json::to_value(schemars::schema_for!(CostArgs)).unwrap(),
         }
     }
 
-    async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
-        Ok(CostOutput {
-            price: 150.50,
-            currency: "USD".to_string(),
-            in_stock: true,
-        })
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let output = quote(&args.item_name, args.sku.as_deref())?;
+        // Los guardrails de salida usan los precios del mejor candidato como
+        // tope de reembolso.
+        self.turn.record_tool(Self::NAME, &output);
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_from_catalog() {
+        let lamp = quote("lámpara de buró", None).unwrap();
+        assert_eq!(lamp.candidates[0].sku, "LMP-001");
+        assert_eq!((lamp.price, lamp.currency.as_str()), (899.0, "MXN"));
+        assert_eq!(lamp.candidates[0].repair_price, Some(350.0));
+        assert!(lamp.in_stock);
+
+        let mirror = quote("espejo", None).unwrap();
+        assert_eq!(mirror.candidates[0].repair_price, None);
+        assert!(!mirror.in_stock);
+
+        assert!(matches!(
+            quote("bicicleta", None),
+            Err(CostError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_quote_chosen_sku() {
+        let chair = quote("silla", Some("sil-002")).unwrap();
+        assert_eq!(chair.candidates.len(), 1);
+        assert_eq!(
+            (chair.candidates[0].sku.as_str(), chair.price),
+            ("SIL-002", 3299.0)
+        );
+
+        assert!(matches!(
+            quote("silla", Some("SIL-999")),
+            Err(CostError::UnknownSku(_))
+        ));
     }
 }
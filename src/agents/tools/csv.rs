@@ -0,0 +1,48 @@
+//! Archivos de datos en CSV simple (`catalog`, `gazetteer`): encabezado y
+//! columnas separadas por comas, sin comillas, en cualquier orden.
+
+/// Encabezado del CSV, en minúsculas.
+pub struct Header(Vec<String>);
+
+impl Header {
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.0.iter().position(|h| h == name)
+    }
+
+    pub fn required(&self, name: &str) -> Result<usize, String> {
+        self.column(name)
+            .ok_or_else(|| format!("missing column '{}'", name))
+    }
+}
+
+/// Fila del CSV: número de línea (desde 1) y campos sin espacios en los extremos.
+pub struct Row<'a> {
+    pub line: usize,
+    pub fields: Vec<&'a str>,
+}
+
+/// Separa el encabezado de las filas. Omite las líneas vacías y falla si una
+/// fila no tiene tantos campos como el encabezado.
+pub fn parse(csv: &str) -> Result<(Header, Vec<Row<'_>>), String> {
+    let mut lines = csv.lines().enumerate();
+    let header: Vec<String> = lines
+        .next()
+        .map(|(_, line)| line.split(',').map(|h| h.trim().to_lowercase()).collect())
+        .unwrap_or_default();
+
+    let rows = lines
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() != header.len() {
+                return Err(format!("line {}: expected {} fields", n + 1, header.len()));
+            }
+            Ok(Row {
+                line: n + 1,
+                fields,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok((Header(header), rows))
+}
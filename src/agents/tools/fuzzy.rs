@@ -0,0 +1,31 @@
+//! Comparación de textos escritos a mano: nombres de lugares
+//! (`gazetteer`, `address_parser`) y de artículos (`catalog`).
+
+use crate::guardrails::normalize;
+
+/// `normalize` (minúsculas, sin acentos) sin puntos ni espacios en los
+/// extremos, para comparar nombres escritos a mano.
+pub fn fold(text: &str) -> String {
+    normalize(text.trim()).replace('.', "")
+}
+
+/// 1 − distancia de Levenshtein / largo del nombre más largo.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
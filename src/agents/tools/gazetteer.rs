@@ -0,0 +1,236 @@
+//! # Gazetteer
+//!
+//! Índice en memoria de códigos postales y localidades, para validar y
+//! ubicar direcciones sin red (`address_parser`, geocodificador `gazetteer`).
+//!
+//! Se carga al arrancar desde `GAZETTEER_FILE` o, si no se indica, desde el
+//! CSV embebido (`gazetteer.csv`). El archivo lleva encabezado y columnas
+//! separadas por comas (sin comillas), en cualquier orden:
+//!
+//! - obligatorias: `country` (ISO 3166-1 alfa-2), `postal_code`, `place`,
+//!   `lat`, `lng`;
+//! - opcionales: `city` (por defecto, `place`) y `state`.
+//!
+//! Las ciudades se buscan sin acentos ni mayúsculas y toleran errores de
+//! tipeo (distancia de edición, ver `MIN_SIMILARITY`).
+
+use super::csv;
+use super::fuzzy::{fold, similarity};
+use super::geocoding::GeoPoint;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const BUNDLED: &str = include_str!("gazetteer.csv");
+
+/// Similitud mínima (0–1) para aceptar un nombre de ciudad escrito con errores.
+pub const MIN_SIMILARITY: f64 = 0.8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Place {
+    pub country: String,
+    pub postal_code: String,
+    /// Colonia o asentamiento principal del código.
+    pub place: String,
+    pub city: String,
+    pub state: String,
+    pub lat: f64,
+    pub lng: f64,
+}
+
+impl Place {
+    pub fn point(&self) -> GeoPoint {
+        GeoPoint {
+            lat: self.lat,
+            lng: self.lng,
+        }
+    }
+}
+
+/// Ciudad encontrada por `Gazetteer::find_city`.
+#[derive(Debug)]
+pub struct CityMatch<'a> {
+    /// Nombre como figura en el gazetteer.
+    pub city: &'a str,
+    pub state: &'a str,
+    /// 1.0 si el nombre coincide exactamente (sin acentos ni mayúsculas).
+    pub similarity: f64,
+    pub places: Vec<&'a Place>,
+}
+
+impl CityMatch<'_> {
+    /// Promedio de los códigos postales de la ciudad.
+    pub fn centroid(&self) -> GeoPoint {
+        let n = self.places.len() as f64;
+        GeoPoint {
+            lat: self.places.iter().map(|p| p.lat).sum::<f64>() / n,
+            lng: self.places.iter().map(|p| p.lng).sum::<f64>() / n,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Gazetteer {
+    places: Vec<Place>,
+    /// `(país, código)` → posición en `places`.
+    by_code: HashMap<(String, String), usize>,
+    /// `(país, ciudad sin acentos)` → posiciones en `places`.
+    by_city: HashMap<(String, String), Vec<usize>>,
+}
+
+static GAZETTEER: OnceLock<Gazetteer> = OnceLock::new();
+
+/// Carga el gazetteer de `GAZETTEER_FILE`. Debe llamarse al arrancar para
+/// que un archivo inválido detenga el proceso en lugar del primer turno.
+pub fn init() -> Result<&'static Gazetteer> {
+    if let Some(gazetteer) = GAZETTEER.get() {
+        return Ok(gazetteer);
+    }
+    let gazetteer = Gazetteer::from_config()?;
+    tracing::info!(places = gazetteer.places.len(), "Gazetteer loaded");
+    Ok(GAZETTEER.get_or_init(|| gazetteer))
+}
+
+/// Gazetteer vigente. Sin `init` previo (tests) se carga el de la configuración.
+pub fn get() -> &'static Gazetteer {
+    GAZETTEER.get_or_init(|| Gazetteer::from_config().unwrap_or_else(|e| panic!("{:#}", e)))
+}
+
+impl Gazetteer {
+    fn from_config() -> Result<Self> {
+        let path = crate::config::get().agents.gazetteer_file.trim();
+        if path.is_empty() {
+            return Self::parse(BUNDLED).map_err(|e| anyhow::anyhow!("gazetteer.csv: {}", e));
+        }
+        let csv = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read gazetteer {}", path))?;
+        Self::parse(&csv).map_err(|e| anyhow::anyhow!("Invalid gazetteer {}: {}", path, e))
+    }
+
+    pub fn parse(csv: &str) -> Result<Self, String> {
+        let (header, rows) = csv::parse(csv)?;
+        let (country, postal_code, place, lat, lng) = (
+            header.required("country")?,
+            header.required("postal_code")?,
+            header.required("place")?,
+            header.required("lat")?,
+            header.required("lng")?,
+        );
+        let (city, state) = (header.column("city"), header.column("state"));
+
+        let mut gazetteer = Self {
+            places: Vec::new(),
+            by_code: HashMap::new(),
+            by_city: HashMap::new(),
+        };
+        for csv::Row { line, fields } in rows {
+            let coordinate = |i: usize| {
+                fields[i]
+                    .parse::<f64>()
+                    .map_err(|_| format!("line {}: invalid coordinate '{}'", line, fields[i]))
+            };
+
+            gazetteer.insert(Place {
+                country: fields[country].to_uppercase(),
+                postal_code: fields[postal_code].to_string(),
+                place: fields[place].to_string(),
+                city: fields[city.unwrap_or(place)].to_string(),
+                state: state.map(|i| fields[i].to_string()).unwrap_or_default(),
+                lat: coordinate(lat)?,
+                lng: coordinate(lng)?,
+            });
+        }
+
+        Ok(gazetteer)
+    }
+
+    fn insert(&mut self, place: Place) {
+        let id = self.places.len();
+        self.by_code
+            .insert((place.country.clone(), place.postal_code.clone()), id);
+        self.by_city
+            .entry((place.country.clone(), fold(&place.city)))
+            .or_default()
+            .push(id);
+        self.places.push(place);
+    }
+
+    pub fn lookup(&self, country: &str, postal_code: &str) -> Option<&Place> {
+        self.by_code
+            .get(&(country.to_uppercase(), postal_code.to_string()))
+            .map(|&i| &self.places[i])
+    }
+
+    /// Ciudad del país con el nombre más parecido a `name`, si alcanza
+    /// `MIN_SIMILARITY`.
+    pub fn find_city(&self, country: &str, name: &str) -> Option<CityMatch<'_>> {
+        let country = country.to_uppercase();
+        let name = fold(name);
+        let (similarity, ids) = self
+            .by_city
+            .iter()
+            .filter(|((c, _), _)| *c == country)
+            .map(|((_, city), ids)| (similarity(&name, city), city, ids))
+            .filter(|(score, _, _)| *score >= MIN_SIMILARITY)
+            // Desempate por nombre para no depender del orden del HashMap.
+            .max_by(|a, b| a.0.total_cmp(&b.0).then_with(|| b.1.cmp(a.1)))
+            .map(|(score, _, ids)| (score, ids))?;
+
+        let places: Vec<&Place> = ids.iter().map(|&i| &self.places[i]).collect();
+        Some(CityMatch {
+            city: &places[0].city,
+            state: &places[0].state,
+            similarity,
+            places,
+        })
+    }
+}
+
+/// Si dos nombres de lugar son el mismo, tolerando acentos y errores de tipeo.
+pub fn same_name(a: &str, b: &str) -> bool {
+    similarity(&fold(a), &fold(b)) >= MIN_SIMILARITY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_gazetteer_lookup() {
+        let gazetteer = get();
+        let roma = gazetteer.lookup("mx", "06700").unwrap();
+        assert_eq!(roma.place, "Roma Norte");
+        assert_eq!(roma.city, "Ciudad de México");
+        assert!(gazetteer.lookup("MX", "99999").is_none());
+    }
+
+    #[test]
+    fn test_find_city_tolerates_typos() {
+        let gazetteer = get();
+        let exact = gazetteer.find_city("MX", "merida").unwrap();
+        assert_eq!((exact.city, exact.similarity), ("Mérida", 1.0));
+
+        let typo = gazetteer.find_city("MX", "Guadalajra").unwrap();
+        assert_eq!(typo.city, "Guadalajara");
+        assert_eq!(typo.places.len(), 2);
+        assert!(typo.similarity < 1.0);
+
+        assert!(gazetteer.find_city("MX", "Springfield").is_none());
+        assert!(gazetteer.find_city("US", "Guadalajara").is_none());
+    }
+
+    #[test]
+    fn test_parse_by_header() {
+        let csv = "lat,lng,place,postal_code,country\n40.41,-3.70,Madrid,28013,es\n";
+        let gazetteer = Gazetteer::parse(csv).unwrap();
+        let place = gazetteer.lookup("ES", "28013").unwrap();
+        assert_eq!(place.city, "Madrid");
+        assert_eq!(place.lat, 40.41);
+
+        assert!(Gazetteer::parse("country,place\n")
+            .unwrap_err()
+            .contains("postal_code"));
+        let bad = "country,postal_code,place,lat,lng\nMX,06000,Centro,x,1\n";
+        assert!(Gazetteer::parse(bad).unwrap_err().contains("line 2"));
+    }
+}
@@ -1,5 +1,262 @@
+//! Geocodificación de direcciones.
+//!
+//! La herramienta analiza la dirección (`address_parser`) y la ubica con el
+//! `Geocoder` configurado en `GEOCODER`:
+//!
+//! - `gazetteer`: código postal o ciudad (con tolerancia a errores de tipeo)
+//!   en el gazetteer cargado al arrancar (`gazetteer.rs`). No usa red; la
+//!   precisión es la del código postal.
+//! - `nominatim`: API de búsqueda de Nominatim (OpenStreetMap) en `GEOCODER_URL`.
+//!
+//! Cada resultado lleva su precisión y una confianza de 0 a 1. Otro proveedor
+//! se agrega implementando `Geocoder` y su variante en `GeocoderBackend`.
+
+use super::address_parser::{self, ParsedAddress};
+use super::gazetteer::{self, same_name};
+use crate::agents::turn::TurnContext;
+use crate::config::GeocoderBackend;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+
+/// Radio medio de la Tierra en kilómetros.
+const EARTH_RADIUS_KM: f64 = 6_371.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lng: f64,
+}
+
+impl GeoPoint {
+    /// Distancia de gran círculo (haversine) en kilómetros.
+    pub fn distance_km(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlng = (other.lng - self.lng).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlng / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
+
+/// Nivel de detalle del punto devuelto.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GeoPrecision {
+    Address,
+    Neighborhood,
+    PostalCode,
+    City,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoMatch {
+    pub point: GeoPoint,
+    pub precision: GeoPrecision,
+    /// De 0 a 1: qué tan seguro es que el punto corresponde a la dirección.
+    pub confidence: f64,
+    /// Ciudad según el proveedor, si la informa.
+    pub city: Option<String>,
+}
+
+/// Proveedor de coordenadas.
+#[async_trait]
+pub trait Geocoder: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// `Ok(None)` si el proveedor no encuentra la dirección.
+    async fn geocode(&self, address: &ParsedAddress) -> Result<Option<GeoMatch>>;
+}
+
+static GEOCODER: OnceLock<Box<dyn Geocoder>> = OnceLock::new();
+
+/// Geocodificador de `GEOCODER`.
+pub fn get() -> &'static dyn Geocoder {
+    GEOCODER
+        .get_or_init(|| {
+            let config = crate::config::get();
+            match config.agents.geocoder {
+                GeocoderBackend::Gazetteer => Box::new(GazetteerGeocoder),
+                GeocoderBackend::Nominatim => Box::new(NominatimGeocoder::new(
+                    &config.agents.geocoder_url,
+                    &config.server.service_name,
+                )),
+            }
+        })
+        .as_ref()
+}
+
+// ================================================================
+// Proveedores
+// ================================================================
+
+/// Código postal o ciudad en el gazetteer.
+pub struct GazetteerGeocoder;
+
+#[async_trait]
+impl Geocoder for GazetteerGeocoder {
+    fn name(&self) -> &'static str {
+        "gazetteer"
+    }
+
+    async fn geocode(&self, address: &ParsedAddress) -> Result<Option<GeoMatch>> {
+        let gazetteer = gazetteer::get();
+        let country = address.country();
+
+        if let Some(place) = address
+            .postal_code
+            .as_ref()
+            .and_then(|code| gazetteer.lookup(country, code))
+        {
+            // Un código de otra ciudad puede ser un error de tipeo en cualquiera de los dos.
+            let consistent = address
+                .city
+                .as_ref()
+                .is_none_or(|c| same_name(c, &place.city) || same_name(c, &place.place));
+            return Ok(Some(GeoMatch {
+                point: place.point(),
+                precision: GeoPrecision::PostalCode,
+                confidence: if consistent { 0.9 } else { 0.5 },
+                city: Some(place.city.clone()),
+            }));
+        }
+
+        // Sin código conocido, el centroide de la ciudad.
+        let Some(city) = address
+            .city
+            .as_ref()
+            .and_then(|c| gazetteer.find_city(country, c))
+        else {
+            return Ok(None);
+        };
+        Ok(Some(GeoMatch {
+            point: city.centroid(),
+            precision: GeoPrecision::City,
+            confidence: (50.0 * city.similarity).round() / 100.0,
+            city: Some(city.city.to_string()),
+        }))
+    }
+}
+
+/// API `/search` de Nominatim. La política de uso del servidor público exige
+/// un User-Agent que identifique al servicio y como máximo una consulta por
+/// segundo; con tráfico real conviene una instancia propia.
+pub struct NominatimGeocoder {
+    client: reqwest::Client,
+    url: String,
+    user_agent: String,
+}
+
+#[derive(Deserialize)]
+struct NominatimPlace {
+    lat: String,
+    lon: String,
+    /// 30 = número de casa, 26–27 = calle, 17–25 = colonia, 16 o menos = ciudad.
+    #[serde(default)]
+    place_rank: u32,
+    #[serde(default)]
+    address: NominatimAddress,
+}
+
+#[derive(Deserialize, Default)]
+struct NominatimAddress {
+    city: Option<String>,
+    town: Option<String>,
+    village: Option<String>,
+}
+
+impl NominatimGeocoder {
+    pub fn new(url: &str, user_agent: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+            user_agent: user_agent.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Geocoder for NominatimGeocoder {
+    fn name(&self) -> &'static str {
+        "nominatim"
+    }
+
+    async fn geocode(&self, address: &ParsedAddress) -> Result<Option<GeoMatch>> {
+        let response = self
+            .client
+            .get(format!("{}/search", self.url))
+            .header(reqwest::header::USER_AGENT, &self.user_agent)
+            .query(&[
+                ("q", address.to_line().as_str()),
+                ("countrycodes", &address.country().to_lowercase()),
+                ("format", "jsonv2"),
+                ("addressdetails", "1"),
+                ("limit", "1"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let places: Vec<NominatimPlace> = serde_json::from_str(&response.text().await?)
+            .context("Unexpected Nominatim response")?;
+        let Some(place) = places.into_iter().next() else {
+            return Ok(None);
+        };
+        let (precision, confidence) = match place.place_rank {
+            26.. => (GeoPrecision::Address, 0.9),
+            17..=25 => (GeoPrecision::Neighborhood, 0.7),
+            _ => (GeoPrecision::City, 0.5),
+        };
+        let NominatimAddress {
+            city,
+            town,
+            village,
+        } = place.address;
+
+        Ok(Some(GeoMatch {
+            point: GeoPoint {
+                lat: place.lat.parse().context("Invalid latitude")?,
+                lng: place.lon.parse().context("Invalid longitude")?,
+            },
+            precision,
+            confidence,
+            city: city.or(town).or(village),
+        }))
+    }
+}
+
+/// Analiza y ubica una dirección con el geocodificador configurado.
+pub async fn locate(text: &str) -> Result<GeoOutput, GeoError> {
+    let address = address_parser::check(text);
+    let geocoder = get();
+    let found = geocoder
+        .geocode(&address.components)
+        .await
+        .map_err(|e| {
+            tracing::warn!(geocoder = geocoder.name(), "Geocoding failed: {:#}", e);
+            GeoError::Unavailable
+        })?
+        .ok_or(GeoError::NotFound)?;
+
+    let components = address.components;
+    Ok(GeoOutput {
+        lat: found.point.lat,
+        lng: found.point.lng,
+        precision: found.precision,
+        confidence: found.confidence,
+        country: components.country().to_string(),
+        city: found.city.or(components.city),
+        zip_code: components.postal_code,
+        normalized_address: address.normalized,
+        geocoder: geocoder.name(),
+    })
+}
+
+// ================================================================
+// Herramienta
+// ================================================================
 
 #[derive(Deserialize, Serialize, JsonSchema)]
 pub struct GeoArgs {
@@ -7,17 +264,47 @@ pub struct GeoArgs {
 }
 
 #[derive(Debug, thiserror::Error)]
-#[error("Error geocoding")]
-pub struct GeoError;
+pub enum GeoError {
+    #[error("No se pudo ubicar la dirección; pide al cliente el código postal o la ciudad")]
+    NotFound,
+
+    #[error("El servicio de geocodificación no está disponible")]
+    Unavailable,
+}
 
-#[derive(Serialize)]
+#[derive(Serialize, Debug, Clone)]
 pub struct GeoOutput {
     pub lat: f64,
     pub lng: f64,
-    pub zip_code: String,
+    pub precision: GeoPrecision,
+    pub confidence: f64,
+    pub zip_code: Option<String>,
+    pub city: Option<String>,
+    /// ISO 3166-1 alfa-2.
+    pub country: String,
+    pub normalized_address: String,
+    /// Proveedor que resolvió las coordenadas.
+    pub geocoder: &'static str,
+}
+
+impl GeoOutput {
+    pub fn point(&self) -> GeoPoint {
+        GeoPoint {
+            lat: self.lat,
+            lng: self.lng,
+        }
+    }
 }
 
-pub struct GeoCoding;
+pub struct GeoCoding {
+    turn: Arc<TurnContext>,
+}
+
+impl GeoCoding {
+    pub fn new(turn: Arc<TurnContext>) -> Self {
+        Self { turn }
+    }
+}
 
 impl rig::tool::Tool for GeoCoding {
     const NAME: &'static str = "geocoding_service";
@@ -29,18 +316,69 @@ impl rig::tool::Tool for GeoCoding {
     async fn definition(&self, _prompt: String) -> rig::completion::ToolDefinition {
         rig::completion::ToolDefinition {
             name: Self::NAME.to_string(),
-            description: "Obtiene coordenadas y código postal simulados para una dirección."
-                .to_string(),
+            description: "Obtiene coordenadas y código postal de una dirección.".to_string(),
             parameters: serde_json::to_value(schemars::schema_for!(GeoArgs)).unwrap(),
         }
     }
 
-    async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
-        // Mock response
-        Ok(GeoOutput {
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let output = locate(&args.address).await?;
+        self.turn.record_tool(Self::NAME, &output);
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_gazetteer_geocoder_confidence() {
+        let by_code = address_parser::parse("Av. Juárez 10, Centro, C.P. 06000");
+        let found = GazetteerGeocoder.geocode(&by_code).await.unwrap().unwrap();
+        assert_eq!(
+            found.point,
+            GeoPoint {
+                lat: 19.4326,
+                lng: -99.1332
+            }
+        );
+        assert_eq!(
+            (found.precision, found.confidence),
+            (GeoPrecision::PostalCode, 0.9)
+        );
+
+        let wrong_city = address_parser::parse("Av. Juárez 10, Centro, Monterrey, C.P. 06000");
+        let found = GazetteerGeocoder
+            .geocode(&wrong_city)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.confidence, 0.5);
+        assert_eq!(found.city.as_deref(), Some("Ciudad de México"));
+
+        let by_city = address_parser::parse("Av. Vallarta 100, Guadalajra, Jal.");
+        let found = GazetteerGeocoder.geocode(&by_city).await.unwrap().unwrap();
+        assert_eq!(found.precision, GeoPrecision::City);
+        assert!((found.point.lat - 20.67835).abs() < 1e-6);
+        assert!(found.confidence > 0.4 && found.confidence < 0.5);
+
+        let unknown = address_parser::parse("Calle 1 100, Springfield");
+        assert!(GazetteerGeocoder.geocode(&unknown).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_distance_km() {
+        let cdmx = GeoPoint {
             lat: 19.4326,
             lng: -99.1332,
-            zip_code: "06000".to_string(),
-        })
+        };
+        let guadalajara = GeoPoint {
+            lat: 20.6767,
+            lng: -103.3475,
+        };
+        assert!((cdmx.distance_km(&guadalajara) - 461.0).abs() < 5.0);
+        assert_eq!(cdmx.distance_km(&cdmx), 0.0);
     }
 }
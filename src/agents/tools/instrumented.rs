@@ -0,0 +1,255 @@
+use crate::agents::genai;
+use crate::agents::turn::TurnContext;
+use crate::infra::audit::{AuditAction, AuditEvent};
+use crate::infra::metrics;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{field::Empty, Instrument, Span};
+
+/// Envuelve una herramienta (o un especialista) y registra sus invocaciones,
+/// errores y latencia en las métricas, además de un span `execute_tool`.
+/// Se registra en el `ToolServer` en lugar de la herramienta:
+/// `tools.tool(Instrumented::new(CostDatabase::new(turn.clone()), turn))`.
+///
+/// También aplica el tiempo límite de la llamada (`TOOL_TIMEOUT_SECS` por
+/// defecto, ver `with_timeout`) y la aborta si el turno se cancela. Las
+/// herramientas con efectos se marcan con `audited`.
+pub struct Instrumented<T> {
+    tool: T,
+    /// Span activo al construir la herramienta. El `ToolServer` la ejecuta en
+    /// otra tarea, así que el padre se fija explícitamente.
+    parent: Span,
+    turn: Arc<TurnContext>,
+    timeout: Duration,
+    /// Acción y modelo que se registran en la auditoría por cada invocación.
+    audit: Option<(AuditAction, String)>,
+}
+
+impl<T> Instrumented<T> {
+    pub fn new(tool: T, turn: &Arc<TurnContext>) -> Self {
+        Self {
+            tool,
+            parent: Span::current(),
+            turn: turn.clone(),
+            timeout: Duration::from_secs(crate::config::get().limits.tool_timeout_secs),
+            audit: None,
+        }
+    }
+
+    /// Reemplaza el tiempo límite (los especialistas usan `SPECIALIST_TIMEOUT_SECS`).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Registra cada invocación como `action` en el log de auditoría del
+    /// turno. `model` es el modelo que ejecuta la herramienta.
+    pub fn audited(mut self, action: AuditAction, model: &str) -> Self {
+        self.audit = Some((action, model.to_string()));
+        self
+    }
+}
+
+/// Error de una llamada instrumentada. El mensaje llega al modelo que invocó
+/// la herramienta, que puede reintentar o responder sin ese dato.
+#[derive(Debug, thiserror::Error)]
+pub enum ToolCallError<E> {
+    #[error(transparent)]
+    Tool(E),
+
+    #[error("La herramienta '{tool}' no respondió en {}s", timeout.as_secs())]
+    Timeout {
+        tool: &'static str,
+        timeout: Duration,
+    },
+
+    #[error("El turno fue cancelado")]
+    Cancelled,
+}
+
+impl<E> ToolCallError<E> {
+    /// Valor de `outcome` en las métricas y de `error.type` en el span.
+    fn outcome(&self) -> &'static str {
+        match self {
+            ToolCallError::Tool(_) => "error",
+            ToolCallError::Timeout { .. } => "timeout",
+            ToolCallError::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl<T> Tool for Instrumented<T>
+where
+    T: Tool,
+    T::Args: Serialize,
+    T::Output: Serialize + Send,
+{
+    const NAME: &'static str = T::NAME;
+
+    type Error = ToolCallError<T::Error>;
+    type Args = T::Args;
+    type Output = T::Output;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.tool.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let span = tracing::info_span!(
+            parent: &self.parent,
+            "execute_tool",
+            otel.name = %format_args!("execute_tool {}", T::NAME),
+            otel.status_code = Empty,
+            gen_ai.operation.name = "execute_tool",
+            gen_ai.tool.name = T::NAME,
+            gen_ai.tool.type = "function",
+            gen_ai.tool.call.arguments = Empty,
+            gen_ai.tool.call.result = Empty,
+            error.type = Empty,
+            session.id = self.turn.session_id(),
+        );
+        genai::record_content(&span, "gen_ai.tool.call.arguments", &args);
+        let audit = self.audit_event(&args);
+
+        let start = Instant::now();
+        let call = tokio::time::timeout(self.timeout, self.tool.call(args));
+        let result = tokio::select! {
+            result = call.instrument(span.clone()) => match result {
+                Ok(result) => result.map_err(ToolCallError::Tool),
+                Err(_) => Err(ToolCallError::Timeout {
+                    tool: T::NAME,
+                    timeout: self.timeout,
+                }),
+            },
+            _ = self.turn.cancelled() => Err(ToolCallError::Cancelled),
+        };
+
+        let outcome = match &result {
+            Ok(output) => {
+                genai::record_content(&span, "gen_ai.tool.call.result", output);
+                "ok"
+            }
+            Err(ToolCallError::Tool(_)) => {
+                genai::record_error(&span, std::any::type_name::<T::Error>());
+                "error"
+            }
+            Err(e) => {
+                genai::record_error(&span, e.outcome());
+                if let ToolCallError::Timeout { .. } = e {
+                    self.turn.record_timeout(T::NAME);
+                    tracing::warn!(parent: &span, tool = T::NAME, "Tool call timed out");
+                }
+                e.outcome()
+            }
+        };
+
+        if let Some((log, event)) = audit {
+            let event = match &result {
+                Ok(output) => event.with_result(output),
+                Err(e) => AuditEvent {
+                    outcome: e.outcome().to_string(),
+                    ..event
+                },
+            };
+            log.record(event).await;
+        }
+
+        let metrics = metrics::get();
+        metrics
+            .tool_calls
+            .with_label_values(&[T::NAME, outcome])
+            .inc();
+        metrics
+            .tool_duration
+            .with_label_values(&[T::NAME])
+            .observe(start.elapsed().as_secs_f64());
+
+        result
+    }
+}
+
+impl<T: Tool> Instrumented<T>
+where
+    T::Args: Serialize,
+{
+    /// Evento de auditoría de la invocación, si la herramienta está marcada y
+    /// el turno tiene caller. Se arma antes de la llamada, que consume los argumentos.
+    fn audit_event(&self, args: &T::Args) -> Option<(crate::infra::audit::AuditLog, AuditEvent)> {
+        let (action, model) = self.audit.as_ref()?;
+        let scope = self.turn.audit()?;
+        let event = AuditEvent {
+            model: Some(model.clone()),
+            variant: Some(scope.variant.clone()),
+            prompt_version: Some(scope.prompt_version.clone()),
+            ..AuditEvent::new(*action, T::NAME, &scope.caller)
+        }
+        .with_session(self.turn.session_id())
+        .with_arguments(args);
+        Some((scope.log.clone(), event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("slow tool error")]
+    struct SlowError;
+
+    #[derive(Deserialize, Serialize)]
+    struct SlowArgs {
+        millis: u64,
+    }
+
+    struct SlowTool;
+
+    impl Tool for SlowTool {
+        const NAME: &'static str = "slow_tool";
+
+        type Error = SlowError;
+        type Args = SlowArgs;
+        type Output = String;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: String::new(),
+                parameters: serde_json::json!({}),
+            }
+        }
+
+        async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+            tokio::time::sleep(Duration::from_millis(args.millis)).await;
+            Ok("done".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_times_out() {
+        let turn = TurnContext::new("session-1");
+        let tool = Instrumented::new(SlowTool, &turn).with_timeout(Duration::from_millis(10));
+
+        let result = tool.call(SlowArgs { millis: 1_000 }).await;
+        assert!(matches!(result, Err(ToolCallError::Timeout { .. })));
+        assert_eq!(turn.timeouts(), vec!["slow_tool"]);
+
+        let result = tool.call(SlowArgs { millis: 1 }).await;
+        assert_eq!(result.unwrap(), "done");
+    }
+
+    #[tokio::test]
+    async fn test_call_is_cancelled_with_turn() {
+        let turn = TurnContext::new("session-1");
+        let tool = Instrumented::new(SlowTool, &turn);
+
+        turn.cancel();
+        let result = tool.call(SlowArgs { millis: 1_000 }).await;
+        assert!(matches!(result, Err(ToolCallError::Cancelled)));
+        assert!(turn.timeouts().is_empty());
+    }
+}
@@ -1,3 +1,11 @@
+pub mod address_distance;
+pub mod address_parser;
+pub mod catalog;
 pub mod cost_database;
+pub mod csv;
+pub mod fuzzy;
+pub mod gazetteer;
 pub mod geocoding;
+pub mod instrumented;
+pub mod shipping_zones;
 pub mod text_reverser;
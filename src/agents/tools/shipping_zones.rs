@@ -0,0 +1,413 @@
+//! # Zonas de envío
+//!
+//! Asigna una zona a cada dirección (por código postal o coordenadas) y
+//! calcula el recargo y el cambio en el tiempo de entrega de mudar un envío
+//! de una zona a otra, según una tabla de reglas en TOML.
+//!
+//! La tabla se carga al arrancar desde `SHIPPING_ZONES_FILE` o, si no se
+//! indica, desde `shipping_zones.toml` (embebido; documenta el formato).
+
+use super::address_parser::DEFAULT_COUNTRY;
+use super::gazetteer;
+use super::geocoding::GeoPoint;
+use crate::agents::turn::TurnContext;
+use anyhow::{bail, Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, OnceLock};
+
+const BUNDLED: &str = include_str!("shipping_zones.toml");
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Zone {
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_country")]
+    pub country: String,
+    /// Prefijos de código postal que pertenecen a la zona.
+    #[serde(default)]
+    pub postal_prefixes: Vec<String>,
+    pub center: Option<GeoPoint>,
+    #[serde(default)]
+    pub radius_km: f64,
+    /// Días hábiles de entrega a la zona.
+    pub delivery_days: u32,
+}
+
+fn default_country() -> String {
+    DEFAULT_COUNTRY.to_string()
+}
+
+impl Zone {
+    fn contains(&self, location: &Location) -> bool {
+        if !self.country.eq_ignore_ascii_case(location.country()) {
+            return false;
+        }
+        if self.postal_prefixes.is_empty() && self.center.is_none() {
+            return true;
+        }
+        let by_prefix = location.postal_code().is_some_and(|code| {
+            self.postal_prefixes
+                .iter()
+                .any(|prefix| code.starts_with(prefix.as_str()))
+        });
+        let by_distance = self
+            .center
+            .zip(location.point())
+            .is_some_and(|(center, point)| center.distance_km(&point) <= self.radius_km);
+        by_prefix || by_distance
+    }
+}
+
+/// Recargo por mudar un envío. Sin condiciones, aplica a cualquier cambio.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ShippingRule {
+    pub name: String,
+    /// Zona de la dirección actual.
+    pub from: Option<String>,
+    /// Zona de la dirección nueva.
+    pub to: Option<String>,
+    /// Sólo si ambas direcciones están en la misma zona.
+    #[serde(default)]
+    pub same_zone: bool,
+    pub surcharge: f64,
+    /// Días que se suman a la entrega por el desvío.
+    #[serde(default)]
+    pub extra_days: u32,
+}
+
+impl ShippingRule {
+    fn is_catch_all(&self) -> bool {
+        self.from.is_none() && self.to.is_none() && !self.same_zone
+    }
+
+    fn matches(&self, from: Option<&Zone>, to: &Zone) -> bool {
+        self.from
+            .as_ref()
+            .is_none_or(|id| from.is_some_and(|z| z.id == *id))
+            && self.to.as_ref().is_none_or(|id| *id == to.id)
+            && (!self.same_zone || from.is_some_and(|z| z.id == to.id))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ShippingTable {
+    pub currency: String,
+    pub zones: Vec<Zone>,
+    pub rules: Vec<ShippingRule>,
+}
+
+static SHIPPING_TABLE: OnceLock<ShippingTable> = OnceLock::new();
+
+/// Carga la tabla de `SHIPPING_ZONES_FILE`. Debe llamarse al arrancar para
+/// que una tabla inválida detenga el proceso en lugar del primer turno.
+pub fn init() -> Result<&'static ShippingTable> {
+    if let Some(table) = SHIPPING_TABLE.get() {
+        return Ok(table);
+    }
+    let table = ShippingTable::from_config()?;
+    tracing::info!(
+        zones = table.zones.len(),
+        rules = table.rules.len(),
+        "Shipping zones loaded"
+    );
+    Ok(SHIPPING_TABLE.get_or_init(|| table))
+}
+
+/// Tabla vigente. Sin `init` previo (tests) se carga la de la configuración.
+pub fn get() -> &'static ShippingTable {
+    SHIPPING_TABLE
+        .get_or_init(|| ShippingTable::from_config().unwrap_or_else(|e| panic!("{:#}", e)))
+}
+
+impl ShippingTable {
+    fn from_config() -> Result<Self> {
+        let path = crate::config::get().agents.shipping_zones_file.trim();
+        if path.is_empty() {
+            return Self::parse(BUNDLED).context("Invalid shipping_zones.toml");
+        }
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read shipping zones {}", path))?;
+        Self::parse(&raw).with_context(|| format!("Invalid shipping zones {}", path))
+    }
+
+    pub fn parse(raw: &str) -> Result<Self> {
+        let table: Self = toml::from_str(raw)?;
+        table.validate()?;
+        Ok(table)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.currency.trim().is_empty() {
+            bail!("currency must not be empty");
+        }
+        if self.zones.is_empty() {
+            bail!("at least one zone is required");
+        }
+        let mut ids = HashSet::new();
+        for zone in &self.zones {
+            if !ids.insert(zone.id.as_str()) {
+                bail!("duplicate zone '{}'", zone.id);
+            }
+            if zone.center.is_some() && zone.radius_km <= 0.0 {
+                bail!("zone '{}': radius_km must be greater than 0", zone.id);
+            }
+        }
+        for rule in &self.rules {
+            if let Some(unknown) = [&rule.from, &rule.to]
+                .into_iter()
+                .flatten()
+                .find(|id| !ids.contains(id.as_str()))
+            {
+                bail!("rule '{}': unknown zone '{}'", rule.name, unknown);
+            }
+        }
+        if !self.rules.last().is_some_and(ShippingRule::is_catch_all) {
+            bail!("the last rule must have no conditions (from, to, same_zone)");
+        }
+        Ok(())
+    }
+
+    pub fn zone_for(&self, location: &Location) -> Option<&Zone> {
+        self.zones.iter().find(|zone| zone.contains(location))
+    }
+
+    /// Recargo y tiempo de entrega de enviar a `new` en lugar de a `current`.
+    /// Sin dirección actual (o fuera de cobertura) se cotiza sólo el destino.
+    pub fn quote(
+        &self,
+        current: Option<&Location>,
+        new: &Location,
+    ) -> Result<ShippingQuote, ShippingError> {
+        if new.postal_code().is_none() && new.point().is_none() {
+            return Err(ShippingError::MissingLocation);
+        }
+        let to = self.zone_for(new).ok_or(ShippingError::NoCoverage)?;
+        let from = current.and_then(|location| self.zone_for(location));
+        let rule = self
+            .rules
+            .iter()
+            .find(|rule| rule.matches(from, to))
+            .expect("validated: the last rule matches any change");
+
+        let delivery_days = to.delivery_days + rule.extra_days;
+        Ok(ShippingQuote {
+            from: from.map(ZoneRef::from),
+            to: ZoneRef::from(to),
+            rule: rule.name.clone(),
+            surcharge: rule.surcharge,
+            currency: self.currency.clone(),
+            delivery_days,
+            delivery_days_change: from
+                .map(|zone| i64::from(delivery_days) - i64::from(zone.delivery_days)),
+        })
+    }
+}
+
+// ================================================================
+// Herramienta
+// ================================================================
+
+/// Ubicación de una dirección: código postal, coordenadas o ambos.
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Default, Clone)]
+pub struct Location {
+    pub postal_code: Option<String>,
+    pub lat: Option<f64>,
+    pub lng: Option<f64>,
+    /// País en ISO 3166-1 alfa-2 (por defecto "MX").
+    pub country: Option<String>,
+}
+
+impl Location {
+    fn country(&self) -> &str {
+        self.country.as_deref().unwrap_or(DEFAULT_COUNTRY)
+    }
+
+    fn postal_code(&self) -> Option<&str> {
+        self.postal_code
+            .as_deref()
+            .map(str::trim)
+            .filter(|code| !code.is_empty())
+    }
+
+    /// Las coordenadas indicadas o, si faltan, las del código postal en el gazetteer.
+    fn point(&self) -> Option<GeoPoint> {
+        match (self.lat, self.lng) {
+            (Some(lat), Some(lng)) => Some(GeoPoint { lat, lng }),
+            _ => self
+                .postal_code()
+                .and_then(|code| gazetteer::get().lookup(self.country(), code))
+                .map(|place| place.point()),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub struct ShippingArgs {
+    /// Ubicación de la dirección actual, si se conoce.
+    pub current: Option<Location>,
+    /// Ubicación de la dirección nueva.
+    pub new: Location,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShippingError {
+    #[error("Indica el código postal o las coordenadas de la dirección nueva")]
+    MissingLocation,
+
+    #[error("La dirección nueva está fuera de las zonas de envío")]
+    NoCoverage,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ZoneRef {
+    pub id: String,
+    pub name: String,
+}
+
+impl From<&Zone> for ZoneRef {
+    fn from(zone: &Zone) -> Self {
+        Self {
+            id: zone.id.clone(),
+            name: zone.name.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ShippingQuote {
+    /// Zona de la dirección actual (vacía si no se conoce).
+    pub from: Option<ZoneRef>,
+    pub to: ZoneRef,
+    /// Regla de la tabla que fijó el recargo.
+    pub rule: String,
+    pub surcharge: f64,
+    pub currency: String,
+    /// Días hábiles de entrega a la dirección nueva.
+    pub delivery_days: u32,
+    /// Diferencia con la entrega a la dirección actual (positiva = más tarde).
+    pub delivery_days_change: Option<i64>,
+}
+
+pub struct ShippingZoneCalculator {
+    turn: Arc<TurnContext>,
+}
+
+impl ShippingZoneCalculator {
+    pub fn new(turn: Arc<TurnContext>) -> Self {
+        Self { turn }
+    }
+}
+
+impl rig::tool::Tool for ShippingZoneCalculator {
+    const NAME: &'static str = "shipping_zone_calculator";
+
+    type Error = ShippingError;
+    type Args = ShippingArgs;
+    type Output = ShippingQuote;
+
+    async fn definition(&self, _prompt: String) -> rig::completion::ToolDefinition {
+        rig::completion::ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Calcula la zona de envío, el recargo y el tiempo de entrega de cambiar \
+                          un envío de la dirección actual a la nueva."
+                .to_string(),
+            parameters: serde_json::to_value(schemars::schema_for!(ShippingArgs)).unwrap(),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let output = get().quote(args.current.as_ref(), &args.new)?;
+        self.turn.record_tool(Self::NAME, &output);
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn postal_code(code: &str) -> Location {
+        Location {
+            postal_code: Some(code.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_zones_by_prefix_and_radius() {
+        let table = get();
+        let zone = |location: &Location| table.zone_for(location).map(|z| z.id.as_str());
+
+        assert_eq!(zone(&postal_code("06700")), Some("valle_de_mexico"));
+        // Sin prefijo propio: la zona sale de las coordenadas del gazetteer.
+        assert_eq!(zone(&postal_code("45050")), Some("guadalajara"));
+        let near_monterrey = Location {
+            lat: Some(25.70),
+            lng: Some(-100.25),
+            ..Default::default()
+        };
+        assert_eq!(zone(&near_monterrey), Some("monterrey"));
+        assert_eq!(zone(&postal_code("97000")), Some("nacional"));
+
+        let abroad = Location {
+            country: Some("US".to_string()),
+            ..postal_code("78701")
+        };
+        assert!(zone(&abroad).is_none());
+    }
+
+    #[test]
+    fn test_quote_applies_first_matching_rule() {
+        let table = get();
+
+        let same = table
+            .quote(Some(&postal_code("06700")), &postal_code("03100"))
+            .unwrap();
+        assert_eq!((same.rule.as_str(), same.surcharge), ("Misma zona", 0.0));
+        assert_eq!(same.delivery_days_change, Some(0));
+
+        let national = table
+            .quote(Some(&postal_code("06700")), &postal_code("97000"))
+            .unwrap();
+        assert_eq!(national.surcharge, 180.0);
+        assert_eq!(national.delivery_days, 5);
+        assert_eq!(national.delivery_days_change, Some(4));
+
+        let metro = table.quote(None, &postal_code("44100")).unwrap();
+        assert_eq!(metro.surcharge, 120.0);
+        assert_eq!(metro.delivery_days_change, None);
+
+        assert!(matches!(
+            table.quote(None, &Location::default()),
+            Err(ShippingError::MissingLocation)
+        ));
+    }
+
+    #[test]
+    fn test_parse_validates_rules() {
+        let zones = "currency = \"MXN\"\n[[zones]]\nid = \"a\"\nname = \"A\"\ndelivery_days = 1\n";
+        let unknown = format!(
+            "{}[[rules]]\nname = \"r\"\nto = \"b\"\nsurcharge = 1.0\n",
+            zones
+        );
+        assert!(ShippingTable::parse(&unknown)
+            .unwrap_err()
+            .to_string()
+            .contains("unknown zone 'b'"));
+
+        let no_default = format!(
+            "{}[[rules]]\nname = \"r\"\nto = \"a\"\nsurcharge = 1.0\n",
+            zones
+        );
+        assert!(ShippingTable::parse(&no_default).is_err());
+
+        let valid = format!("{}[[rules]]\nname = \"r\"\nsurcharge = 1.0\n", zones);
+        assert!(ShippingTable::parse(&valid).is_ok());
+    }
+}
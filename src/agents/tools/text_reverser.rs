@@ -1,5 +1,7 @@
+use crate::agents::turn::TurnContext;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 #[derive(Deserialize, Serialize, JsonSchema)]
 pub struct ReverserArgs {
@@ -10,7 +12,15 @@ pub struct ReverserArgs {
 #[error("Error reversing")]
 pub struct ReverserError;
 
-pub struct TextReverser;
+pub struct TextReverser {
+    turn: Arc<TurnContext>,
+}
+
+impl TextReverser {
+    pub fn new(turn: Arc<TurnContext>) -> Self {
+        Self { turn }
+    }
+}
 
 impl rig::tool::Tool for TextReverser {
     const NAME: &'static str = "text_reverser";
@@ -30,6 +40,9 @@ impl rig::tool::Tool for TextReverser {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        Ok(args.text.chars().rev().collect())
+        let output: String = args.text.chars().rev().collect();
+        self.turn.record_tool(Self::NAME, &output);
+
+        Ok(output)
     }
 }
@@ -0,0 +1,228 @@
+//! # Contexto de Turno
+//!
+//! Estado compartido entre el Orquestador, los especialistas y las herramientas
+//! durante un único turno de conversación.
+//!
+//! Rig ejecuta cada herramienta dentro de la tarea de su `ToolServer`, por lo
+//! que ni los task-locals ni el contexto del span actual llegan hasta ellas.
+//! Por eso el grafo de agentes se construye en cada turno (ver
+//! `Orchestrator::chat`) y el `TurnContext` se inyecta explícitamente.
+//!
+//! Por la misma razón, soltar el futuro del turno (timeout o cliente que se
+//! desconecta) no detiene a los especialistas que ya están corriendo: el
+//! turno lleva un `CancellationToken` que `Instrumented` observa.
+
+use crate::infra::audit::AuditScope;
+use crate::infra::usage::{self, ModelUsage, TurnUsage};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+/// Resultado de una herramienta ejecutada durante el turno.
+#[derive(Debug, Clone)]
+pub struct ToolRecord {
+    pub tool: &'static str,
+    pub output: serde_json::Value,
+}
+
+#[derive(Default)]
+pub struct TurnContext {
+    session_id: String,
+    /// `None` en los tests.
+    audit: Option<AuditScope>,
+    tool_outputs: Mutex<Vec<ToolRecord>>,
+    usage: Mutex<Vec<ModelUsage>>,
+    timeouts: Mutex<Vec<&'static str>>,
+    subjects: Mutex<Vec<String>>,
+    tickets: Mutex<Vec<String>>,
+    cancellation: CancellationToken,
+}
+
+impl TurnContext {
+    /// Turno sin auditoría: tareas en segundo plano (resúmenes) y tests.
+    pub fn new(session_id: &str) -> Arc<Self> {
+        Arc::new(Self {
+            session_id: session_id.to_string(),
+            ..Self::default()
+        })
+    }
+
+    /// Turno de un caller: las herramientas marcadas con
+    /// `Instrumented::audited` registran sus invocaciones (ver `infra/audit.rs`).
+    pub fn with_audit(session_id: &str, audit: AuditScope) -> Arc<Self> {
+        Arc::new(Self {
+            session_id: session_id.to_string(),
+            audit: Some(audit),
+            ..Self::default()
+        })
+    }
+
+    pub fn audit(&self) -> Option<&AuditScope> {
+        self.audit.as_ref()
+    }
+
+    /// Sesión a la que pertenece el turno (se propaga a los spans como `session.id`).
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Cancela las herramientas y especialistas que sigan en curso.
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+
+    /// Se resuelve cuando el turno se cancela.
+    pub async fn cancelled(&self) {
+        self.cancellation.cancelled().await
+    }
+
+    /// Guard que cancela el turno al soltarse. El handler lo mantiene vivo
+    /// mientras atiende la petición: si el cliente se desconecta, axum suelta
+    /// el futuro del handler y con él el guard.
+    pub fn cancel_on_drop(&self) -> DropGuard {
+        self.cancellation.clone().drop_guard()
+    }
+
+    /// Registra una herramienta o especialista que excedió su tiempo límite.
+    pub fn record_timeout(&self, tool: &'static str) {
+        self.timeouts
+            .lock()
+            .expect("turn context poisoned")
+            .push(tool);
+    }
+
+    /// Herramientas y especialistas que excedieron su tiempo límite, en orden.
+    pub fn timeouts(&self) -> Vec<&'static str> {
+        self.timeouts.lock().expect("turn context poisoned").clone()
+    }
+
+    /// Registra la salida de una herramienta. Lo usan las herramientas hoja
+    /// (`CostDatabase`, `GeoCoding`, ...) para que los guardrails de salida
+    /// puedan contrastar la respuesta final con los datos reales.
+    pub fn record_tool<T: Serialize>(&self, tool: &'static str, output: &T) {
+        let output = serde_json::to_value(output).unwrap_or(serde_json::Value::Null);
+        self.tool_outputs
+            .lock()
+            .expect("turn context poisoned")
+            .push(ToolRecord { tool, output });
+    }
+
+    /// Salidas registradas por la herramienta `tool`, en orden de ejecución.
+    pub fn tool_outputs(&self, tool: &str) -> Vec<serde_json::Value> {
+        self.tool_outputs
+            .lock()
+            .expect("turn context poisoned")
+            .iter()
+            .filter(|record| record.tool == tool)
+            .map(|record| record.output.clone())
+            .collect()
+    }
+
+    /// Registra un titular de datos mencionado en el turno (ej. el
+    /// `customer_id` de un cambio de dirección) para indexar la sesión bajo
+    /// su id (ver `infra/redis/subjects.rs`).
+    pub fn record_subject(&self, subject_id: &str) {
+        let subject_id = subject_id.trim();
+        let mut subjects = self.subjects.lock().expect("turn context poisoned");
+        if !subject_id.is_empty() && !subjects.iter().any(|s| s == subject_id) {
+            subjects.push(subject_id.to_string());
+        }
+    }
+
+    /// Titulares registrados en el turno, en orden.
+    pub fn subjects(&self) -> Vec<String> {
+        self.subjects.lock().expect("turn context poisoned").clone()
+    }
+
+    /// Registra un ticket emitido durante el turno (ej. `DMG-1234`).
+    pub fn record_ticket(&self, ticket_id: &str) {
+        let mut tickets = self.tickets.lock().expect("turn context poisoned");
+        if !tickets.iter().any(|t| t == ticket_id) {
+            tickets.push(ticket_id.to_string());
+        }
+    }
+
+    /// Tickets emitidos durante el turno, en orden.
+    pub fn tickets(&self) -> Vec<String> {
+        self.tickets.lock().expect("turn context poisoned").clone()
+    }
+
+    /// Acumula el consumo de una completion. Lo llama `AnyModel` en cada
+    /// llamada, tanto del orquestador como de los especialistas.
+    pub fn record_usage(&self, provider: &str, model: &str, input_tokens: u64, output_tokens: u64) {
+        let cost_usd = usage::get().cost(model, input_tokens, output_tokens);
+        let mut models = self.usage.lock().expect("turn context poisoned");
+
+        match models
+            .iter_mut()
+            .find(|m| m.provider == provider && m.model == model)
+        {
+            Some(entry) => {
+                entry.calls += 1;
+                entry.input_tokens += input_tokens;
+                entry.output_tokens += output_tokens;
+                entry.cost_usd += cost_usd;
+            }
+            None => models.push(ModelUsage {
+                provider: provider.to_string(),
+                model: model.to_string(),
+                calls: 1,
+                input_tokens,
+                output_tokens,
+                cost_usd,
+            }),
+        }
+    }
+
+    /// Consumo del turno hasta el momento, por modelo.
+    pub fn usage(&self) -> TurnUsage {
+        TurnUsage::new(self.usage.lock().expect("turn context poisoned").clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_are_filtered_by_tool() {
+        let turn = TurnContext::new("session-1");
+        turn.record_tool("cost_database", &serde_json::json!({ "price": 10.0 }));
+        turn.record_tool(
+            "geocoding_service",
+            &serde_json::json!({ "zip_code": "06000" }),
+        );
+        turn.record_tool("cost_database", &serde_json::json!({ "price": 20.0 }));
+
+        let prices = turn.tool_outputs("cost_database");
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices[1]["price"], 20.0);
+    }
+
+    #[test]
+    fn test_usage_is_aggregated_per_model() {
+        let turn = TurnContext::new("session-1");
+        turn.record_usage("gemini", "gemini-2.5-pro", 1_000, 100);
+        turn.record_usage("gemini", "gemini-2.5-flash", 500, 50);
+        turn.record_usage("gemini", "gemini-2.5-pro", 2_000, 200);
+
+        let usage = turn.usage();
+        assert_eq!(usage.models.len(), 2);
+        assert_eq!(usage.models[0].calls, 2);
+        assert_eq!(usage.models[0].input_tokens, 3_000);
+        assert_eq!(usage.input_tokens, 3_500);
+        assert_eq!(usage.output_tokens, 350);
+        assert!(usage.cost_usd > 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_drop_guard_cancels_turn() {
+        let turn = TurnContext::new("session-1");
+        let guard = turn.cancel_on_drop();
+        drop(guard);
+
+        tokio::time::timeout(std::time::Duration::from_millis(10), turn.cancelled())
+            .await
+            .expect("turn should be cancelled");
+    }
+}
@@ -0,0 +1,110 @@
+//! # Enrutamiento de Variantes (A/B)
+//!
+//! Mantiene un `Orchestrator` por variante configurada y asigna cada sesión
+//! a una de ellas de forma determinista según el hash de su `session_id`,
+//! respetando los pesos configurados. Una misma sesión siempre cae en la
+//! misma variante mientras no cambien los pesos.
+
+use super::config::AgentVariant;
+use super::orchestrator::Orchestrator;
+use crate::infra::hash::stable_hash;
+use std::sync::Arc;
+
+/// Variante asignada a un turno.
+#[derive(Clone)]
+pub struct AssignedVariant {
+    pub name: String,
+    /// Versión de los prompts/ajustes de la variante (ver `AgentsConfig::version`).
+    pub prompt_version: String,
+    pub orchestrator: Arc<Orchestrator>,
+}
+
+pub struct VariantRouter {
+    variants: Vec<(u32, AssignedVariant)>,
+    total_weight: u32,
+}
+
+impl VariantRouter {
+    pub fn new(variants: &[AgentVariant]) -> Self {
+        let variants: Vec<(u32, AssignedVariant)> = variants
+            .iter()
+            .map(|variant| {
+                (
+                    variant.weight,
+                    AssignedVariant {
+                        name: variant.name.clone(),
+                        prompt_version: variant.config.version(),
+                        orchestrator: Arc::new(Orchestrator::new(&variant.config)),
+                    },
+                )
+            })
+            .collect();
+        let total_weight = variants.iter().map(|(weight, _)| weight).sum();
+
+        Self {
+            variants,
+            total_weight,
+        }
+    }
+
+    /// Asigna la variante correspondiente a `session_id`.
+    pub fn assign(&self, session_id: &str) -> AssignedVariant {
+        let index = pick_index(
+            self.variants.iter().map(|(weight, _)| *weight),
+            self.total_weight,
+            session_id,
+        );
+        self.variants[index].1.clone()
+    }
+
+    /// Todas las variantes, en el orden configurado.
+    pub fn variants(&self) -> impl Iterator<Item = &AssignedVariant> {
+        self.variants.iter().map(|(_, variant)| variant)
+    }
+}
+
+/// Elige el índice de la variante cuyo rango de pesos contiene el bucket de la sesión.
+fn pick_index(weights: impl Iterator<Item = u32>, total_weight: u32, session_id: &str) -> usize {
+    if total_weight == 0 {
+        return 0;
+    }
+
+    let bucket = (stable_hash(session_id.as_bytes()) % u64::from(total_weight)) as u32;
+    let mut upper = 0;
+    for (index, weight) in weights.enumerate() {
+        upper += weight;
+        if bucket < upper {
+            return index;
+        }
+    }
+
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assignment_is_deterministic() {
+        let weights = [50, 50];
+        let first = pick_index(weights.into_iter(), 100, "session-abc");
+        let second = pick_index(weights.into_iter(), 100, "session-abc");
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_assignment_respects_weights() {
+        let weights = [90, 10];
+        let hits = (0..10_000)
+            .filter(|i| pick_index(weights.into_iter(), 100, &format!("session-{}", i)) == 1)
+            .count();
+        assert!((700..1300).contains(&hits), "hits = {}", hits);
+    }
+
+    #[test]
+    fn test_zero_weight_variant_is_never_assigned() {
+        let weights = [0, 1];
+        assert!((0..100).all(|i| pick_index(weights.into_iter(), 1, &i.to_string()) == 1));
+    }
+}
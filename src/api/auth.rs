@@ -0,0 +1,127 @@
+use crate::infra::errors::DomainError;
+use axum::{
+    extract::Request,
+    http::{header::AUTHORIZATION, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::OnceLock;
+
+/// Caller usado cuando `API_KEYS` no está configurado.
+const ANONYMOUS_CALLER: &str = "anonymous";
+
+/// Quién hace la petición: el nombre asociado a su API key. Se usa para
+/// contabilizar consumo y aplicar presupuestos, nunca se expone la key.
+#[derive(Debug, Clone)]
+pub struct Caller(pub String);
+
+/// API keys de `API_KEYS` (`nombre=key;...`) como pares `(nombre, key)`.
+fn api_keys() -> &'static [(String, String)] {
+    static KEYS: OnceLock<Vec<(String, String)>> = OnceLock::new();
+    KEYS.get_or_init(|| parse_api_keys(crate::config::get().server.api_keys.expose()))
+}
+
+fn parse_api_keys(spec: &str) -> Vec<(String, String)> {
+    spec.split(';')
+        .filter(|entry| !entry.trim().is_empty())
+        .filter_map(|entry| {
+            let parsed = entry
+                .split_once('=')
+                .map(|(name, key)| (name.trim().to_string(), key.trim().to_string()))
+                .filter(|(name, key)| !name.is_empty() && !key.is_empty());
+            if parsed.is_none() {
+                tracing::warn!("Ignoring invalid API key entry");
+            }
+            parsed
+        })
+        .collect()
+}
+
+/// Todos los callers posibles: los nombres de `API_KEYS` y `anonymous`.
+pub fn caller_names() -> Vec<String> {
+    api_keys()
+        .iter()
+        .map(|(name, _)| name.clone())
+        .chain([ANONYMOUS_CALLER.to_string()])
+        .collect()
+}
+
+/// `name` es un caller (el nombre de una API key o `anonymous`), no un usuario.
+pub fn is_caller_name(name: &str) -> bool {
+    name == ANONYMOUS_CALLER || api_keys().iter().any(|(caller, _)| caller == name)
+}
+
+/// Middleware para `/chat`: identifica al caller por el header `X-API-Key`.
+/// Si `API_KEYS` está vacío, el acceso es libre y el caller es `anonymous`.
+pub async fn identify_caller(mut request: Request, next: Next) -> Result<Response, DomainError> {
+    let keys = api_keys();
+
+    let caller = if keys.is_empty() {
+        ANONYMOUS_CALLER.to_string()
+    } else {
+        let provided = request
+            .headers()
+            .get("x-api-key")
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| DomainError::unauthorized("Falta el header X-API-Key"))?;
+
+        keys.iter()
+            .find(|(_, key)| constant_time_eq(provided.as_bytes(), key.as_bytes()))
+            .map(|(name, _)| name.clone())
+            .ok_or_else(|| DomainError::unauthorized("API key inválida"))?
+    };
+
+    request.extensions_mut().insert(Caller(caller));
+    Ok(next.run(request).await)
+}
+
+/// Middleware para las rutas `/admin/*`: exige `Authorization: Bearer {ADMIN_TOKEN}`.
+/// Si `ADMIN_TOKEN` no está configurado, las rutas de administración quedan deshabilitadas.
+pub async fn require_admin(request: Request, next: Next) -> Result<Response, DomainError> {
+    let expected = crate::config::get().server.admin_token.expose();
+
+    if expected.is_empty() {
+        return Err(DomainError::unauthorized(
+            "Los endpoints de administración están deshabilitados",
+        ));
+    }
+
+    match bearer_token(request.headers()) {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => {
+            Ok(next.run(request).await)
+        }
+        _ => Err(DomainError::unauthorized(
+            "Token de administración inválido",
+        )),
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_api_keys() {
+        let keys = parse_api_keys("web=sk-web; mobile = sk-mobile ;roto;=sin-nombre");
+        assert_eq!(
+            keys,
+            vec![
+                ("web".to_string(), "sk-web".to_string()),
+                ("mobile".to_string(), "sk-mobile".to_string()),
+            ]
+        );
+    }
+}
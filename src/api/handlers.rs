@@ -1,23 +1,121 @@
 use crate::{
-    api::request::{ChatRequest, ChatResponse, FileAttachment},
+    agents::{
+        tools::catalog::{self, Catalog},
+        turn::TurnContext,
+    },
+    api::auth::{self, Caller},
+    api::request::{
+        AuditQuery, AuditResponse, ChatRequest, ChatResponse, ChatTrace, FeedbackExportQuery,
+        FeedbackRequest, FileAttachment, KeyRotationQuery, ReloadResponse, SessionListQuery,
+        SessionListResponse, SessionUpdateRequest, UsageQuery, UsageReport,
+    },
+    config::HistoryReadPolicy,
+    guardrails::output::{turn_quotes, OutputContext},
     infra::{
-        errors::{DomainError, DomainResult},
-        redis::{ChatMessage, Role},
+        audit::{AuditAction, AuditEvent, AuditFilter, AuditScope},
+        errors::{DomainError, DomainResult, LlmKind},
+        health::{self, CheckResult, Readiness},
+        metrics,
+        redis::{
+            feedback::Feedback,
+            sessions::{SessionCursor, SessionFilter, SessionRef, SessionStatus},
+            unix_now, ChatMessage, Role,
+        },
+        usage,
     },
     state::AppState,
 };
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{timeout_at, Instant};
 use uuid::Uuid;
 
-pub async fn health_check() -> impl IntoResponse {
+pub async fn health_check(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    if state.lifecycle.is_draining() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "DRAINING");
+    }
     (StatusCode::OK, "OK")
 }
 
+/// Liveness: sólo indica que el proceso responde.
+pub async fn liveness_check() -> impl IntoResponse {
+    (StatusCode::OK, "OK")
+}
+
+/// Readiness: revisa Redis, la configuración y los proveedores LLM
+/// (ver `infra/health.rs`). Responde 503 si falla alguna dependencia crítica.
+pub async fn readiness_check(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    if state.lifecycle.is_draining() {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(Readiness::draining()));
+    }
+
+    let agents = state.agents();
+    let redis = async {
+        let start = Instant::now();
+        let result = state.redis.ping().await;
+        CheckResult::critical(result.map_err(|e| format!("{:#}", e)), start.elapsed())
+    };
+    let providers = async {
+        let mut checks = Vec::new();
+        for provider in health::providers_in_use(&agents) {
+            let check = state.provider_probes.check(provider).await;
+            checks.push((format!("provider:{}", provider.as_str()), check));
+        }
+        checks
+    };
+    let (redis, providers) = tokio::join!(redis, providers);
+
+    let mut checks = std::collections::BTreeMap::new();
+    checks.insert("redis".to_string(), redis);
+    checks.insert("config".to_string(), health::check_config(&agents));
+    checks.extend(providers);
+
+    let readiness = Readiness::new(checks);
+    if !readiness.is_ready() {
+        tracing::warn!(status = ?readiness.status, "Readiness check failed");
+    }
+    let status = if readiness.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness))
+}
+
+pub async fn metrics_handler() -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::get().render(),
+    )
+}
+
+#[tracing::instrument(
+    name = "chat",
+    skip_all,
+    fields(
+        session_id = tracing::field::Empty,
+        caller = tracing::field::Empty,
+        variant = tracing::field::Empty,
+        input_flags = tracing::field::Empty,
+        output_action = tracing::field::Empty,
+        timeout = tracing::field::Empty,
+        history = tracing::field::Empty
+    )
+)]
 pub async fn chat_handler(
     State(state): State<Arc<AppState>>,
+    Extension(Caller(caller)): Extension<Caller>,
     Json(payload): Json<ChatRequest>,
 ) -> Result<impl IntoResponse, DomainError> {
+    let deadline =
+        Instant::now() + Duration::from_secs(crate::config::get().limits.chat_timeout_secs);
     let prompt = validate_prompt(&payload.prompt)?;
     let files = validate_files(payload.files)?;
 
@@ -26,38 +124,618 @@ pub async fn chat_handler(
         .clone()
         .unwrap_or_else(|| Uuid::new_v4().to_string());
 
-    let history = state
-        .redis
-        .get_history(&session_id)
-        .await
-        .unwrap_or_default();
+    let variant = state.agents().assign(&session_id);
+    metrics::get().touch_session(&session_id);
 
-    let response_text = state.orchestrator.chat(&prompt, history, files).await;
+    let span = tracing::Span::current();
+    span.record("session_id", session_id.as_str());
+    span.record("caller", caller.as_str());
+    span.record("variant", variant.name.as_str());
 
-    let new_messages = vec![
-        ChatMessage {
-            role: Role::User,
-            content: prompt,
+    check_budget(&state, &caller).await?;
+
+    let turn = TurnContext::with_audit(
+        &session_id,
+        AuditScope {
+            log: state.audit.clone(),
+            caller: caller.clone(),
+            variant: variant.name.clone(),
+            prompt_version: variant.prompt_version.clone(),
         },
-        ChatMessage {
-            role: Role::Assistant,
-            content: response_text.clone(),
+    );
+    // Si el cliente se desconecta, axum suelta este futuro y el guard cancela
+    // los especialistas que sigan corriendo en sus `ToolServer`.
+    let _cancel_on_drop = turn.cancel_on_drop();
+    let mut usage_recorder = UsageRecorder::new(&state, &turn, &caller);
+
+    let input_check = timeout_at(deadline, state.input_guardrails.check(&prompt, &turn))
+        .await
+        .map_err(|_| turn_timeout(&span, "input_guardrails"))?;
+    if let Some(blocked) = input_check.blocked {
+        tracing::warn!(
+            guard = blocked.guard,
+            category = %blocked.category,
+            reason = %blocked.reason,
+            "Prompt blocked by input guardrails"
+        );
+        return Err(DomainError::validation(
+            "La solicitud fue bloqueada por las políticas de seguridad",
+        )
+        .with_data(blocked));
+    }
+    if !input_check.flags.is_empty() {
+        let categories: Vec<&str> = input_check
+            .flags
+            .iter()
+            .map(|f| f.category.as_str())
+            .collect();
+        span.record("input_flags", categories.join(",").as_str());
+    }
+    for flag in &input_check.flags {
+        tracing::warn!(
+            guard = flag.guard,
+            category = %flag.category,
+            reason = %flag.reason,
+            "Prompt flagged by input guardrails"
+        );
+    }
+
+    let (history, meta, history_degraded) = match state.redis.get_conversation(&session_id).await {
+        Ok((history, meta)) => (history, meta, false),
+        Err(e) => match crate::config::get().store.history_read_policy {
+            HistoryReadPolicy::Fail => return Err(e.into()),
+            HistoryReadPolicy::Degrade => {
+                tracing::warn!(
+                    "Failed to read chat history, continuing without it: {:#}",
+                    e
+                );
+                span.record("history", "degraded");
+                (Vec::new(), None, true)
+            }
         },
+    };
+
+    // Un caller no puede continuar sesiones creadas con otra API key.
+    if meta.as_ref().is_some_and(|meta| meta.caller != caller) {
+        return Err(DomainError::session_not_found(&session_id));
+    }
+    let session = SessionRef::new(
+        &session_id,
+        meta.as_ref(),
+        &caller,
+        payload.user_id.as_deref(),
+    );
+
+    let (response_text, output_review) = timeout_at(deadline, async {
+        let draft = variant
+            .orchestrator
+            .chat(&turn, &prompt, history, files)
+            .await;
+
+        let output_context = OutputContext::new(
+            &prompt,
+            &turn,
+            &session.quoted_prices,
+            variant.orchestrator.config(),
+        );
+        state
+            .output_guardrails
+            .review(draft, &output_context, &turn)
+            .await
+    })
+    .await
+    .map_err(|_| {
+        turn.cancel();
+        turn_timeout(&span, "orchestrator")
+    })?;
+    if let Some(action) = output_review.action {
+        span.record(
+            "output_action",
+            format!("{:?}", action).to_lowercase().as_str(),
+        );
+    }
+    for violation in &output_review.violations {
+        tracing::warn!(
+            policy = violation.policy,
+            action = ?violation.action,
+            reason = %violation.reason,
+            "Response violated output policy"
+        );
+    }
+
+    let assistant_message =
+        ChatMessage::new(Role::Assistant, response_text.clone()).with_variant(&variant.name);
+    let message_id = assistant_message.id.clone();
+    usage_recorder.request_id = message_id.clone();
+
+    let new_messages = vec![
+        ChatMessage::new(Role::User, prompt).with_variant(&variant.name),
+        assistant_message,
     ];
 
-    if let Err(e) = state.redis.add_messages(&session_id, new_messages).await {
-        tracing::warn!("Failed to save chat history: {}", e);
+    let session = session
+        .with_links(turn.subjects(), turn.tickets())
+        .with_quotes(turn_quotes(&turn));
+    let before = meta.as_ref().map_or(0, |meta| meta.message_count);
+    let after = before + new_messages.len() as u64;
+    match state.redis.add_messages(&session, new_messages).await {
+        Ok(()) => {
+            if let Some(summarizer) = &state.summarizer {
+                if summarizer.is_due(before, after) {
+                    summarizer.spawn(&state.lifecycle, state.redis.clone(), session_id.clone());
+                }
+            }
+        }
+        Err(e) => tracing::warn!("Failed to save chat history: {}", e),
     }
 
+    let turn_usage = turn.usage();
+
     Ok((
         StatusCode::OK,
         Json(ChatResponse {
             response: response_text,
             session_id,
+            message_id,
+            trace: ChatTrace {
+                variant: variant.name,
+                prompt_version: variant.prompt_version,
+                input_flags: input_check.flags,
+                output_review,
+                usage: turn_usage,
+                timeouts: turn.timeouts(),
+                history_degraded,
+            },
+        }),
+    ))
+}
+
+/// Guarda el consumo del turno al soltarse, así que también se registra cuando
+/// el turno termina por timeout, error o desconexión del cliente. La escritura
+/// corre como tarea de fondo que el drain espera.
+struct UsageRecorder {
+    state: Arc<AppState>,
+    turn: Arc<TurnContext>,
+    caller: String,
+    /// Id del mensaje del asistente si el turno respondió; si no, uno propio.
+    request_id: String,
+}
+
+impl UsageRecorder {
+    fn new(state: &Arc<AppState>, turn: &Arc<TurnContext>, caller: &str) -> Self {
+        Self {
+            state: state.clone(),
+            turn: turn.clone(),
+            caller: caller.to_string(),
+            request_id: Uuid::new_v4().to_string(),
+        }
+    }
+}
+
+impl Drop for UsageRecorder {
+    fn drop(&mut self) {
+        let usage = self.turn.usage();
+        if usage.models.is_empty() {
+            return;
+        }
+
+        let redis = self.state.redis.clone();
+        let session_id = self.turn.session_id().to_string();
+        let caller = std::mem::take(&mut self.caller);
+        let request_id = std::mem::take(&mut self.request_id);
+        self.state.lifecycle.spawn(async move {
+            if let Err(e) = redis
+                .record_turn_usage(&request_id, &session_id, &caller, &usage)
+                .await
+            {
+                tracing::warn!(session_id, "Failed to record token usage: {}", e);
+            }
+        });
+    }
+}
+
+/// El turno superó `CHAT_TIMEOUT_SECS`. `stage` indica en qué etapa ocurrió.
+fn turn_timeout(span: &tracing::Span, stage: &'static str) -> DomainError {
+    let limit = crate::config::get().limits.chat_timeout_secs;
+    span.record("timeout", stage);
+    tracing::warn!(stage, limit, "Chat turn timed out");
+    DomainError::llm(
+        LlmKind::Timeout,
+        format!("La solicitud excedió el tiempo límite de {}s", limit),
+    )
+}
+
+/// Rechaza el turno si el caller ya agotó su presupuesto mensual. Si Redis no
+/// responde, el turno continúa: el presupuesto no debe tumbar el servicio.
+async fn check_budget(state: &AppState, caller: &str) -> Result<(), DomainError> {
+    let Some(limit) = usage::get().budget(caller) else {
+        return Ok(());
+    };
+
+    let spent = match state.redis.monthly_cost(caller, unix_now()).await {
+        Ok(spent) => spent,
+        Err(e) => {
+            tracing::warn!("Failed to read monthly usage, skipping budget check: {}", e);
+            return Ok(());
+        }
+    };
+
+    if spent >= limit {
+        tracing::warn!(caller, spent, limit, "Monthly budget exceeded");
+        return Err(
+            DomainError::budget_exceeded("Se alcanzó el presupuesto mensual de uso")
+                .with_data(serde_json::json!({ "spent_usd": spent, "budget_usd": limit })),
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn reload_agents_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, DomainError> {
+    state.reload_agents().map_err(|e| {
+        DomainError::internal(format!("No se pudo recargar la configuración: {:#}", e))
+    })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ReloadResponse {
+            status: "reloaded".to_string(),
+        }),
+    ))
+}
+
+/// Re-envuelve las data keys de las sesiones con la master key activa o, con
+/// `reencrypt=true`, las reemplaza y re-cifra el contenido de cada sesión,
+/// incluidas las guardadas en claro (ver `infra/encryption.rs`).
+pub async fn rotate_encryption_keys_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<KeyRotationQuery>,
+) -> Result<impl IntoResponse, DomainError> {
+    let report = if query.reencrypt {
+        state
+            .redis
+            .reencrypt_sessions(&auth::caller_names())
+            .await?
+    } else {
+        state.redis.rotate_data_keys().await?
+    };
+    Ok((StatusCode::OK, Json(report)))
+}
+
+/// Reemplaza el catálogo de precios de `cost_database` en todas las
+/// instancias (ver `agents/tools/catalog.rs`). Con `Content-Type: text/csv`
+/// el cuerpo es CSV; si no, un arreglo JSON de artículos.
+pub async fn import_catalog_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<impl IntoResponse, DomainError> {
+    let is_csv = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/csv"));
+    let catalog = if is_csv {
+        Catalog::from_csv(&body)
+    } else {
+        Catalog::from_json(&body)
+    }
+    .map_err(|e| DomainError::validation(format!("Catálogo inválido: {}", e)))?;
+
+    let import = state.redis.save_catalog(&catalog).await?;
+    catalog::replace(catalog);
+    tracing::info!(items = import.items, version = %import.version, "Catalog imported");
+
+    let event = AuditEvent::new(AuditAction::CatalogImport, "POST /admin/catalog", "admin")
+        .with_arguments(&serde_json::json!({
+            "format": if is_csv { "csv" } else { "json" },
+            "items": import.items,
+        }))
+        .with_result(&import);
+    state.audit.record(event).await;
+
+    Ok((StatusCode::OK, Json(import)))
+}
+
+pub async fn feedback_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(Caller(caller)): Extension<Caller>,
+    Path((session_id, message_id)): Path<(String, String)>,
+    Json(payload): Json<FeedbackRequest>,
+) -> Result<impl IntoResponse, DomainError> {
+    let comment = validate_comment(payload.comment)?;
+
+    // Sólo el caller que creó la sesión puede valorarla: las valoraciones
+    // ajenas sesgarían la comparación de variantes.
+    let (history, meta) = state.redis.get_conversation(&session_id).await?;
+    if history.is_empty() || meta.is_none_or(|meta| meta.caller != caller) {
+        return Err(DomainError::session_not_found(&session_id));
+    }
+
+    let message = history
+        .iter()
+        .find(|m| !m.id.is_empty() && m.id == message_id)
+        .ok_or_else(|| DomainError::not_found(format!("Mensaje '{}' no encontrado", message_id)))?;
+
+    if message.role != Role::Assistant {
+        return Err(DomainError::validation(
+            "Sólo se puede valorar mensajes del asistente",
+        ));
+    }
+
+    let feedback = Feedback {
+        session_id,
+        message_id,
+        rating: payload.rating,
+        category: payload.category,
+        comment,
+        variant: message.variant.clone(),
+        created_at: unix_now(),
+    };
+
+    state.redis.save_feedback(&feedback).await?;
+
+    tracing::info!(
+        target: "feedback",
+        session_id = %feedback.session_id,
+        message_id = %feedback.message_id,
+        rating = ?feedback.rating,
+        category = ?feedback.category,
+        variant = feedback.variant.as_deref().unwrap_or_default(),
+        has_comment = feedback.comment.is_some(),
+        "User feedback received"
+    );
+
+    Ok((StatusCode::CREATED, Json(feedback)))
+}
+
+/// Sesiones del caller, de la más reciente a la más antigua, filtradas por
+/// dueño, estado y actividad. Se pagina con el `next_cursor` de la respuesta.
+pub async fn list_sessions_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(Caller(caller)): Extension<Caller>,
+    Query(query): Query<SessionListQuery>,
+) -> Result<impl IntoResponse, DomainError> {
+    let limit = query.limit.unwrap_or(20);
+    if !(1..=100).contains(&limit) {
+        return Err(DomainError::validation("'limit' debe estar entre 1 y 100"));
+    }
+    let cursor: Option<SessionCursor> = query.cursor.as_deref().map(str::parse).transpose()?;
+
+    let filter = SessionFilter {
+        owner: query.owner,
+        status: query.status,
+        since: query.since,
+    };
+    let (sessions, next_cursor) = state
+        .redis
+        .list_sessions(&caller, &filter, limit, cursor.as_ref())
+        .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(SessionListResponse {
+            sessions,
+            next_cursor: next_cursor.map(|c| c.to_string()),
         }),
     ))
 }
 
+/// Cambia el estado, el título o las etiquetas de una sesión del caller. Al
+/// cerrarla se regenera su resumen (ver `agents/summary.rs`).
+pub async fn update_session_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(Caller(caller)): Extension<Caller>,
+    Path(session_id): Path<String>,
+    Json(payload): Json<SessionUpdateRequest>,
+) -> Result<impl IntoResponse, DomainError> {
+    let title = payload.title.map(|t| t.trim().to_string());
+    if title.as_ref().is_some_and(|t| t.chars().count() > 200) {
+        return Err(DomainError::validation(
+            "El título excede el límite de 200 caracteres",
+        ));
+    }
+    let tags = payload.tags.map(validate_tags).transpose()?;
+
+    let meta = state
+        .redis
+        .update_session(&session_id, &caller, payload.status, title, tags)
+        .await?;
+
+    if payload.status == Some(SessionStatus::Escalated) {
+        let event = AuditEvent::new(AuditAction::Escalation, "PATCH /sessions/{id}", &caller)
+            .with_session(&session_id)
+            .with_arguments(&serde_json::json!({ "status": SessionStatus::Escalated }));
+        state.audit.record(event).await;
+    }
+
+    // Al cerrar, el resumen se regenera si quedaron mensajes sin resumir.
+    let stale = meta
+        .summary
+        .as_ref()
+        .is_none_or(|summary| summary.message_count < meta.message_count);
+    if payload.status == Some(SessionStatus::Closed) && stale {
+        if let Some(summarizer) = &state.summarizer {
+            summarizer.spawn(&state.lifecycle, state.redis.clone(), session_id);
+        }
+    }
+
+    Ok((StatusCode::OK, Json(meta)))
+}
+
+fn validate_tags(tags: Vec<String>) -> DomainResult<Vec<String>> {
+    if tags.len() > 20 {
+        return Err(DomainError::validation(
+            "No se pueden asignar más de 20 etiquetas",
+        ));
+    }
+
+    let mut cleaned: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() || tag.chars().count() > 50 {
+            return Err(DomainError::validation(
+                "Las etiquetas deben tener entre 1 y 50 caracteres",
+            ));
+        }
+        if !cleaned.contains(&tag) {
+            cleaned.push(tag);
+        }
+    }
+
+    Ok(cleaned)
+}
+
+/// Exporta en JSON todo lo guardado de un titular: las sesiones de las que es
+/// dueño con su historial, metadatos y feedback, y sus tickets (ver
+/// `infra/redis/subjects.rs`).
+pub async fn export_subject_handler(
+    State(state): State<Arc<AppState>>,
+    Path(subject_id): Path<String>,
+) -> Result<impl IntoResponse, DomainError> {
+    validate_subject(&subject_id)?;
+    let export = state.redis.export_subject(&subject_id).await?;
+
+    Ok((
+        StatusCode::OK,
+        [(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"subject-export.json\"",
+        )],
+        Json(export),
+    ))
+}
+
+/// Borra todo lo guardado de un titular y responde con la constancia del borrado.
+pub async fn erase_subject_handler(
+    State(state): State<Arc<AppState>>,
+    Path(subject_id): Path<String>,
+) -> Result<impl IntoResponse, DomainError> {
+    validate_subject(&subject_id)?;
+    let erasure = state.redis.erase_subject(&subject_id).await?;
+
+    let event = AuditEvent::new(
+        AuditAction::SubjectErasure,
+        "DELETE /subjects/{id}",
+        "admin",
+    )
+    .with_arguments(&serde_json::json!({ "subject_id": subject_id }))
+    .with_result(&erasure);
+    state.audit.record(event).await;
+
+    Ok((StatusCode::OK, Json(erasure)))
+}
+
+/// Un caller agrupa a todos los usuarios de su API key: exportar o borrar sus
+/// datos alcanzaría a personas distintas del titular.
+fn validate_subject(subject_id: &str) -> Result<(), DomainError> {
+    if auth::is_caller_name(subject_id) {
+        return Err(DomainError::validation(format!(
+            "'{}' es el nombre de un caller, no un titular de datos",
+            subject_id
+        )));
+    }
+    Ok(())
+}
+
+/// Eventos de auditoría, del más reciente al más antiguo, filtrados por
+/// rango de fechas, acción, caller y sesión.
+pub async fn audit_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AuditQuery>,
+) -> Result<impl IntoResponse, DomainError> {
+    let limit = query.limit.unwrap_or(100);
+    if !(1..=500).contains(&limit) {
+        return Err(DomainError::validation("'limit' debe estar entre 1 y 500"));
+    }
+    let to = query.to.unwrap_or_else(unix_now);
+    let from = query.from.unwrap_or(0);
+    if from > to {
+        return Err(DomainError::validation("'from' debe ser anterior a 'to'"));
+    }
+
+    let filter = AuditFilter {
+        from,
+        to,
+        action: query.action,
+        caller: query.caller,
+        session_id: query.session_id,
+        limit,
+    };
+    let events = state.audit.query(&filter).await?;
+
+    Ok((StatusCode::OK, Json(AuditResponse { events })))
+}
+
+/// Consumo de tokens y costo agregado por sesión, caller, modelo o día.
+pub async fn usage_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<UsageQuery>,
+) -> Result<impl IntoResponse, DomainError> {
+    let to = query.to.unwrap_or_else(unix_now);
+    let from = query.from.unwrap_or_else(|| usage::month_start(to));
+    if from > to {
+        return Err(DomainError::validation("'from' debe ser anterior a 'to'"));
+    }
+
+    let records = state.redis.usage_records(from, to).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(UsageReport {
+            from,
+            to,
+            group_by: query.group_by,
+            total: usage::totals(&records),
+            groups: usage::aggregate(&records, query.group_by),
+        }),
+    ))
+}
+
+/// Exporta el feedback como NDJSON (una línea por valoración, con su conversación),
+/// listo para revisión offline o para construir datasets de evaluación.
+pub async fn feedback_export_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FeedbackExportQuery>,
+) -> Result<impl IntoResponse, DomainError> {
+    let since = query.since.unwrap_or(0);
+    let until = query.until.unwrap_or_else(unix_now);
+
+    let exports = state.redis.export_feedback(since, until).await?;
+
+    let mut body = String::new();
+    for export in exports {
+        body.push_str(&serde_json::to_string(&export)?);
+        body.push('\n');
+    }
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        body,
+    ))
+}
+
+fn validate_comment(comment: Option<String>) -> DomainResult<Option<String>> {
+    let Some(comment) = comment else {
+        return Ok(None);
+    };
+
+    let trimmed = comment.trim();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+
+    if trimmed.chars().count() > 2_000 {
+        return Err(DomainError::validation(
+            "El comentario excede el límite de 2,000 caracteres",
+        ));
+    }
+
+    Ok(Some(trimmed.to_string()))
+}
+
 fn validate_prompt(prompt: &str) -> DomainResult<String> {
     let trimmed = prompt.trim();
 
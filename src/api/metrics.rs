@@ -0,0 +1,41 @@
+use crate::infra::errors::ErrorCode;
+use crate::infra::metrics;
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::time::Instant;
+
+/// Middleware que registra conteo y latencia de cada petición por ruta
+/// (plantilla de la ruta, no la URL concreta), método y status.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = request.method().to_string();
+    let start = Instant::now();
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16().to_string();
+    let error_code = response
+        .extensions()
+        .get::<ErrorCode>()
+        .map(|code| code.0)
+        .unwrap_or("");
+
+    let metrics = metrics::get();
+    metrics
+        .http_requests
+        .with_label_values(&[route.as_str(), method.as_str(), status.as_str(), error_code])
+        .inc();
+    metrics
+        .http_duration
+        .with_label_values(&[route.as_str(), method.as_str(), status.as_str()])
+        .observe(start.elapsed().as_secs_f64());
+
+    response
+}
@@ -1,3 +1,6 @@
+pub mod auth;
 pub mod handlers;
+pub mod metrics;
 pub mod request;
 pub mod routes;
+pub mod shutdown;
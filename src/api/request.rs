@@ -1,3 +1,9 @@
+use crate::guardrails::output::OutputReview;
+use crate::guardrails::GuardVerdict;
+use crate::infra::audit::{AuditAction, AuditEvent};
+use crate::infra::redis::feedback::{FeedbackCategory, Rating};
+use crate::infra::redis::sessions::{SessionMeta, SessionStatus};
+use crate::infra::usage::{GroupBy, TurnUsage, UsageGroup, UsageTotals};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -10,6 +16,9 @@ pub struct FileAttachment {
 pub struct ChatRequest {
     pub prompt: String,
     pub session_id: Option<String>,
+    /// Cliente final que conversa; queda como dueño de la sesión si es nueva.
+    /// Por defecto, el caller.
+    pub user_id: Option<String>,
     pub files: Option<Vec<FileAttachment>>,
 }
 
@@ -17,4 +26,125 @@ pub struct ChatRequest {
 pub struct ChatResponse {
     pub response: String,
     pub session_id: String,
+    /// Id del mensaje del asistente, para enviar feedback sobre él.
+    pub message_id: String,
+    pub trace: ChatTrace,
+}
+
+/// Información de diagnóstico del turno.
+#[derive(Serialize)]
+pub struct ChatTrace {
+    /// Variante de agentes (A/B) asignada a la sesión.
+    pub variant: String,
+    /// Versión de los prompts/ajustes de esa variante.
+    pub prompt_version: String,
+    /// Hallazgos de los guardrails de entrada que no bloquearon el turno.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub input_flags: Vec<GuardVerdict>,
+    /// Políticas de salida incumplidas y la acción aplicada a la respuesta.
+    #[serde(skip_serializing_if = "OutputReview::is_clean")]
+    pub output_review: OutputReview,
+    /// Tokens y costo del turno (orquestador y especialistas), por modelo.
+    pub usage: TurnUsage,
+    /// Especialistas y herramientas que excedieron su tiempo límite.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub timeouts: Vec<&'static str>,
+    /// El historial no pudo leerse y el turno se atendió sin contexto
+    /// (`HISTORY_READ_POLICY=degrade`).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub history_degraded: bool,
+}
+
+#[derive(Serialize)]
+pub struct ReloadResponse {
+    pub status: String,
+}
+
+#[derive(Deserialize)]
+pub struct FeedbackRequest {
+    pub rating: Rating,
+    pub category: Option<FeedbackCategory>,
+    pub comment: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct FeedbackExportQuery {
+    /// Inicio del rango (segundos epoch). Por defecto, desde siempre.
+    pub since: Option<u64>,
+    /// Fin del rango (segundos epoch). Por defecto, ahora.
+    pub until: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct UsageQuery {
+    /// Inicio del rango (segundos epoch). Por defecto, el inicio del mes en curso.
+    pub from: Option<u64>,
+    /// Fin del rango (segundos epoch). Por defecto, ahora.
+    pub to: Option<u64>,
+    /// `session`, `caller` (por defecto), `model` o `day`.
+    #[serde(default)]
+    pub group_by: GroupBy,
+}
+
+#[derive(Serialize)]
+pub struct UsageReport {
+    pub from: u64,
+    pub to: u64,
+    pub group_by: GroupBy,
+    pub total: UsageTotals,
+    pub groups: Vec<UsageGroup>,
+}
+
+#[derive(Deserialize)]
+pub struct SessionListQuery {
+    pub owner: Option<String>,
+    pub status: Option<SessionStatus>,
+    /// Sólo sesiones con actividad desde este instante (segundos epoch).
+    pub since: Option<u64>,
+    /// Sesiones por página (1-100). Por defecto, 20.
+    pub limit: Option<usize>,
+    /// `next_cursor` de la página anterior.
+    pub cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SessionListResponse {
+    pub sessions: Vec<SessionMeta>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SessionUpdateRequest {
+    pub status: Option<SessionStatus>,
+    /// Título visible en el listado; vacío lo elimina.
+    pub title: Option<String>,
+    /// Reemplaza las etiquetas de la sesión.
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct KeyRotationQuery {
+    /// Reemplaza las data keys y re-cifra las sesiones, incluidas las
+    /// guardadas en claro. Por defecto, sólo se re-envuelven.
+    #[serde(default)]
+    pub reencrypt: bool,
+}
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    /// Inicio del rango (segundos epoch). Por defecto, desde siempre.
+    pub from: Option<u64>,
+    /// Fin del rango (segundos epoch). Por defecto, ahora.
+    pub to: Option<u64>,
+    pub action: Option<AuditAction>,
+    pub caller: Option<String>,
+    pub session_id: Option<String>,
+    /// Eventos a devolver (1-500). Por defecto, 100.
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct AuditResponse {
+    pub events: Vec<AuditEvent>,
 }
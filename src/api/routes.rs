@@ -1,7 +1,16 @@
-use super::handlers::{chat_handler, health_check};
+use super::auth::{identify_caller, require_admin};
+use super::handlers::{
+    audit_handler, chat_handler, erase_subject_handler, export_subject_handler,
+    feedback_export_handler, feedback_handler, health_check, import_catalog_handler,
+    list_sessions_handler, liveness_check, metrics_handler, readiness_check, reload_agents_handler,
+    rotate_encryption_keys_handler, update_session_handler, usage_handler,
+};
+use super::metrics::track_requests;
+use super::shutdown::track_in_flight;
 use crate::state::AppState;
 use axum::{
-    routing::{get, post},
+    middleware,
+    routing::{delete, get, patch, post},
     Router,
 };
 use std::sync::Arc;
@@ -18,9 +27,59 @@ pub fn app_router(state: Arc<AppState>) -> Router {
         .allow_methods(Any)
         .allow_headers(Any);
 
+    let in_flight = middleware::from_fn_with_state(state.clone(), track_in_flight);
+
+    let admin = Router::new()
+        .route("/reload", post(reload_agents_handler))
+        .route("/feedback/export", get(feedback_export_handler))
+        .route("/encryption/rotate", post(rotate_encryption_keys_handler))
+        .route("/audit", get(audit_handler))
+        .route("/catalog", post(import_catalog_handler))
+        .route_layer(middleware::from_fn(require_admin));
+
+    let usage = Router::new()
+        .route("/usage", get(usage_handler))
+        .route_layer(middleware::from_fn(require_admin));
+
+    let subjects = Router::new()
+        .route(
+            "/subjects/{id}",
+            delete(erase_subject_handler).route_layer(in_flight.clone()),
+        )
+        .route("/subjects/{id}/export", get(export_subject_handler))
+        .route_layer(middleware::from_fn(require_admin));
+
     Router::new()
         .route("/health", get(health_check))
-        .route("/chat", post(chat_handler))
+        .route("/health/live", get(liveness_check))
+        .route("/health/ready", get(readiness_check))
+        .route("/metrics", get(metrics_handler))
+        .route(
+            "/chat",
+            post(chat_handler)
+                .route_layer(middleware::from_fn(identify_caller))
+                .route_layer(in_flight.clone()),
+        )
+        .route(
+            "/sessions",
+            get(list_sessions_handler).route_layer(middleware::from_fn(identify_caller)),
+        )
+        .route(
+            "/sessions/{id}",
+            patch(update_session_handler)
+                .route_layer(middleware::from_fn(identify_caller))
+                .route_layer(in_flight.clone()),
+        )
+        .route(
+            "/sessions/{id}/messages/{message_id}/feedback",
+            post(feedback_handler)
+                .route_layer(middleware::from_fn(identify_caller))
+                .route_layer(in_flight),
+        )
+        .merge(usage)
+        .merge(subjects)
+        .nest("/admin", admin)
+        .layer(middleware::from_fn(track_requests))
         .layer(TraceLayer::new_for_http())
         .layer(CompressionLayer::new())
         .layer(cors)
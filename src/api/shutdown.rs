@@ -0,0 +1,25 @@
+use crate::infra::errors::DomainError;
+use crate::state::AppState;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+/// Middleware para las rutas que escriben en la sesión (`/chat`, feedback):
+/// durante el drain rechaza peticiones nuevas y, mientras tanto, registra las
+/// que están en curso para que el apagado espere a que se persistan.
+pub async fn track_in_flight(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, DomainError> {
+    if state.lifecycle.is_draining() {
+        return Err(DomainError::service_unavailable(
+            "El servicio se está deteniendo, intenta de nuevo",
+        ));
+    }
+
+    Ok(state.lifecycle.track(next.run(request)).await)
+}
@@ -0,0 +1,850 @@
+//! # Configuración del servicio
+//!
+//! Se construye por capas; cada una sobrescribe a la anterior:
+//!
+//! 1. Valores por defecto (el `Default` de cada sección).
+//! 2. Archivo TOML del entorno: `config/{entorno}.toml` (`lcl`, `sbx`, `prd`),
+//!    o el indicado con `CONFIG_FILE` / `--config`. El entorno sale de
+//!    `APP_ENV` / `--env` (por defecto `LCL`). Sin archivo se omite la capa.
+//! 3. Variables de entorno (y `.env`), con los nombres de `ENV_VARS`. Todas
+//!    aceptan la variante `{VARIABLE}_FILE` con la ruta de un archivo que
+//!    contiene el valor, para secretos montados como volumen (Secret Manager).
+//! 4. Flags de línea de comandos: `--seccion.clave=valor` (ej. `--server.port=9090`).
+//!
+//! El resultado se valida al arrancar (`init`) y se informan todos los
+//! errores juntos en lugar de fallar en el primero.
+//!
+//! ```toml
+//! # config/prd.toml
+//! [store]
+//! base_path = "PRD"
+//!
+//! [telemetry]
+//! backend = "gcp"
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const ENVIRONMENTS: [&str; 3] = ["LCL", "SBX", "PRD"];
+const DEFAULT_ENVIRONMENT: &str = "LCL";
+const CONFIG_DIR: &str = "config";
+
+/// Variables de entorno y la clave (`seccion.clave`) que sobrescriben.
+const ENV_VARS: &[(&str, &str)] = &[
+    ("PORT", "server.port"),
+    ("SHUTDOWN_GRACE_SECS", "server.shutdown_grace_secs"),
+    ("SERVICE_NAME", "server.service_name"),
+    ("PROJECT_ID", "server.project_id"),
+    ("ADMIN_TOKEN", "server.admin_token"),
+    ("API_KEYS", "server.api_keys"),
+    ("HEALTH_PROBE_TTL_SECS", "server.health_probe_ttl_secs"),
+    ("REDIS_URL", "store.redis_url"),
+    ("REDIS_TOPOLOGY", "store.topology"),
+    ("REDIS_SENTINEL_SERVICE", "store.sentinel_service"),
+    ("REDIS_RECONNECT_RETRIES", "store.reconnect_retries"),
+    (
+        "REDIS_RECONNECT_MAX_DELAY_MS",
+        "store.reconnect_max_delay_ms",
+    ),
+    ("HISTORY_READ_POLICY", "store.history_read_policy"),
+    ("REDIS_BASE_PATH", "store.base_path"),
+    ("SESSION_TTL", "store.session_ttl"),
+    ("HISTORY_MAX_MESSAGES", "store.history_max_messages"),
+    ("REDIS_TIMEOUT_MS", "store.timeout_ms"),
+    ("USAGE_RETENTION_DAYS", "store.usage_retention_days"),
+    ("AUDIT_SINK", "store.audit_sink"),
+    ("AUDIT_FILE", "store.audit_file"),
+    ("GEMINI_API_KEY", "models.gemini_api_key"),
+    ("OPENAI_API_KEY", "models.openai_api_key"),
+    ("ANTHROPIC_API_KEY", "models.anthropic_api_key"),
+    ("MODEL_PRICES", "models.prices"),
+    ("AGENTS_CONFIG_DIR", "agents.config_dir"),
+    ("AGENTS_WATCH_INTERVAL", "agents.watch_interval"),
+    ("SUMMARY_MODEL", "agents.summary_model"),
+    ("SUMMARY_AFTER_TURNS", "agents.summary_after_turns"),
+    ("GEOCODER", "agents.geocoder"),
+    ("GEOCODER_URL", "agents.geocoder_url"),
+    ("GAZETTEER_FILE", "agents.gazetteer_file"),
+    ("SHIPPING_ZONES_FILE", "agents.shipping_zones_file"),
+    ("CATALOG_FILE", "agents.catalog_file"),
+    (
+        "CATALOG_REFRESH_INTERVAL",
+        "agents.catalog_refresh_interval",
+    ),
+    ("GUARDRAILS_ENABLED", "guardrails.enabled"),
+    ("GUARDRAILS_CLASSIFIER_MODEL", "guardrails.classifier_model"),
+    ("GUARDRAILS_DENYLIST", "guardrails.denylist"),
+    ("OUTPUT_GUARDRAILS_POLICIES", "guardrails.output_policies"),
+    (
+        "OUTPUT_GUARDRAILS_REWRITE_MODEL",
+        "guardrails.rewrite_model",
+    ),
+    ("PII_DETECTORS", "privacy.pii_detectors"),
+    ("PII_REDACT_LOGS", "privacy.redact_logs"),
+    ("PII_REDACT_HISTORY", "privacy.redact_history"),
+    ("PII_REDACT_EXPORTS", "privacy.redact_exports"),
+    ("ENCRYPTION_KEYRING", "privacy.encryption_keyring"),
+    ("DEBUG_LEVEL", "telemetry.level"),
+    ("TELEMETRY_BACKEND", "telemetry.backend"),
+    ("OTEL_EXPORTER_OTLP_PROTOCOL", "telemetry.otlp_protocol"),
+    ("OTEL_EXPORTER_OTLP_ENDPOINT", "telemetry.otlp_endpoint"),
+    ("GENAI_CAPTURE_CONTENT", "telemetry.genai_capture_content"),
+    ("CHAT_TIMEOUT_SECS", "limits.chat_timeout_secs"),
+    ("SPECIALIST_TIMEOUT_SECS", "limits.specialist_timeout_secs"),
+    ("TOOL_TIMEOUT_SECS", "limits.tool_timeout_secs"),
+    ("USAGE_BUDGETS", "limits.usage_budgets"),
+];
+
+// ============================================================================
+// 1. SECCIONES
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub server: ServerSection,
+    pub store: StoreSection,
+    pub models: ModelsSection,
+    pub agents: AgentsSection,
+    pub guardrails: GuardrailsSection,
+    pub privacy: PrivacySection,
+    pub telemetry: TelemetrySection,
+    pub limits: LimitsSection,
+    /// Entorno (`LCL`, `SBX`, `PRD`) y archivo cargado, para los logs de arranque.
+    #[serde(skip)]
+    pub environment: String,
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSection {
+    pub port: u16,
+    /// Plazo para terminar los turnos en curso tras SIGTERM/SIGINT.
+    pub shutdown_grace_secs: u64,
+    pub service_name: String,
+    pub project_id: String,
+    /// Bearer token de `/admin/*` y `/usage` (vacío = deshabilitados).
+    pub admin_token: Secret,
+    /// Callers de `/chat`: `nombre=key;...` (vacío = acceso libre).
+    pub api_keys: Secret,
+    /// Segundos que `/health/ready` reutiliza la sonda a cada proveedor.
+    pub health_probe_ttl_secs: u64,
+}
+
+impl Default for ServerSection {
+    fn default() -> Self {
+        Self {
+            port: 8080,
+            shutdown_grace_secs: 8,
+            service_name: "rustlant-agent-local".to_string(),
+            project_id: "local".to_string(),
+            admin_token: Secret::default(),
+            api_keys: Secret::default(),
+            health_probe_ttl_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StoreSection {
+    /// Puede incluir la contraseña de Redis. Con `sentinel` o `cluster`, lista
+    /// de URLs separadas por comas (sentinels o nodos semilla).
+    pub redis_url: Secret,
+    pub topology: RedisTopology,
+    /// Nombre del master monitoreado por los sentinels.
+    pub sentinel_service: String,
+    /// Reintentos de reconexión (backoff exponencial) antes de fallar el comando.
+    pub reconnect_retries: usize,
+    pub reconnect_max_delay_ms: u64,
+    pub history_read_policy: HistoryReadPolicy,
+    /// Prefijo de todas las claves (`LCL`, `SBX`, `PRD`).
+    pub base_path: String,
+    /// Tiempo de vida de las sesiones (segundos).
+    pub session_ttl: u64,
+    /// Mensajes que se conservan por sesión (los más antiguos se descartan);
+    /// `0` = sin límite.
+    pub history_max_messages: u64,
+    /// Tiempo límite de conexión y de cada operación.
+    pub timeout_ms: u64,
+    pub usage_retention_days: u64,
+    /// Dónde se escribe el registro de auditoría (ver `infra/audit.rs`).
+    pub audit_sink: AuditBackend,
+    /// Archivo JSONL del registro de auditoría con `audit_sink = "file"`.
+    pub audit_file: String,
+}
+
+impl Default for StoreSection {
+    fn default() -> Self {
+        Self {
+            redis_url: Secret::new("redis://default@localhost:6379"),
+            topology: RedisTopology::Standalone,
+            sentinel_service: "mymaster".to_string(),
+            reconnect_retries: 6,
+            reconnect_max_delay_ms: 2_000,
+            history_read_policy: HistoryReadPolicy::Fail,
+            base_path: "LCL".to_string(),
+            session_ttl: 86_400,
+            history_max_messages: 200,
+            timeout_ms: 2_000,
+            usage_retention_days: 90,
+            audit_sink: AuditBackend::Redis,
+            audit_file: "audit.jsonl".to_string(),
+        }
+    }
+}
+
+impl StoreSection {
+    pub fn redis_urls(&self) -> Vec<&str> {
+        self.redis_url
+            .expose()
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedisTopology {
+    Standalone,
+    Sentinel,
+    Cluster,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditBackend {
+    /// Stream `{base}:audit` en Redis.
+    Redis,
+    /// Archivo JSONL local (`store.audit_file`).
+    File,
+}
+
+/// Qué hacer si no se puede leer el historial al comenzar un turno.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryReadPolicy {
+    /// Responder `503 REDIS_*`: el turno sin contexto confundiría al usuario.
+    Fail,
+    /// Atender el turno sin historial (se marca en la traza).
+    Degrade,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelsSection {
+    pub gemini_api_key: Secret,
+    pub openai_api_key: Secret,
+    pub anthropic_api_key: Secret,
+    /// `modelo=entrada/salida;...` en USD por millón de tokens.
+    pub prices: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentsSection {
+    /// Directorio con prompts y `agents.toml` recargables (vacío = embebidos).
+    pub config_dir: String,
+    /// Segundos entre revisiones del directorio (0 = sin watcher).
+    pub watch_interval: u64,
+    /// Modelo (Gemini) que genera título y resumen de las sesiones (vacío = deshabilitado).
+    pub summary_model: String,
+    /// Turnos tras los que se genera el primer resumen; se regenera al cerrar la sesión.
+    pub summary_after_turns: u64,
+    /// Proveedor de `geocoding_service` (ver `agents/tools/geocoding.rs`).
+    pub geocoder: GeocoderBackend,
+    /// URL base del proveedor con `geocoder = "nominatim"`.
+    pub geocoder_url: String,
+    /// CSV de códigos postales y localidades (vacío = el embebido, ver
+    /// `agents/tools/gazetteer.rs`).
+    pub gazetteer_file: String,
+    /// Tabla TOML de zonas y recargos de envío (vacío = la embebida, ver
+    /// `agents/tools/shipping_zones.rs`).
+    pub shipping_zones_file: String,
+    /// Catálogo de artículos en CSV o JSON (vacío = el embebido, ver
+    /// `agents/tools/catalog.rs`).
+    pub catalog_file: String,
+    /// Segundos entre revisiones del catálogo importado en Redis (0 = sólo al arrancar).
+    pub catalog_refresh_interval: u64,
+}
+
+impl Default for AgentsSection {
+    fn default() -> Self {
+        Self {
+            config_dir: String::new(),
+            watch_interval: 10,
+            summary_model: "gemini-2.5-flash-lite".to_string(),
+            summary_after_turns: 3,
+            geocoder: GeocoderBackend::Gazetteer,
+            geocoder_url: "https://nominatim.openstreetmap.org".to_string(),
+            gazetteer_file: String::new(),
+            shipping_zones_file: String::new(),
+            catalog_file: String::new(),
+            catalog_refresh_interval: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeocoderBackend {
+    /// Gazetteer en memoria, sin red.
+    Gazetteer,
+    /// API de búsqueda de Nominatim (OpenStreetMap).
+    Nominatim,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GuardrailsSection {
+    pub enabled: bool,
+    pub classifier_model: String,
+    pub denylist: String,
+    pub output_policies: String,
+    pub rewrite_model: String,
+}
+
+impl Default for GuardrailsSection {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            classifier_model: String::new(),
+            denylist: String::new(),
+            output_policies:
+                "refund_cap=rewrite;promises=disclaimer;prompt_leak=fallback;language=rewrite"
+                    .to_string(),
+            rewrite_model: "gemini-2.5-flash".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PrivacySection {
+    pub pii_detectors: String,
+    pub redact_logs: bool,
+    pub redact_history: bool,
+    pub redact_exports: bool,
+    /// Master keys para cifrar el historial en reposo: `id=base64;...`, la
+    /// última es la activa (vacío = sin cifrado). Ver `infra/encryption.rs`.
+    pub encryption_keyring: Secret,
+}
+
+impl Default for PrivacySection {
+    fn default() -> Self {
+        Self {
+            pii_detectors: "email,phone,card,national_id,address,customer_id".to_string(),
+            redact_logs: true,
+            redact_history: true,
+            redact_exports: true,
+            encryption_keyring: Secret::default(),
+        }
+    }
+}
+
+/// Destino de logs y trazas (ver `infra/telemetry.rs`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TelemetryBackend {
+    /// Cloud Logging y Cloud Trace.
+    Gcp,
+    /// Logs JSON y trazas OTLP.
+    Otlp,
+    /// Logs legibles, sin trazas.
+    Console,
+    /// Logs JSON, sin trazas.
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetrySection {
+    /// Nivel de logs o directiva de `EnvFilter` (`INFO`, `service=debug`).
+    pub level: String,
+    pub backend: TelemetryBackend,
+    pub otlp_protocol: String,
+    /// Vacío = endpoint local según el protocolo (ver `otlp_endpoint()`).
+    pub otlp_endpoint: String,
+    pub genai_capture_content: bool,
+}
+
+impl Default for TelemetrySection {
+    fn default() -> Self {
+        Self {
+            level: "INFO".to_string(),
+            backend: TelemetryBackend::Gcp,
+            otlp_protocol: "grpc".to_string(),
+            otlp_endpoint: String::new(),
+            genai_capture_content: false,
+        }
+    }
+}
+
+impl TelemetrySection {
+    pub fn otlp_endpoint(&self) -> &str {
+        match (self.otlp_endpoint.as_str(), self.otlp_protocol.as_str()) {
+            ("", "http/protobuf") => "http://localhost:4318",
+            ("", _) => "http://localhost:4317",
+            (endpoint, _) => endpoint,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsSection {
+    pub chat_timeout_secs: u64,
+    pub specialist_timeout_secs: u64,
+    pub tool_timeout_secs: u64,
+    /// Presupuesto mensual en USD por caller: `caller=usd;...`.
+    pub usage_budgets: String,
+}
+
+impl Default for LimitsSection {
+    fn default() -> Self {
+        Self {
+            chat_timeout_secs: 120,
+            specialist_timeout_secs: 60,
+            tool_timeout_secs: 15,
+            usage_budgets: String::new(),
+        }
+    }
+}
+
+/// Valor sensible: no aparece en `Debug` (ni, por tanto, en los logs).
+#[derive(Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new<S: Into<String>>(value: S) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            f.write_str("Secret(\"\")")
+        } else {
+            f.write_str("Secret(***)")
+        }
+    }
+}
+
+// ============================================================================
+// 2. CARGA Y VALIDACIÓN
+// ============================================================================
+
+/// Todos los problemas encontrados al cargar la configuración.
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid configuration:\n  - {}", .0.join("\n  - "))]
+pub struct ConfigError(pub Vec<String>);
+
+static CONFIG: OnceLock<AppConfig> = OnceLock::new();
+
+/// Carga la configuración con los flags del proceso. Debe llamarse al
+/// arrancar, antes de cualquier `get()`.
+pub fn init() -> Result<&'static AppConfig, ConfigError> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = AppConfig::from_process(&args)?;
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+/// Configuración vigente. Sin `init` previo (tests) se carga sin flags.
+pub fn get() -> &'static AppConfig {
+    CONFIG.get_or_init(|| AppConfig::from_process(&[]).unwrap_or_else(|e| panic!("{}", e)))
+}
+
+impl AppConfig {
+    fn from_process(args: &[String]) -> Result<Self, ConfigError> {
+        dotenv::dotenv().ok();
+        let vars: HashMap<String, String> = std::env::vars().collect();
+        Self::load(&vars, args)
+    }
+
+    /// Aplica las capas sobre los valores por defecto y valida el resultado.
+    pub fn load(vars: &HashMap<String, String>, args: &[String]) -> Result<Self, ConfigError> {
+        let mut errors = Vec::new();
+        let flags = Flags::parse(args, &mut errors);
+
+        let environment = flags
+            .environment
+            .clone()
+            .or_else(|| vars.get("APP_ENV").cloned())
+            .unwrap_or_else(|| DEFAULT_ENVIRONMENT.to_string())
+            .to_uppercase();
+        if !ENVIRONMENTS.contains(&environment.as_str()) {
+            errors.push(format!(
+                "APP_ENV must be one of {}, got '{}'",
+                ENVIRONMENTS.join(", "),
+                environment
+            ));
+        }
+
+        let mut merged =
+            toml::Value::try_from(Self::default()).expect("Failed to serialize default config");
+        let defaults = merged.clone();
+
+        // Capa 2: archivo del entorno
+        let source = match flags
+            .file
+            .clone()
+            .or_else(|| vars.get("CONFIG_FILE").cloned())
+        {
+            Some(file) => Some(PathBuf::from(file)),
+            None => {
+                let path =
+                    Path::new(CONFIG_DIR).join(format!("{}.toml", environment.to_lowercase()));
+                path.exists().then_some(path)
+            }
+        };
+        if let Some(path) = &source {
+            match read_file_layer(path) {
+                Ok(layer) => merge_toml(&mut merged, layer),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        // Capa 3: variables de entorno
+        for (name, key) in ENV_VARS {
+            let file_name = format!("{}_FILE", name);
+            let value = match (vars.get(*name), vars.get(&file_name)) {
+                (Some(_), Some(_)) => {
+                    errors.push(format!("{} and {} are both set", name, file_name));
+                    continue;
+                }
+                (Some(value), None) => value.clone(),
+                (None, Some(path)) => match std::fs::read_to_string(path) {
+                    Ok(value) => value.trim().to_string(),
+                    Err(e) => {
+                        errors.push(format!("{}: failed to read {}: {}", file_name, path, e));
+                        continue;
+                    }
+                },
+                (None, None) => continue,
+            };
+            if let Err(e) = set_key(&mut merged, &defaults, key, &value) {
+                errors.push(format!("{}: {}", name, e));
+            }
+        }
+
+        // Capa 4: flags
+        for (key, value) in &flags.overrides {
+            if let Err(e) = set_key(&mut merged, &defaults, key, value) {
+                errors.push(format!("--{}: {}", key, e));
+            }
+        }
+
+        let mut config: Self = match merged.try_into() {
+            Ok(config) => config,
+            Err(e) => {
+                errors.push(format!("{}", e).trim().to_string());
+                return Err(ConfigError(errors));
+            }
+        };
+        config.environment = environment;
+        config.source = source;
+
+        errors.extend(config.validate());
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError(errors))
+        }
+    }
+
+    /// Reglas que el tipo no expresa. Devuelve todos los errores encontrados.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut check = |ok: bool, message: &str| {
+            if !ok {
+                errors.push(message.to_string());
+            }
+        };
+
+        check(self.server.port > 0, "server.port must be greater than 0");
+        let urls = self.store.redis_urls();
+        check(
+            !urls.is_empty()
+                && urls.iter().all(|url| {
+                    ["redis://", "rediss://", "redis+unix://", "unix://"]
+                        .iter()
+                        .any(|scheme| url.starts_with(scheme))
+                }),
+            "store.redis_url must start with redis://, rediss:// or unix://",
+        );
+        check(
+            self.store.topology != RedisTopology::Standalone || urls.len() <= 1,
+            "store.redis_url accepts a single URL with the standalone topology",
+        );
+        check(
+            !self.store.base_path.is_empty(),
+            "store.base_path must not be empty",
+        );
+        check(
+            self.store.session_ttl > 0,
+            "store.session_ttl must be greater than 0",
+        );
+        check(
+            self.store.timeout_ms > 0,
+            "store.timeout_ms must be greater than 0",
+        );
+        check(
+            self.store.audit_sink != AuditBackend::File || !self.store.audit_file.trim().is_empty(),
+            "store.audit_file must not be empty with the file audit sink",
+        );
+        check(
+            self.agents.geocoder != GeocoderBackend::Nominatim
+                || self.agents.geocoder_url.starts_with("http://")
+                || self.agents.geocoder_url.starts_with("https://"),
+            "agents.geocoder_url must start with http:// or https://",
+        );
+        check(
+            self.limits.chat_timeout_secs > 0
+                && self.limits.specialist_timeout_secs > 0
+                && self.limits.tool_timeout_secs > 0,
+            "limits.*_timeout_secs must be greater than 0",
+        );
+        check(
+            matches!(
+                self.telemetry.otlp_protocol.as_str(),
+                "grpc" | "http/protobuf"
+            ),
+            "telemetry.otlp_protocol must be grpc or http/protobuf",
+        );
+        let keyring = self.privacy.encryption_keyring.expose();
+        check(
+            keyring.trim().is_empty()
+                || crate::infra::encryption::LocalKeyring::parse(keyring).is_ok(),
+            "privacy.encryption_keyring must be id=base64 entries with 32-byte keys",
+        );
+        check(
+            tracing_subscriber::EnvFilter::try_new(&self.telemetry.level).is_ok(),
+            "telemetry.level is not a valid log level or filter",
+        );
+
+        errors
+    }
+}
+
+/// Flags de línea de comandos ya separados.
+#[derive(Default)]
+struct Flags {
+    environment: Option<String>,
+    file: Option<String>,
+    overrides: Vec<(String, String)>,
+}
+
+impl Flags {
+    /// Acepta `--clave=valor` y `--clave valor`.
+    fn parse(args: &[String], errors: &mut Vec<String>) -> Self {
+        let mut flags = Self::default();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let Some(flag) = arg.strip_prefix("--") else {
+                errors.push(format!("Unexpected argument '{}'", arg));
+                continue;
+            };
+            let (name, value) = match flag.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (flag, args.next().cloned()),
+            };
+            let Some(value) = value else {
+                errors.push(format!("--{} requires a value", name));
+                continue;
+            };
+
+            match name {
+                "env" => flags.environment = Some(value),
+                "config" => flags.file = Some(value),
+                key => flags.overrides.push((key.to_string(), value)),
+            }
+        }
+
+        flags
+    }
+}
+
+fn read_file_layer(path: &Path) -> Result<toml::Value, String> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    toml::from_str(&raw).map_err(|e| format!("Invalid {}: {}", path.display(), e.message()))
+}
+
+/// Asigna `value` a `key` (`seccion.clave`) con el tipo que tiene en los
+/// valores por defecto.
+fn set_key(
+    merged: &mut toml::Value,
+    defaults: &toml::Value,
+    key: &str,
+    value: &str,
+) -> Result<(), String> {
+    let Some((section, field)) = key.split_once('.') else {
+        return Err(format!("unknown key '{}'", key));
+    };
+    let Some(default) = defaults.get(section).and_then(|s| s.get(field)) else {
+        return Err(format!("unknown key '{}'", key));
+    };
+
+    let typed = match default {
+        toml::Value::Integer(_) => value
+            .trim()
+            .parse()
+            .map(toml::Value::Integer)
+            .map_err(|_| format!("must be a number, got '{}'", value))?,
+        toml::Value::Boolean(_) => value
+            .trim()
+            .parse()
+            .map(toml::Value::Boolean)
+            .map_err(|_| format!("must be true or false, got '{}'", value))?,
+        _ => toml::Value::String(value.to_string()),
+    };
+
+    if let Some(table) = merged.get_mut(section).and_then(|s| s.as_table_mut()) {
+        table.insert(field.to_string(), typed);
+    }
+    Ok(())
+}
+
+/// Combina dos documentos TOML: las tablas se mezclan clave a clave y el
+/// resto de valores de `overrides` reemplaza a los de `base`.
+pub fn merge_toml(base: &mut toml::Value, overrides: toml::Value) {
+    match (base, overrides) {
+        (toml::Value::Table(base), toml::Value::Table(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_toml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn args(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|a| a.to_string()).collect()
+    }
+
+    fn temp_file(contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("config-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let file = temp_file(
+            "[server]\nport = 9000\nservice_name = \"from-file\"\n\n[store]\nbase_path = \"SBX\"\n",
+        );
+        let config = AppConfig::load(
+            &vars(&[
+                ("CONFIG_FILE", file.to_str().unwrap()),
+                ("PORT", "9100"),
+                ("GUARDRAILS_ENABLED", "false"),
+            ]),
+            &args(&["--server.port=9200", "--env", "sbx"]),
+        )
+        .unwrap();
+
+        assert_eq!(config.environment, "SBX");
+        assert_eq!(config.server.port, 9200);
+        assert_eq!(config.server.service_name, "from-file");
+        assert_eq!(config.store.base_path, "SBX");
+        assert!(!config.guardrails.enabled);
+        assert_eq!(config.limits.chat_timeout_secs, 120);
+        assert_eq!(config.telemetry.otlp_endpoint(), "http://localhost:4317");
+    }
+
+    #[test]
+    fn test_secrets_from_files() {
+        let secret = temp_file("sk-secret\n");
+        let config = AppConfig::load(
+            &vars(&[("GEMINI_API_KEY_FILE", secret.to_str().unwrap())]),
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(config.models.gemini_api_key.expose(), "sk-secret");
+        assert!(!format!("{:?}", config).contains("sk-secret"));
+    }
+
+    #[test]
+    fn test_redis_topologies() {
+        let config = AppConfig::load(
+            &vars(&[
+                ("REDIS_TOPOLOGY", "sentinel"),
+                ("REDIS_URL", "redis://s1:26379, rediss://s2:26379"),
+                ("HISTORY_READ_POLICY", "degrade"),
+            ]),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(config.store.topology, RedisTopology::Sentinel);
+        assert_eq!(
+            config.store.redis_urls(),
+            vec!["redis://s1:26379", "rediss://s2:26379"]
+        );
+        assert_eq!(config.store.history_read_policy, HistoryReadPolicy::Degrade);
+
+        let error = AppConfig::load(
+            &vars(&[("REDIS_URL", "redis://a:6379,redis://b:6379")]),
+            &[],
+        )
+        .unwrap_err();
+        assert!(error.0[0].contains("single URL"), "{}", error);
+    }
+
+    #[test]
+    fn test_reports_all_errors() {
+        let file = temp_file("[server]\nprot = 1\n");
+        let error = AppConfig::load(
+            &vars(&[
+                ("CONFIG_FILE", file.to_str().unwrap()),
+                ("SESSION_TTL", "un-dia"),
+            ]),
+            &args(&["--server.nope=1"]),
+        )
+        .unwrap_err();
+        assert_eq!(error.0.len(), 3, "{}", error);
+        assert!(error.0[0].starts_with("SESSION_TTL"));
+        assert!(error.0[1].starts_with("--server.nope"));
+        assert!(error.0[2].contains("prot"));
+
+        let error = AppConfig::load(
+            &vars(&[("OTEL_EXPORTER_OTLP_PROTOCOL", "udp"), ("SESSION_TTL", "0")]),
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(error.0.len(), 2, "{}", error);
+
+        let error = AppConfig::load(&vars(&[("TELEMETRY_BACKEND", "datadog")]), &[]).unwrap_err();
+        assert!(error.0[0].contains("unknown variant"), "{}", error);
+    }
+}
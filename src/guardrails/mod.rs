@@ -0,0 +1,86 @@
+//! # Guardrails
+//!
+//! Controles de seguridad que se ejecutan alrededor del Orquestador.
+//! No conocen la API HTTP: reciben texto y devuelven veredictos; es el
+//! handler quien decide cómo traducirlos a errores o a la traza de respuesta.
+//!
+//! - `input`: se ejecuta antes de `Orchestrator::chat` (inyección, jailbreak, temas vetados).
+//! - `output`: se ejecuta sobre la respuesta (montos, promesas, fuga de prompts, idioma).
+
+pub mod input;
+pub mod output;
+
+use serde::Serialize;
+
+/// Acción resultante de un control. El orden importa: `Block` > `Flag` > `Allow`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GuardAction {
+    Allow,
+    Flag,
+    Block,
+}
+
+/// Resultado de un control individual.
+#[derive(Debug, Clone, Serialize)]
+pub struct GuardVerdict {
+    /// Nombre del control que emitió el veredicto.
+    pub guard: &'static str,
+    pub action: GuardAction,
+    /// Categoría del hallazgo (ej. `prompt_injection`, `denied_topic:weapons`).
+    pub category: String,
+    pub reason: String,
+}
+
+impl GuardVerdict {
+    pub fn allow(guard: &'static str) -> Self {
+        Self {
+            guard,
+            action: GuardAction::Allow,
+            category: String::new(),
+            reason: String::new(),
+        }
+    }
+
+    pub fn flag<C: Into<String>, R: Into<String>>(
+        guard: &'static str,
+        category: C,
+        reason: R,
+    ) -> Self {
+        Self {
+            guard,
+            action: GuardAction::Flag,
+            category: category.into(),
+            reason: reason.into(),
+        }
+    }
+
+    pub fn block<C: Into<String>, R: Into<String>>(
+        guard: &'static str,
+        category: C,
+        reason: R,
+    ) -> Self {
+        Self {
+            guard,
+            action: GuardAction::Block,
+            category: category.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Normaliza texto para comparaciones: minúsculas y sin acentos.
+pub fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' | 'ä' | 'â' => 'a',
+            'é' | 'è' | 'ë' | 'ê' => 'e',
+            'í' | 'ì' | 'ï' | 'î' => 'i',
+            'ó' | 'ò' | 'ö' | 'ô' => 'o',
+            'ú' | 'ù' | 'ü' | 'û' => 'u',
+            'ñ' => 'n',
+            other => other,
+        })
+        .collect()
+}
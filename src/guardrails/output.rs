@@ -0,0 +1,802 @@
+//! Guardrails de salida: se evalúan sobre la respuesta del Orquestador antes
+//! de entregarla al usuario y guardarla en el historial.
+//!
+//! ## Políticas incluidas
+//! - `refund_cap`: ningún monto supera lo cotizado en la sesión por `cost_database` o
+//!   `shipping_zone_calculator`.
+//! - `promises`: sin promesas no aprobadas (garantías, plazos, reembolsos "seguros").
+//! - `prompt_leak`: la respuesta no reproduce texto de los system prompts.
+//! - `language`: la respuesta está en el idioma del usuario.
+//!
+//! ## Acciones
+//! Cada política tiene una acción configurable (`OUTPUT_GUARDRAILS_POLICIES`).
+//! Se aplica la más severa de las violaciones: `disclaimer` < `rewrite` < `fallback`.
+//! Si la reescritura falla o sigue incumpliendo, se usa el fallback.
+//!
+//! Para añadir una política, implementa `OutputPolicy` y regístrala en `policy_by_name`.
+
+use super::normalize;
+use crate::agents::config::{AgentSettings, AgentsConfig};
+use crate::agents::tools::cost_database::CostDatabase;
+use crate::agents::tools::shipping_zones::ShippingZoneCalculator;
+use crate::agents::turn::TurnContext;
+use crate::agents::TurnAgent;
+use regex::{Regex, RegexSet};
+use rig::tool::Tool;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+pub const FALLBACK_RESPONSE: &str =
+    "Lo siento, no puedo darte una respuesta confiable en este momento. \
+Un agente de soporte revisará tu caso y te contactará.";
+
+pub const DISCLAIMER: &str =
+    "_Nota: cualquier reembolso, reemplazo o plazo mencionado está sujeto \
+a la validación final de nuestro equipo de soporte._";
+
+/// Acción aplicada a una respuesta que incumple una política, de menor a mayor severidad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputAction {
+    Disclaimer,
+    Rewrite,
+    Fallback,
+}
+
+impl OutputAction {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "disclaimer" => Some(OutputAction::Disclaimer),
+            "rewrite" => Some(OutputAction::Rewrite),
+            "fallback" => Some(OutputAction::Fallback),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyViolation {
+    pub policy: &'static str,
+    pub action: OutputAction,
+    pub reason: String,
+}
+
+/// Resultado de la revisión de salida; se expone en `trace.output_review`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OutputReview {
+    pub violations: Vec<PolicyViolation>,
+    /// Acción aplicada finalmente (puede escalar a `fallback` si la reescritura falla).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<OutputAction>,
+}
+
+impl OutputReview {
+    pub fn is_clean(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Datos del turno contra los que se valida la respuesta.
+pub struct OutputContext<'a> {
+    pub prompt: &'a str,
+    /// Montos cotizados en turnos anteriores de la sesión y en este.
+    pub quoted_prices: Vec<f64>,
+    /// System prompts de la variante que respondió.
+    pub system_prompts: Vec<&'a str>,
+}
+
+impl<'a> OutputContext<'a> {
+    /// `session_quotes` son los precios que la sesión guardó de turnos
+    /// anteriores (`SessionMeta::quoted_prices`).
+    pub fn new(
+        prompt: &'a str,
+        turn: &TurnContext,
+        session_quotes: &[f64],
+        config: &'a AgentsConfig,
+    ) -> Self {
+        Self {
+            prompt,
+            quoted_prices: session_quotes
+                .iter()
+                .copied()
+                .chain(turn_quotes(turn))
+                .collect(),
+            system_prompts: vec![
+                &config.orchestrator.preamble,
+                &config.address.preamble,
+                &config.damage.preamble,
+                &config.dummy.preamble,
+            ],
+        }
+    }
+}
+
+/// Montos cotizados durante el turno: precios de `cost_database` y recargos de
+/// `shipping_zone_calculator`. Se guardan en la sesión para que un turno
+/// posterior pueda volver a mencionarlos.
+pub fn turn_quotes(turn: &TurnContext) -> Vec<f64> {
+    let surcharges = turn
+        .tool_outputs(ShippingZoneCalculator::NAME)
+        .into_iter()
+        .filter_map(|output| output["surcharge"].as_f64());
+
+    turn.tool_outputs(CostDatabase::NAME)
+        .iter()
+        .flat_map(quoted_prices)
+        .chain(surcharges)
+        .collect()
+}
+
+/// Precios de reparación y reemplazo del mejor candidato de una respuesta de
+/// `cost_database`. Los demás candidatos son artículos que el cliente no
+/// confirmó: no amplían el tope.
+fn quoted_prices(output: &serde_json::Value) -> Vec<f64> {
+    let best = &output["candidates"][0];
+    [
+        &output["price"],
+        &best["repair_price"],
+        &best["replacement_price"],
+    ]
+    .into_iter()
+    .filter_map(serde_json::Value::as_f64)
+    .collect()
+}
+
+/// Una política que valida la respuesta final.
+pub trait OutputPolicy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Devuelve el motivo si la respuesta incumple la política.
+    fn check(&self, response: &str, ctx: &OutputContext) -> Option<String>;
+}
+
+// ============================================================================
+// 1. PIPELINE
+// ============================================================================
+
+pub struct OutputGuardrails {
+    policies: Vec<(Box<dyn OutputPolicy>, OutputAction)>,
+    rewriter: Option<TurnAgent>,
+}
+
+impl OutputGuardrails {
+    pub fn new(
+        policies: Vec<(Box<dyn OutputPolicy>, OutputAction)>,
+        rewriter: Option<TurnAgent>,
+    ) -> Self {
+        Self { policies, rewriter }
+    }
+
+    pub fn from_config() -> Self {
+        let config = crate::config::get();
+        if !config.guardrails.enabled {
+            return Self::new(Vec::new(), None);
+        }
+
+        let policies = config
+            .guardrails
+            .output_policies
+            .split(';')
+            .filter(|entry| !entry.trim().is_empty())
+            .filter_map(|entry| {
+                let parsed = entry.split_once('=').and_then(|(name, action)| {
+                    Some((policy_by_name(name.trim())?, OutputAction::parse(action)?))
+                });
+                if parsed.is_none() {
+                    tracing::warn!("Ignoring invalid output policy entry: {}", entry);
+                }
+                parsed
+            })
+            .collect();
+
+        let rewriter = (!config.guardrails.rewrite_model.is_empty()).then(|| {
+            let settings = AgentSettings {
+                model: config.guardrails.rewrite_model.clone(),
+                temperature: Some(0.2),
+                preamble: include_str!("rewrite_prompt.md").to_string(),
+                ..Default::default()
+            };
+            TurnAgent::new(settings)
+        });
+
+        Self::new(policies, rewriter)
+    }
+
+    fn evaluate(&self, response: &str, ctx: &OutputContext) -> Vec<PolicyViolation> {
+        self.policies
+            .iter()
+            .filter_map(|(policy, action)| {
+                policy.check(response, ctx).map(|reason| PolicyViolation {
+                    policy: policy.name(),
+                    action: *action,
+                    reason,
+                })
+            })
+            .collect()
+    }
+
+    /// Valida la respuesta y devuelve la versión a entregar junto con la revisión.
+    /// El consumo de la reescritura se registra en `turn`.
+    pub async fn review(
+        &self,
+        response: String,
+        ctx: &OutputContext<'_>,
+        turn: &Arc<TurnContext>,
+    ) -> (String, OutputReview) {
+        let violations = self.evaluate(&response, ctx);
+        let Some(action) = violations.iter().map(|v| v.action).max() else {
+            return (response, OutputReview::default());
+        };
+
+        let (final_response, applied) = match action {
+            OutputAction::Disclaimer => (with_disclaimer(&response), action),
+            OutputAction::Fallback => (FALLBACK_RESPONSE.to_string(), action),
+            OutputAction::Rewrite => match self.rewrite(&response, &violations, ctx, turn).await {
+                Some(rewritten) => {
+                    // La reescritura se vuelve a validar: sólo se toleran disclaimers.
+                    let remaining = self.evaluate(&rewritten, ctx);
+                    match remaining.iter().map(|v| v.action).max() {
+                        None => (rewritten, action),
+                        Some(OutputAction::Disclaimer) => (with_disclaimer(&rewritten), action),
+                        Some(_) => (FALLBACK_RESPONSE.to_string(), OutputAction::Fallback),
+                    }
+                }
+                None => (FALLBACK_RESPONSE.to_string(), OutputAction::Fallback),
+            },
+        };
+
+        let review = OutputReview {
+            violations,
+            action: Some(applied),
+        };
+        (final_response, review)
+    }
+
+    async fn rewrite(
+        &self,
+        response: &str,
+        violations: &[PolicyViolation],
+        ctx: &OutputContext<'_>,
+        turn: &Arc<TurnContext>,
+    ) -> Option<String> {
+        let rewriter = self.rewriter.as_ref()?;
+
+        let problems: Vec<String> = violations
+            .iter()
+            .map(|v| format!("- {}: {}", v.policy, v.reason))
+            .collect();
+        let max_amount = ctx
+            .quoted_prices
+            .iter()
+            .copied()
+            .reduce(f64::max)
+            .map(|max| format!("{:.2}", max))
+            .unwrap_or_else(|| "ninguno (no menciones montos)".to_string());
+
+        let prompt = format!(
+            "## Mensaje del usuario\n{}\n\n## Respuesta original\n{}\n\n## Problemas detectados\n{}\n\n## Monto máximo permitido\n{}",
+            ctx.prompt,
+            response,
+            problems.join("\n"),
+            max_amount
+        );
+
+        match rewriter.prompt(turn, &prompt).await {
+            Ok(rewritten) if !rewritten.trim().is_empty() => Some(rewritten.trim().to_string()),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("Output guardrail rewrite failed: {}", e);
+                None
+            }
+        }
+    }
+}
+
+fn with_disclaimer(response: &str) -> String {
+    format!("{}\n\n{}", response.trim_end(), DISCLAIMER)
+}
+
+fn policy_by_name(name: &str) -> Option<Box<dyn OutputPolicy>> {
+    match name {
+        "refund_cap" => Some(Box::new(RefundCapPolicy::new())),
+        "promises" => Some(Box::new(PromisePolicy::new())),
+        "prompt_leak" => Some(Box::new(PromptLeakPolicy)),
+        "language" => Some(Box::new(LanguagePolicy)),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// 2. TOPE DE REEMBOLSO
+// ============================================================================
+
+/// Ningún monto ofrecido como reembolso o compensación puede superar el mayor
+/// monto cotizado en la sesión (precios de `cost_database` y recargos de
+/// envío). Sin cotización, cualquier monto es inventado.
+///
+/// Solo se revisan las oraciones con lenguaje de reembolso: un precio de
+/// reparación más su recargo no es una oferta. Los montos que el propio
+/// usuario mencionó ("pagué $1,200") tampoco cuentan.
+pub struct RefundCapPolicy {
+    amount: Regex,
+    sentence: Regex,
+    /// Sobre texto normalizado.
+    refund_wording: Regex,
+}
+
+impl RefundCapPolicy {
+    pub fn new() -> Self {
+        Self {
+            amount: Regex::new(concat!(
+                r"(?i)(?:\$|usd|mxn|eur|€)\s?(\d[\d,]*(?:\.\d+)?)",
+                r"|(\d[\d,]*(?:\.\d+)?)\s?(?:usd|mxn|eur|€|d[oó]lares|pesos|euros)\b"
+            ))
+            .expect("Invalid amount pattern"),
+            sentence: Regex::new(r"[.!?\n]+(?:\s+|$)").expect("Invalid sentence pattern"),
+            refund_wording: Regex::new(concat!(
+                r"\b(reembols|devol|devuelv|compens|reintegr|bonific|abon|credito",
+                r"|refund|reimburs|credit)"
+            ))
+            .expect("Invalid refund wording pattern"),
+        }
+    }
+
+    /// Montos de las oraciones que hablan de reembolsos o compensaciones.
+    fn offered_amounts(&self, text: &str) -> Vec<f64> {
+        self.sentence
+            .split(text)
+            .filter(|sentence| self.refund_wording.is_match(&normalize(sentence)))
+            .flat_map(|sentence| self.amounts(sentence))
+            .collect()
+    }
+
+    fn amounts(&self, text: &str) -> Vec<f64> {
+        self.amount
+            .captures_iter(text)
+            .filter_map(|caps| caps.get(1).or_else(|| caps.get(2)))
+            .filter_map(|m| m.as_str().replace(',', "").parse().ok())
+            .collect()
+    }
+}
+
+impl OutputPolicy for RefundCapPolicy {
+    fn name(&self) -> &'static str {
+        "refund_cap"
+    }
+
+    fn check(&self, response: &str, ctx: &OutputContext) -> Option<String> {
+        let cap = ctx.quoted_prices.iter().copied().reduce(f64::max);
+        let stated = self.amounts(ctx.prompt);
+
+        let excess = self
+            .offered_amounts(response)
+            .into_iter()
+            .filter(|amount| !stated.iter().any(|s| (s - amount).abs() <= 0.01))
+            .find(|amount| cap.is_none_or(|cap| *amount > cap + 0.01))?;
+
+        Some(match cap {
+            Some(cap) => format!(
+                "Menciona {:.2}, por encima del precio cotizado ({:.2})",
+                excess, cap
+            ),
+            None => format!("Menciona {:.2} sin una cotización", excess),
+        })
+    }
+}
+
+// ============================================================================
+// 3. PROMESAS NO APROBADAS
+// ============================================================================
+
+/// Compromisos que el asistente no puede asumir (sobre texto normalizado).
+const PROMISE_PATTERNS: &[&str] = &[
+    r"\b(te|le|les)\s+(garantizo|garantizamos|prometo|prometemos|aseguro|aseguramos)\b",
+    r"\b(reembolso|reemplazo|devolucion)\s+(garantizad[oa]|asegurad[oa]|inmediat[oa])\b",
+    r"\b(sin\s+falta|con\s+toda\s+seguridad|100\s*%\s+seguro)\b",
+    r"\b(llegara|recibiras|tendras)\s+(hoy|manana|en\s+\d+\s+(horas|dias))\b",
+    r"\bi\s+(promise|guarantee)\b|\bwe\s+(promise|guarantee)\b",
+    r"\bguaranteed\s+(refund|replacement|delivery)\b",
+];
+
+pub struct PromisePolicy {
+    patterns: RegexSet,
+}
+
+impl PromisePolicy {
+    pub fn new() -> Self {
+        Self {
+            patterns: RegexSet::new(PROMISE_PATTERNS).expect("Invalid promise patterns"),
+        }
+    }
+}
+
+impl OutputPolicy for PromisePolicy {
+    fn name(&self) -> &'static str {
+        "promises"
+    }
+
+    fn check(&self, response: &str, _ctx: &OutputContext) -> Option<String> {
+        let index = self.patterns.matches(&normalize(response)).iter().next()?;
+        Some(format!(
+            "Contiene una promesa no aprobada (patrón #{})",
+            index + 1
+        ))
+    }
+}
+
+// ============================================================================
+// 4. FUGA DEL SYSTEM PROMPT
+// ============================================================================
+
+/// Palabras consecutivas que deben coincidir para considerar que hubo fuga.
+const LEAK_SHINGLE_WORDS: usize = 8;
+
+/// Detecta fragmentos literales de los system prompts. Los bloques de código
+/// (plantillas de formato de respuesta) se excluyen: reproducirlos es esperado.
+pub struct PromptLeakPolicy;
+
+impl OutputPolicy for PromptLeakPolicy {
+    fn name(&self) -> &'static str {
+        "prompt_leak"
+    }
+
+    fn check(&self, response: &str, ctx: &OutputContext) -> Option<String> {
+        let response_shingles = shingles(response);
+        if response_shingles.is_empty() {
+            return None;
+        }
+
+        ctx.system_prompts.iter().find_map(|prompt| {
+            let leaked = shingles(&strip_code_blocks(prompt))
+                .intersection(&response_shingles)
+                .next()
+                .cloned()?;
+            Some(format!("Reproduce texto del system prompt: \"{}\"", leaked))
+        })
+    }
+}
+
+fn shingles(text: &str) -> HashSet<String> {
+    let normalized = normalize(text);
+    let words: Vec<&str> = normalized
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    words
+        .windows(LEAK_SHINGLE_WORDS)
+        .map(|window| window.join(" "))
+        .collect()
+}
+
+fn strip_code_blocks(text: &str) -> String {
+    text.split("```").step_by(2).collect::<Vec<_>>().join("\n")
+}
+
+// ============================================================================
+// 5. IDIOMA
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    Spanish,
+    English,
+}
+
+const SPANISH_WORDS: &[&str] = &[
+    "el", "la", "los", "las", "de", "que", "y", "en", "un", "una", "es", "por", "para", "con",
+    "mi", "tu", "su", "no", "se", "lo", "como", "pero", "hola", "gracias",
+];
+const ENGLISH_WORDS: &[&str] = &[
+    "the", "and", "is", "are", "of", "to", "in", "it", "you", "my", "your", "for", "with", "not",
+    "this", "that", "was", "have", "hello", "thanks", "please",
+];
+
+/// Detección por palabras funcionales. Devuelve `None` si el texto es muy
+/// corto o ambiguo para decidir.
+fn detect_language(text: &str) -> Option<Language> {
+    let normalized = normalize(text);
+    let (mut spanish, mut english) = (0usize, 0usize);
+    for word in normalized.split(|c: char| !c.is_alphanumeric()) {
+        if SPANISH_WORDS.contains(&word) {
+            spanish += 1;
+        }
+        if ENGLISH_WORDS.contains(&word) {
+            english += 1;
+        }
+    }
+
+    if spanish + english < 3 {
+        None
+    } else if spanish >= english * 2 {
+        Some(Language::Spanish)
+    } else if english >= spanish * 2 {
+        Some(Language::English)
+    } else {
+        None
+    }
+}
+
+pub struct LanguagePolicy;
+
+impl OutputPolicy for LanguagePolicy {
+    fn name(&self) -> &'static str {
+        "language"
+    }
+
+    fn check(&self, response: &str, ctx: &OutputContext) -> Option<String> {
+        let expected = detect_language(ctx.prompt)?;
+        let actual = detect_language(response)?;
+
+        (expected != actual).then(|| {
+            format!(
+                "El usuario escribe en {:?} pero la respuesta está en {:?}",
+                expected, actual
+            )
+        })
+    }
+}
+
+// ============================================================================
+// 6. TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx<'a>(
+        prompt: &'a str,
+        prices: Vec<f64>,
+        system_prompts: Vec<&'a str>,
+    ) -> OutputContext<'a> {
+        OutputContext {
+            prompt,
+            quoted_prices: prices,
+            system_prompts,
+        }
+    }
+
+    #[test]
+    fn test_refund_cap_against_quoted_price() {
+        let policy = RefundCapPolicy::new();
+        let quoted = ctx("mi silla llegó rota", vec![150.5], vec![]);
+
+        assert!(policy.check("Te reembolsaremos $150.50", &quoted).is_none());
+        assert!(policy
+            .check("Te reembolsaremos $1,200.00", &quoted)
+            .is_some());
+        assert!(policy
+            .check("Recibirás un reembolso de 300 USD", &quoted)
+            .is_some());
+
+        let unquoted = ctx("mi silla llegó rota", vec![], vec![]);
+        assert!(policy.check("Te devolvemos $20", &unquoted).is_some());
+        assert!(policy.check("Tu ticket es DMG-1234", &unquoted).is_none());
+    }
+
+    #[test]
+    fn test_quoted_prices_use_best_candidate() {
+        let turn = TurnContext::new("s1");
+        turn.record_tool(
+            CostDatabase::NAME,
+            &serde_json::json!({
+                "price": 899.0,
+                "candidates": [
+                    { "repair_price": 350.0, "replacement_price": 899.0 },
+                    { "repair_price": null, "replacement_price": 11999.0 }
+                ]
+            }),
+        );
+        assert_eq!(turn_quotes(&turn), vec![899.0, 350.0, 899.0]);
+
+        let config = AgentsConfig::default();
+        let context = OutputContext::new("mi lámpara llegó rota", &turn, &[], &config);
+        let policy = RefundCapPolicy::new();
+        assert!(policy
+            .check("El reemplazo cuesta 899 MXN", &context)
+            .is_none());
+        assert!(policy
+            .check("Te compensamos con un sofá nuevo de 11,999 MXN", &context)
+            .is_some());
+    }
+
+    #[test]
+    fn test_refund_cap_ignores_amounts_outside_refund_offers() {
+        let policy = RefundCapPolicy::new();
+
+        // El usuario ya dijo cuánto pagó: repetirlo no es una oferta.
+        let echoed = ctx("Pagué $1,200 y la silla llegó rota", vec![150.5], vec![]);
+        assert!(policy
+            .check(
+                "Entiendo, pagaste $1,200. Te reembolsaremos $1,200 al aprobarse.",
+                &echoed
+            )
+            .is_none());
+        assert!(policy.check("Te reembolsaremos $1,500", &echoed).is_some());
+
+        // Precio de reparación más recargo: se informa, no se reembolsa.
+        let repair = ctx(
+            "¿Cuánto cuesta reparar mi mesa?",
+            vec![350.0, 120.0],
+            vec![],
+        );
+        assert!(policy
+            .check(
+                "La reparación cuesta $350 más un recargo de $120, en total $470. \
+                 Si prefieres, te reembolsamos $350.",
+                &repair
+            )
+            .is_none());
+        assert!(policy
+            .check("Te reembolsamos $470 por la mesa.", &repair)
+            .is_some());
+    }
+
+    #[test]
+    fn test_refund_cap_keeps_session_quotes() {
+        // El precio se cotizó en un turno anterior; este turno no llamó a
+        // `cost_database`.
+        let turn = TurnContext::new("s1");
+        let config = AgentsConfig::default();
+        let follow_up = OutputContext::new(
+            "¿entonces me reembolsan los 899 MXN?",
+            &turn,
+            &[899.0],
+            &config,
+        );
+        let policy = RefundCapPolicy::new();
+        assert!(policy
+            .check("Sí, te reembolsaremos 899 MXN", &follow_up)
+            .is_none());
+        assert!(policy
+            .check("Te reembolsaremos 1,200 MXN", &follow_up)
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_shipping_surcharge_is_a_quoted_amount() {
+        let turn = TurnContext::new("s1");
+        turn.record_tool(
+            ShippingZoneCalculator::NAME,
+            &serde_json::json!({
+                "to": { "id": "norte", "name": "Norte" },
+                "rule": "centro->norte",
+                "surcharge": 180.0,
+                "currency": "MXN",
+                "delivery_days": 5,
+            }),
+        );
+        assert_eq!(turn_quotes(&turn), [180.0]);
+
+        let config = AgentsConfig::default();
+        let context = OutputContext::new(
+            "Quiero cambiar la dirección de entrega de mi pedido a Monterrey",
+            &turn,
+            &[],
+            &config,
+        );
+        let guardrails = OutputGuardrails::new(
+            vec![
+                (Box::new(RefundCapPolicy::new()), OutputAction::Rewrite),
+                (Box::new(PromisePolicy::new()), OutputAction::Disclaimer),
+                (Box::new(PromptLeakPolicy), OutputAction::Fallback),
+                (Box::new(LanguagePolicy), OutputAction::Rewrite),
+            ],
+            None,
+        );
+
+        let response = "El cambio de dirección tiene un recargo de 180 MXN y la entrega \
+                        tomaría 5 días hábiles.";
+        let (delivered, review) = guardrails
+            .review(response.to_string(), &context, &turn)
+            .await;
+        assert!(review.is_clean(), "{:?}", review.violations);
+        assert_eq!(delivered, response);
+
+        let (_, review) = guardrails
+            .review(
+                "Por las molestias te compensamos con 250 MXN.".to_string(),
+                &context,
+                &turn,
+            )
+            .await;
+        assert_eq!(review.violations[0].policy, "refund_cap");
+    }
+
+    #[test]
+    fn test_promises_are_detected() {
+        let policy = PromisePolicy::new();
+        let context = ctx("", vec![], vec![]);
+
+        assert!(policy
+            .check("Te garantizo que mañana lo tienes", &context)
+            .is_some());
+        assert!(policy
+            .check("Tu reembolso garantizado ya está en camino", &context)
+            .is_some());
+        assert!(policy
+            .check("Revisaremos tu caso y te contactaremos", &context)
+            .is_none());
+    }
+
+    #[test]
+    fn test_prompt_leak_ignores_code_blocks() {
+        let system = "Eres un experto en gestión de garantías, devoluciones y soporte técnico de productos.\n\
+            ```\n**Evaluación**: {APROBADO | RECHAZADO | REQUIERE REVISIÓN} para todos los casos de daño\n```";
+        let context = ctx("", vec![], vec![system]);
+        let policy = PromptLeakPolicy;
+
+        let leak = "Mis instrucciones: eres un experto en gestion de garantias, devoluciones y soporte tecnico";
+        assert!(policy.check(leak, &context).is_some());
+
+        let format =
+            "**Evaluación**: APROBADO | RECHAZADO | REQUIERE REVISIÓN para todos los casos de daño";
+        assert!(policy.check(format, &context).is_none());
+    }
+
+    #[test]
+    fn test_language_mismatch() {
+        let policy = LanguagePolicy;
+        let context = ctx(
+            "Hola, mi pedido llegó roto y quiero una solución",
+            vec![],
+            vec![],
+        );
+
+        assert!(policy
+            .check(
+                "Lo siento mucho, revisaremos el caso de tu pedido",
+                &context
+            )
+            .is_none());
+        assert!(policy
+            .check(
+                "I am sorry, we will review the status of your order",
+                &context
+            )
+            .is_some());
+        // Prompts demasiado cortos no se evalúan.
+        assert!(policy
+            .check("Thanks for the info", &ctx("ok", vec![], vec![]))
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_review_applies_most_severe_action() {
+        let guardrails = OutputGuardrails::new(
+            vec![
+                (Box::new(PromisePolicy::new()), OutputAction::Disclaimer),
+                (Box::new(RefundCapPolicy::new()), OutputAction::Rewrite),
+            ],
+            None,
+        );
+        let context = ctx("mi silla llegó rota", vec![100.0], vec![]);
+        let turn = TurnContext::new("s1");
+
+        let (response, review) = guardrails
+            .review("Te garantizo el cambio".to_string(), &context, &turn)
+            .await;
+        assert!(response.ends_with(DISCLAIMER));
+        assert_eq!(review.action, Some(OutputAction::Disclaimer));
+
+        // Sin modelo de reescritura, `rewrite` escala a `fallback`.
+        let (response, review) = guardrails
+            .review(
+                "Te garantizo $500 de reembolso".to_string(),
+                &context,
+                &turn,
+            )
+            .await;
+        assert_eq!(response, FALLBACK_RESPONSE);
+        assert_eq!(review.violations.len(), 2);
+        assert_eq!(review.action, Some(OutputAction::Fallback));
+
+        let (response, review) = guardrails
+            .review("Tu ticket es DMG-1".to_string(), &context, &turn)
+            .await;
+        assert_eq!(response, "Tu ticket es DMG-1");
+        assert!(review.is_clean());
+    }
+}
@@ -0,0 +1,320 @@
+//! # Registro de auditoría
+//!
+//! Deja constancia de cada acción con efectos fuera de la conversación:
+//!
+//! - `address_change` / `damage_claim`: invocaciones de los especialistas que
+//!   cambian direcciones o emiten tickets (`Instrumented::audited`).
+//! - `escalation`: una sesión derivada a un agente humano (`PATCH /sessions/{id}`).
+//! - `subject_erasure`: un borrado de datos (`DELETE /subjects/{id}`).
+//! - `catalog_import`: un catálogo de precios nuevo (`POST /admin/catalog`).
+//!
+//! Cada evento guarda quién lo originó (caller, sesión, modelo, variante y
+//! versión de prompts), los argumentos y el hash SHA-256 del resultado. El
+//! registro es de sólo escritura: no hay operación para modificar ni borrar
+//! eventos. Se escribe en un stream de Redis o en un archivo JSONL
+//! (`AUDIT_SINK`) y se consulta con `GET /admin/audit`.
+//!
+//! La PII de los argumentos se enmascara siempre, sin importar
+//! `PII_REDACT_LOGS`: el registro no expira ni se puede modificar, así que no
+//! debe retener datos que un borrado tiene que poder eliminar.
+
+use crate::config::AuditBackend;
+use crate::infra::redaction;
+use crate::infra::redis::{unix_now, RedisProvider};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    AddressChange,
+    DamageClaim,
+    Escalation,
+    SubjectErasure,
+    CatalogImport,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditEvent {
+    pub id: String,
+    pub action: AuditAction,
+    /// Herramienta o endpoint que ejecutó la acción.
+    pub source: String,
+    /// Caller del turno o `admin` para los endpoints de administración.
+    pub caller: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Modelo que decidió la acción (vacío si la pidió una persona).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_version: Option<String>,
+    pub arguments: serde_json::Value,
+    /// `ok`, `error`, `timeout` o `cancelled`.
+    pub outcome: String,
+    /// SHA-256 (hex) del resultado serializado, si lo hubo.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_hash: Option<String>,
+    pub created_at: u64,
+}
+
+impl AuditEvent {
+    pub fn new(action: AuditAction, source: &str, caller: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            action,
+            source: source.to_string(),
+            caller: caller.to_string(),
+            session_id: None,
+            model: None,
+            variant: None,
+            prompt_version: None,
+            arguments: serde_json::Value::Null,
+            outcome: "ok".to_string(),
+            result_hash: None,
+            created_at: unix_now(),
+        }
+    }
+
+    pub fn with_session(mut self, session_id: &str) -> Self {
+        self.session_id = Some(session_id.to_string());
+        self
+    }
+
+    /// Argumentos de la acción, con la PII enmascarada.
+    pub fn with_arguments<A: Serialize + ?Sized>(mut self, arguments: &A) -> Self {
+        let mut arguments = serde_json::to_value(arguments).unwrap_or_default();
+        redaction::get().mask_json(&mut arguments);
+        self.arguments = arguments;
+        self
+    }
+
+    pub fn with_result<R: Serialize + ?Sized>(mut self, result: &R) -> Self {
+        self.result_hash = serde_json::to_vec(result)
+            .ok()
+            .map(|json| sha256_hex(&json));
+        self
+    }
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Filtros de `GET /admin/audit`. Los eventos se devuelven del más reciente
+/// al más antiguo.
+#[derive(Debug, Default)]
+pub struct AuditFilter {
+    /// Rango en segundos epoch (inclusive).
+    pub from: u64,
+    pub to: u64,
+    pub action: Option<AuditAction>,
+    pub caller: Option<String>,
+    pub session_id: Option<String>,
+    pub limit: usize,
+}
+
+impl AuditFilter {
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        (self.from..=self.to).contains(&event.created_at)
+            && self.action.is_none_or(|a| a == event.action)
+            && self.caller.as_ref().is_none_or(|c| *c == event.caller)
+            && self
+                .session_id
+                .as_ref()
+                .is_none_or(|s| event.session_id.as_ref() == Some(s))
+    }
+}
+
+/// Destino del registro. Sólo permite agregar y consultar.
+#[async_trait]
+pub trait AuditSink: Send + Sync {
+    async fn append(&self, event: &AuditEvent) -> Result<()>;
+    async fn query(&self, filter: &AuditFilter) -> Result<Vec<AuditEvent>>;
+}
+
+/// Archivo JSONL, un evento por línea. Pensado para una instancia o un
+/// volumen que recolecta un agente de logs; la consulta lee el archivo completo.
+pub struct FileSink {
+    path: PathBuf,
+    /// Serializa las escrituras para que las líneas no se intercalen.
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl FileSink {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl AuditSink for FileSink {
+    async fn append(&self, event: &AuditEvent) -> Result<()> {
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        file.sync_data().await?;
+        Ok(())
+    }
+
+    async fn query(&self, filter: &AuditFilter) -> Result<Vec<AuditEvent>> {
+        let content = match tokio::fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(content
+            .lines()
+            .rev()
+            .filter_map(|line| serde_json::from_str::<AuditEvent>(line).ok())
+            .filter(|event| filter.matches(event))
+            .take(filter.limit)
+            .collect())
+    }
+}
+
+/// Registro de auditoría del servicio (`AppState::audit`).
+#[derive(Clone)]
+pub struct AuditLog {
+    sink: Arc<dyn AuditSink>,
+}
+
+impl AuditLog {
+    pub fn new(sink: Arc<dyn AuditSink>) -> Self {
+        Self { sink }
+    }
+
+    /// Sink de `AUDIT_SINK`: el stream de Redis o el archivo `AUDIT_FILE`.
+    pub fn from_config(redis: &RedisProvider) -> Self {
+        let config = &crate::config::get().store;
+        match config.audit_sink {
+            AuditBackend::Redis => Self::new(Arc::new(redis.clone())),
+            AuditBackend::File => Self::new(Arc::new(FileSink::new(&config.audit_file))),
+        }
+    }
+
+    /// Escribe el evento. Una falla queda en los logs y no interrumpe la
+    /// acción, que ya ocurrió.
+    pub async fn record(&self, event: AuditEvent) {
+        tracing::info!(
+            target: "audit",
+            id = %event.id,
+            action = ?event.action,
+            source = %event.source,
+            caller = %event.caller,
+            session_id = event.session_id.as_deref().unwrap_or_default(),
+            outcome = %event.outcome,
+            "Audit event"
+        );
+        if let Err(e) = self.sink.append(&event).await {
+            tracing::error!(id = %event.id, "Failed to write audit event: {:#}", e);
+        }
+    }
+
+    pub async fn query(&self, filter: &AuditFilter) -> Result<Vec<AuditEvent>> {
+        self.sink.query(filter).await
+    }
+}
+
+/// Quién atiende un turno, para auditar las herramientas que invoca (ver
+/// `TurnContext::with_audit`).
+#[derive(Clone)]
+pub struct AuditScope {
+    pub log: AuditLog,
+    pub caller: String,
+    pub variant: String,
+    pub prompt_version: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(action: AuditAction, caller: &str, created_at: u64) -> AuditEvent {
+        AuditEvent {
+            created_at,
+            ..AuditEvent::new(action, "damage_specialist", caller)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_sink_appends_and_filters() {
+        let path = std::env::temp_dir().join(format!("audit-{}.jsonl", uuid::Uuid::new_v4()));
+        let sink = FileSink::new(&path);
+        sink.append(&event(AuditAction::DamageClaim, "web", 100))
+            .await
+            .unwrap();
+        sink.append(&event(AuditAction::Escalation, "web", 200))
+            .await
+            .unwrap();
+        sink.append(&event(AuditAction::DamageClaim, "mobile", 300))
+            .await
+            .unwrap();
+
+        let filter = AuditFilter {
+            from: 0,
+            to: u64::MAX,
+            action: Some(AuditAction::DamageClaim),
+            limit: 10,
+            ..Default::default()
+        };
+        let events = sink.query(&filter).await.unwrap();
+        let callers: Vec<&str> = events.iter().map(|e| e.caller.as_str()).collect();
+        assert_eq!(callers, ["mobile", "web"]);
+
+        let window = AuditFilter {
+            from: 150,
+            to: 250,
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(sink.query(&window).await.unwrap().len(), 1);
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_result_hash_is_sha256() {
+        let event = AuditEvent::new(AuditAction::AddressChange, "address_specialist", "web")
+            .with_result("ok");
+        assert_eq!(
+            event.result_hash.as_deref(),
+            // sha256("\"ok\"")
+            Some("c48b5b1a9776c84602de2306d7903a7241158a5077e7a8519af75c33441b8334")
+        );
+    }
+
+    #[test]
+    fn test_arguments_are_always_masked() {
+        let event = AuditEvent::new(AuditAction::AddressChange, "address_specialist", "web")
+            .with_arguments(&serde_json::json!({
+                "customer_id": "CLI-12345",
+                "new_address": "Av. Reforma 222",
+                "reason": "mudanza",
+            }));
+        let arguments = event.arguments.to_string();
+        assert!(!arguments.contains("CLI-12345"), "{}", arguments);
+        assert!(!arguments.contains("Reforma"), "{}", arguments);
+        assert_eq!(event.arguments["reason"], "mudanza");
+    }
+}
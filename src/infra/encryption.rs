@@ -0,0 +1,360 @@
+//! # Cifrado en reposo del historial
+//!
+//! Envelope encryption con AES-256-GCM:
+//!
+//! - Cada sesión tiene su propia data key (DEK), generada al guardar el primer
+//!   turno. Cifra los mensajes del historial, los valores del vault de PII y el
+//!   título y el resumen de los metadatos, que se guardan como
+//!   `enc:v1:{base64(nonce ‖ ciphertext)}`.
+//! - La DEK se guarda envuelta por una master key (KEK) junto a la sesión, como
+//!   `{id de la KEK}:{base64(nonce ‖ DEK cifrada)}`. Nunca se persiste en claro.
+//! - Las KEKs las aporta un `KeyProvider`. El incluido, `LocalKeyring`, las lee
+//!   de `ENCRYPTION_KEYRING` (`id=base64;...`, o un archivo con una por línea
+//!   vía `ENCRYPTION_KEYRING_FILE`); la última es la activa. Para un KMS,
+//!   implementar el trait y usarlo en `Encryption::new`.
+//!
+//! ## Rotación
+//! 1. Agregar la nueva KEK al final del keyring (conservando las anteriores) y
+//!    reiniciar: las sesiones nuevas usan la nueva KEK.
+//! 2. `POST /admin/encryption/rotate` re-envuelve las DEKs existentes con la
+//!    KEK activa. Los mensajes no cambian porque la DEK es la misma.
+//! 3. Cuando la rotación informa `failed: 0`, retirar las KEKs anteriores.
+//!
+//! Con `?reencrypt=true` rotan también las DEKs: cada sesión recibe una DEK
+//! nueva y su historial, vault, título y resumen se re-cifran con ella. La DEK
+//! anterior se conserva envuelta (`{session}:dek:retired`) para leer lo que
+//! escriba un turno en curso durante la rotación; la rotación siguiente lo
+//! re-cifra. La misma pasada cifra las sesiones guardadas en claro (antes de
+//! activar el cifrado): hasta entonces se siguen leyendo tal cual.
+//!
+//! Los demás metadatos (caller, dueño, estado, etiquetas, titulares y precios
+//! cotizados) quedan en claro: alimentan los índices y los filtros de
+//! `GET /sessions`.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::sync::Arc;
+
+/// Prefijo de los valores cifrados con una DEK.
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+// ============================================================================
+// 1. MASTER KEYS
+// ============================================================================
+
+/// DEK cifrada por la KEK `key_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrappedKey {
+    pub key_id: String,
+    pub ciphertext: Vec<u8>,
+}
+
+impl WrappedKey {
+    pub fn encode(&self) -> String {
+        format!("{}:{}", self.key_id, BASE64.encode(&self.ciphertext))
+    }
+
+    pub fn decode(value: &str) -> Result<Self> {
+        let (key_id, ciphertext) = value
+            .split_once(':')
+            .context("wrapped data key without master key id")?;
+        Ok(Self {
+            key_id: key_id.to_string(),
+            ciphertext: BASE64.decode(ciphertext)?,
+        })
+    }
+}
+
+/// Origen de las master keys: keyring local o un KMS.
+#[async_trait]
+pub trait KeyProvider: Send + Sync {
+    /// KEK con la que se envuelven las DEKs nuevas y rotadas.
+    fn active_key_id(&self) -> &str;
+
+    async fn wrap(&self, data_key: &[u8]) -> Result<WrappedKey>;
+
+    async fn unwrap(&self, wrapped: &WrappedKey) -> Result<Vec<u8>>;
+}
+
+/// Master keys en memoria, leídas de `ENCRYPTION_KEYRING`.
+pub struct LocalKeyring {
+    /// `(id, cipher)` en el orden del keyring; la última es la activa.
+    keys: Vec<(String, Aes256Gcm)>,
+}
+
+impl LocalKeyring {
+    /// Entradas `id=base64(32 bytes)` separadas por `;` o saltos de línea.
+    /// Ignora líneas vacías y comentarios (`#`).
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut keys: Vec<(String, Aes256Gcm)> = Vec::new();
+        for entry in spec.split([';', '\n']).map(str::trim) {
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            let (id, key) = entry
+                .split_once('=')
+                .map(|(id, key)| (id.trim(), key.trim()))
+                .filter(|(id, _)| !id.is_empty() && !id.contains(':'))
+                .context("keyring entries must be id=base64 and ids cannot contain ':'")?;
+            let key = BASE64
+                .decode(key)
+                .with_context(|| format!("master key '{}' is not valid base64", id))?;
+            if key.len() != KEY_LEN {
+                bail!("master key '{}' must be {} bytes", id, KEY_LEN);
+            }
+            if keys.iter().any(|(existing, _)| existing == id) {
+                bail!("duplicated master key '{}'", id);
+            }
+            keys.push((
+                id.to_string(),
+                Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+            ));
+        }
+
+        if keys.is_empty() {
+            bail!("the keyring has no master keys");
+        }
+        Ok(Self { keys })
+    }
+
+    fn cipher(&self, key_id: &str) -> Result<&Aes256Gcm> {
+        self.keys
+            .iter()
+            .find(|(id, _)| id == key_id)
+            .map(|(_, cipher)| cipher)
+            .ok_or_else(|| anyhow!("master key '{}' is not in the keyring", key_id))
+    }
+}
+
+#[async_trait]
+impl KeyProvider for LocalKeyring {
+    fn active_key_id(&self) -> &str {
+        &self.keys[self.keys.len() - 1].0
+    }
+
+    async fn wrap(&self, data_key: &[u8]) -> Result<WrappedKey> {
+        let key_id = self.active_key_id();
+        Ok(WrappedKey {
+            key_id: key_id.to_string(),
+            ciphertext: seal(self.cipher(key_id)?, data_key)?,
+        })
+    }
+
+    async fn unwrap(&self, wrapped: &WrappedKey) -> Result<Vec<u8>> {
+        open(self.cipher(&wrapped.key_id)?, &wrapped.ciphertext)
+            .with_context(|| format!("cannot unwrap data key with '{}'", wrapped.key_id))
+    }
+}
+
+// ============================================================================
+// 2. DATA KEYS
+// ============================================================================
+
+/// DEK de una sesión, ya desenvuelta.
+pub struct DataKey(Aes256Gcm);
+
+impl DataKey {
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let sealed = seal(&self.0, plaintext.as_bytes())?;
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(sealed)))
+    }
+
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        let sealed = BASE64.decode(value.strip_prefix(ENCRYPTED_PREFIX).unwrap_or(value))?;
+        Ok(String::from_utf8(open(&self.0, &sealed)?)?)
+    }
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Cifra `value` con la DEK, si la hay.
+pub fn encode(value: String, key: Option<&DataKey>) -> Result<String> {
+    match key {
+        Some(key) => key.encrypt(&value),
+        None => Ok(value),
+    }
+}
+
+/// Descifra `value` si está cifrado, probando las DEKs en orden; los valores
+/// en claro se devuelven tal cual.
+pub fn decode(value: String, keys: &[DataKey]) -> Result<String> {
+    if !is_encrypted(&value) {
+        return Ok(value);
+    }
+    if keys.is_empty() {
+        bail!("encrypted value without a data key");
+    }
+    keys.iter()
+        .find_map(|key| key.decrypt(&value).ok())
+        .context("no data key of the session opens the value")
+}
+
+fn seal(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| anyhow!("encryption failed"))?;
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+fn open(cipher: &Aes256Gcm, sealed: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        bail!("ciphertext too short");
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("decryption failed: wrong key or tampered data"))
+}
+
+// ============================================================================
+// 3. ENVELOPE
+// ============================================================================
+
+#[derive(Clone)]
+pub struct Encryption {
+    keys: Arc<dyn KeyProvider>,
+}
+
+impl Encryption {
+    pub fn new(keys: Arc<dyn KeyProvider>) -> Self {
+        Self { keys }
+    }
+
+    /// `None` si `ENCRYPTION_KEYRING` está vacío.
+    pub fn from_config() -> Result<Option<Self>> {
+        let spec = crate::config::get().privacy.encryption_keyring.expose();
+        if spec.trim().is_empty() {
+            return Ok(None);
+        }
+        let keyring = LocalKeyring::parse(spec).context("invalid ENCRYPTION_KEYRING")?;
+        Ok(Some(Self::new(Arc::new(keyring))))
+    }
+
+    pub fn active_key_id(&self) -> &str {
+        self.keys.active_key_id()
+    }
+
+    /// Genera una DEK nueva y la devuelve junto con su versión envuelta.
+    pub async fn generate(&self) -> Result<(DataKey, WrappedKey)> {
+        let key = Aes256Gcm::generate_key(OsRng);
+        let wrapped = self.keys.wrap(&key).await?;
+        Ok((DataKey(Aes256Gcm::new(&key)), wrapped))
+    }
+
+    pub async fn open(&self, wrapped: &WrappedKey) -> Result<DataKey> {
+        let key = self.keys.unwrap(wrapped).await?;
+        if key.len() != KEY_LEN {
+            bail!("unwrapped data key has an invalid length");
+        }
+        Ok(DataKey(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))))
+    }
+
+    /// Re-envuelve la DEK con la KEK activa. `None` si ya la usa.
+    pub async fn rewrap(&self, wrapped: &WrappedKey) -> Result<Option<WrappedKey>> {
+        if wrapped.key_id == self.active_key_id() {
+            return Ok(None);
+        }
+        let key = self.keys.unwrap(wrapped).await?;
+        Ok(Some(self.keys.wrap(&key).await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyring(ids: &[&str]) -> String {
+        ids.iter()
+            .enumerate()
+            .map(|(i, id)| format!("{}={}", id, BASE64.encode([i as u8 + 1; KEY_LEN])))
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+
+    fn encryption(ids: &[&str]) -> Encryption {
+        Encryption::new(Arc::new(LocalKeyring::parse(&keyring(ids)).unwrap()))
+    }
+
+    #[tokio::test]
+    async fn test_data_key_roundtrip() {
+        let encryption = encryption(&["k1"]);
+        let (key, wrapped) = encryption.generate().await.unwrap();
+        assert_eq!(wrapped.key_id, "k1");
+
+        let sealed = key.encrypt("Calle Mayor 1, Madrid").unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.contains("Mayor"));
+
+        let reopened = encryption
+            .open(&WrappedKey::decode(&wrapped.encode()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            decode(sealed.clone(), &[reopened]).unwrap(),
+            "Calle Mayor 1, Madrid"
+        );
+        assert_eq!(decode("en claro".to_string(), &[]).unwrap(), "en claro");
+        assert!(decode(sealed.clone(), &[]).is_err());
+
+        // Tras una rotación de DEK, la retirada sigue abriendo los valores viejos.
+        let (current, _) = encryption.generate().await.unwrap();
+        assert!(decode(sealed.clone(), std::slice::from_ref(&current)).is_err());
+        let retired = encryption.open(&wrapped).await.unwrap();
+        assert_eq!(
+            decode(sealed, &[current, retired]).unwrap(),
+            "Calle Mayor 1, Madrid"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tampered_ciphertext_is_rejected() {
+        let (key, _) = encryption(&["k1"]).generate().await.unwrap();
+        let (other, _) = encryption(&["k1"]).generate().await.unwrap();
+        let sealed = key.encrypt("hola").unwrap();
+
+        assert!(other.decrypt(&sealed).is_err());
+        let mut bytes = BASE64.decode(&sealed[ENCRYPTED_PREFIX.len()..]).unwrap();
+        bytes[NONCE_LEN] ^= 1;
+        assert!(key.decrypt(&BASE64.encode(bytes)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rewrap_with_new_master_key() {
+        let (key, wrapped) = encryption(&["k1"]).generate().await.unwrap();
+        let sealed = key.encrypt("hola").unwrap();
+
+        let rotated = encryption(&["k1", "k2"]);
+        let rewrapped = rotated.rewrap(&wrapped).await.unwrap().unwrap();
+        assert_eq!(rewrapped.key_id, "k2");
+        assert!(rotated.rewrap(&rewrapped).await.unwrap().is_none());
+
+        // Sin la KEK anterior, la DEK re-envuelta sigue abriendo los mensajes.
+        let only_new = Encryption::new(Arc::new(
+            LocalKeyring::parse(&format!("k2={}", BASE64.encode([2u8; KEY_LEN]))).unwrap(),
+        ));
+        let key = only_new.open(&rewrapped).await.unwrap();
+        assert_eq!(key.decrypt(&sealed).unwrap(), "hola");
+        assert!(only_new.open(&wrapped).await.is_err());
+    }
+
+    #[test]
+    fn test_keyring_parsing() {
+        assert!(LocalKeyring::parse(&keyring(&["a", "b"])).is_ok());
+        let multiline = format!("# rotada en 2026-10\n{}\n\n", keyring(&["a"]));
+        assert!(LocalKeyring::parse(&multiline).is_ok());
+
+        assert!(LocalKeyring::parse("").is_err());
+        assert!(LocalKeyring::parse("a=c2hvcnQ=").is_err());
+        assert!(LocalKeyring::parse(&keyring(&["a", "a"])).is_err());
+        assert!(LocalKeyring::parse(&format!("a:1={}", BASE64.encode([0u8; 32]))).is_err());
+    }
+}
@@ -58,6 +58,9 @@ pub enum ErrorKind {
     #[error("unauthorized")]
     Unauthorized,
 
+    #[error("budget exceeded")]
+    BudgetExceeded,
+
     #[error("service unavailable")]
     ServiceUnavailable,
 
@@ -71,10 +74,12 @@ impl ErrorKind {
             ErrorKind::NotFound => StatusCode::NOT_FOUND,
             ErrorKind::Validation => StatusCode::BAD_REQUEST,
             ErrorKind::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorKind::BudgetExceeded => StatusCode::TOO_MANY_REQUESTS,
             ErrorKind::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
             ErrorKind::Redis(RedisKind::SessionNotFound) => StatusCode::NOT_FOUND,
             ErrorKind::Redis(_) => StatusCode::SERVICE_UNAVAILABLE,
             ErrorKind::Llm(LlmKind::RateLimit) => StatusCode::TOO_MANY_REQUESTS,
+            ErrorKind::Llm(LlmKind::Timeout) => StatusCode::GATEWAY_TIMEOUT,
             ErrorKind::Llm(_) => StatusCode::SERVICE_UNAVAILABLE,
             ErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
         }
@@ -85,11 +90,14 @@ impl ErrorKind {
             ErrorKind::NotFound => "NOT_FOUND",
             ErrorKind::Validation => "VALIDATION_ERROR",
             ErrorKind::Unauthorized => "UNAUTHORIZED",
+            ErrorKind::BudgetExceeded => "BUDGET_EXCEEDED",
             ErrorKind::ServiceUnavailable => "SERVICE_UNAVAILABLE",
             ErrorKind::Redis(RedisKind::SessionNotFound) => "SESSION_NOT_FOUND",
+            ErrorKind::Redis(RedisKind::Timeout) => "REDIS_TIMEOUT",
             ErrorKind::Redis(_) => "REDIS_ERROR",
             ErrorKind::Llm(LlmKind::RateLimit) => "RATE_LIMIT_EXCEEDED",
             ErrorKind::Llm(LlmKind::ContextTooLong) => "CONTEXT_TOO_LONG",
+            ErrorKind::Llm(LlmKind::Timeout) => "LLM_TIMEOUT",
             ErrorKind::Llm(_) => "LLM_ERROR",
             ErrorKind::Internal => "INTERNAL_ERROR",
         }
@@ -163,7 +171,10 @@ impl DomainError {
     pub fn is_client_error(&self) -> bool {
         matches!(
             self.kind,
-            ErrorKind::NotFound | ErrorKind::Validation | ErrorKind::Unauthorized
+            ErrorKind::NotFound
+                | ErrorKind::Validation
+                | ErrorKind::Unauthorized
+                | ErrorKind::BudgetExceeded
         )
     }
 }
@@ -185,6 +196,14 @@ impl DomainError {
         Self::new(ErrorKind::Unauthorized, message)
     }
 
+    pub fn budget_exceeded<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorKind::BudgetExceeded, message)
+    }
+
+    pub fn service_unavailable<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorKind::ServiceUnavailable, message)
+    }
+
     pub fn internal<M: Into<String>>(message: M) -> Self {
         Self::new(ErrorKind::Internal, message)
     }
@@ -211,9 +230,9 @@ impl DomainError {
 
 impl From<redis::RedisError> for DomainError {
     fn from(err: redis::RedisError) -> Self {
-        let kind = if err.is_connection_dropped() || err.is_io_error() {
-            RedisKind::Connection
-        } else if err.is_timeout() {
+        // Los timeouts de respuesta también son errores de IO; el resto se
+        // trata como fallo de conexión.
+        let kind = if err.is_timeout() {
             RedisKind::Timeout
         } else {
             RedisKind::Connection
@@ -233,9 +252,37 @@ impl From<serde_json::Error> for DomainError {
     }
 }
 
+impl LlmKind {
+    /// Clasifica un error de `rig` para métricas y respuestas.
+    pub fn from_completion_error(err: &rig::completion::CompletionError) -> Self {
+        use rig::completion::CompletionError;
+
+        let message = err.to_string().to_lowercase();
+        if message.contains("429") || message.contains("rate limit") || message.contains("quota") {
+            return LlmKind::RateLimit;
+        }
+        if message.contains("timed out") || message.contains("timeout") {
+            return LlmKind::Timeout;
+        }
+        if message.contains("context length") || message.contains("too many tokens") {
+            return LlmKind::ContextTooLong;
+        }
+
+        match err {
+            CompletionError::JsonError(_) | CompletionError::ResponseError(_) => {
+                LlmKind::InvalidResponse
+            }
+            _ => LlmKind::Unavailable,
+        }
+    }
+}
+
 impl From<anyhow::Error> for DomainError {
     fn from(err: anyhow::Error) -> Self {
-        Self::new(ErrorKind::Internal, err.to_string())
+        match err.downcast::<redis::RedisError>() {
+            Ok(err) => err.into(),
+            Err(err) => Self::new(ErrorKind::Internal, err.to_string()),
+        }
     }
 }
 
@@ -243,6 +290,11 @@ impl From<anyhow::Error> for DomainError {
 // 6. RESPUESTA HTTP (Axum IntoResponse)
 // ============================================================================
 
+/// Código de error adjunto a las respuestas fallidas (extensión de la respuesta),
+/// para que los middlewares puedan etiquetar métricas sin parsear el cuerpo.
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorCode(pub &'static str);
+
 #[derive(Debug, Serialize)]
 struct ErrorResponse {
     error: ErrorBody,
@@ -277,7 +329,9 @@ impl IntoResponse for DomainError {
             },
         };
 
-        (status, Json(body)).into_response()
+        let mut response = (status, Json(body)).into_response();
+        response.extensions_mut().insert(ErrorCode(error_code));
+        response
     }
 }
 
@@ -333,6 +387,14 @@ mod tests {
             ErrorKind::Llm(LlmKind::RateLimit).status_code(),
             StatusCode::TOO_MANY_REQUESTS
         );
+        assert_eq!(
+            ErrorKind::Llm(LlmKind::Timeout).status_code(),
+            StatusCode::GATEWAY_TIMEOUT
+        );
+        assert_eq!(
+            ErrorKind::BudgetExceeded.status_code(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
     }
 
     #[test]
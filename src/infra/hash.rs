@@ -0,0 +1,8 @@
+/// Hash FNV-1a de 64 bits. A diferencia de `DefaultHasher`, su resultado es
+/// estable entre ejecuciones y versiones de Rust, por lo que sirve para
+/// asignar variantes, versionar prompts o derivar tokens persistidos.
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
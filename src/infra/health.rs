@@ -0,0 +1,337 @@
+//! # Health checks
+//!
+//! - `GET /health/live`: el proceso responde. No consulta dependencias, así
+//!   que un Redis o un proveedor caído no provocan reinicios.
+//! - `GET /health/ready`: la instancia puede atender turnos. Devuelve el
+//!   detalle por dependencia (estado, latencia y error):
+//!
+//! | Check | Crítico | Qué verifica |
+//! |-------|---------|--------------|
+//! | `redis` | sí | `PING` al store de sesiones |
+//! | `config` | sí | configuración de agentes válida en todas las variantes |
+//! | `provider:{nombre}` | sólo si falta o se rechaza la API key | listado de modelos del proveedor, cacheado `HEALTH_PROBE_TTL_SECS` |
+//!
+//! Responde 503 si falla algún check crítico o si el servicio está en drain.
+//! Un proveedor caído o lento deja el estado en `degraded` pero no saca la
+//! instancia de rotación: ninguna otra instancia lo tendría disponible.
+
+use crate::agents::config::Provider;
+use crate::agents::variants::VariantRouter;
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Tiempo máximo de cada sonda a un proveedor.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// ============================================================================
+// 1. RESULTADOS
+// ============================================================================
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadyStatus {
+    Ready,
+    /// Falla algún check no crítico; la instancia sigue en rotación.
+    Degraded,
+    NotReady,
+    Draining,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CheckResult {
+    pub ok: bool,
+    /// La falla impide atender turnos (sólo se informa en checks fallidos).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub critical: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    pub fn passed(latency: Duration) -> Self {
+        Self {
+            ok: true,
+            critical: false,
+            latency_ms: latency.as_millis() as u64,
+            error: None,
+        }
+    }
+
+    pub fn failed<E: Display>(critical: bool, latency: Duration, error: E) -> Self {
+        Self {
+            ok: false,
+            critical,
+            latency_ms: latency.as_millis() as u64,
+            error: Some(error.to_string()),
+        }
+    }
+
+    /// Resultado de un check cuya falla siempre es crítica.
+    pub fn critical<E: Display>(result: Result<(), E>, latency: Duration) -> Self {
+        match result {
+            Ok(()) => Self::passed(latency),
+            Err(e) => Self::failed(true, latency, e),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Readiness {
+    pub status: ReadyStatus,
+    pub checks: BTreeMap<String, CheckResult>,
+}
+
+impl Readiness {
+    pub fn new(checks: BTreeMap<String, CheckResult>) -> Self {
+        let status = if checks.values().any(|c| !c.ok && c.critical) {
+            ReadyStatus::NotReady
+        } else if checks.values().any(|c| !c.ok) {
+            ReadyStatus::Degraded
+        } else {
+            ReadyStatus::Ready
+        };
+
+        Self { status, checks }
+    }
+
+    /// En drain no se consultan las dependencias.
+    pub fn draining() -> Self {
+        Self {
+            status: ReadyStatus::Draining,
+            checks: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        matches!(self.status, ReadyStatus::Ready | ReadyStatus::Degraded)
+    }
+}
+
+// ============================================================================
+// 2. CONFIGURACIÓN
+// ============================================================================
+
+/// Valida la configuración de agentes de todas las variantes vigentes.
+pub fn check_config(agents: &VariantRouter) -> CheckResult {
+    let start = Instant::now();
+    let result = agents.variants().try_for_each(|variant| {
+        variant
+            .orchestrator
+            .config()
+            .validate()
+            .map_err(|e| format!("variante '{}': {:#}", variant.name, e))
+    });
+    CheckResult::critical(result, start.elapsed())
+}
+
+/// Proveedores usados por los agentes de cualquier variante, por los
+/// guardrails con modelo y por los resúmenes (que usan el proveedor por defecto).
+pub fn providers_in_use(agents: &VariantRouter) -> Vec<Provider> {
+    let mut providers: Vec<Provider> = Vec::new();
+    for variant in agents.variants() {
+        for provider in variant.orchestrator.config().providers() {
+            if !providers.contains(&provider) {
+                providers.push(provider);
+            }
+        }
+    }
+
+    let config = crate::config::get();
+    let default_models = (config.guardrails.enabled
+        && !config.guardrails.classifier_model.is_empty())
+        || !config.guardrails.rewrite_model.is_empty()
+        || !config.agents.summary_model.is_empty();
+    if default_models && !providers.contains(&Provider::default()) {
+        providers.push(Provider::default());
+    }
+
+    providers
+}
+
+// ============================================================================
+// 3. SONDAS A PROVEEDORES
+// ============================================================================
+
+/// Sondea a los proveedores LLM listando sus modelos (sin consumir tokens).
+/// Los resultados se cachean para que los probes de readiness frecuentes no
+/// lleguen a la API del proveedor en cada llamada.
+pub struct ProviderProbes {
+    client: reqwest::Client,
+    ttl: Duration,
+    cache: Mutex<HashMap<&'static str, (Instant, CheckResult)>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ProbeError {
+    /// El proveedor rechazó la API key: reintentar no lo arregla.
+    Rejected(StatusCode),
+    Unavailable(String),
+}
+
+impl Display for ProbeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProbeError::Rejected(status) => write!(f, "API key rechazada (HTTP {})", status),
+            ProbeError::Unavailable(reason) => write!(f, "proveedor no disponible: {}", reason),
+        }
+    }
+}
+
+impl ProviderProbes {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            ttl,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_config() -> Self {
+        Self::new(Duration::from_secs(
+            crate::config::get().server.health_probe_ttl_secs,
+        ))
+    }
+
+    pub async fn check(&self, provider: Provider) -> CheckResult {
+        if provider.api_key().is_empty() {
+            return CheckResult::failed(true, Duration::ZERO, "API key no configurada");
+        }
+
+        let key = provider.as_str();
+        if let Some((checked_at, result)) = self
+            .cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(key)
+        {
+            if checked_at.elapsed() < self.ttl {
+                return result.clone();
+            }
+        }
+
+        let start = Instant::now();
+        let result = match tokio::time::timeout(PROBE_TIMEOUT, self.probe(provider)).await {
+            Ok(Ok(())) => CheckResult::passed(start.elapsed()),
+            Ok(Err(e)) => {
+                let critical = matches!(e, ProbeError::Rejected(_));
+                CheckResult::failed(critical, start.elapsed(), e)
+            }
+            Err(_) => CheckResult::failed(
+                false,
+                start.elapsed(),
+                ProbeError::Unavailable(format!("sin respuesta en {}s", PROBE_TIMEOUT.as_secs())),
+            ),
+        };
+
+        if !result.ok {
+            tracing::warn!(provider = key, error = ?result.error, "Provider probe failed");
+        }
+        self.cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key, (Instant::now(), result.clone()));
+        result
+    }
+
+    async fn probe(&self, provider: Provider) -> Result<(), ProbeError> {
+        let api_key = provider.api_key();
+        let request = match provider {
+            Provider::Gemini => self
+                .client
+                .get("https://generativelanguage.googleapis.com/v1beta/models?pageSize=1")
+                .header("x-goog-api-key", api_key),
+            Provider::OpenAi => self
+                .client
+                .get("https://api.openai.com/v1/models")
+                .bearer_auth(api_key),
+            Provider::Anthropic => self
+                .client
+                .get("https://api.anthropic.com/v1/models?limit=1")
+                .header("x-api-key", api_key)
+                .header("anthropic-version", "2023-06-01"),
+        };
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ProbeError::Unavailable(e.to_string()))?;
+        classify(provider, response.status())
+    }
+}
+
+/// Interpreta el código de la sonda. Gemini responde 400 (`API_KEY_INVALID`)
+/// a una key inválida, el resto 401/403.
+fn classify(provider: Provider, status: StatusCode) -> Result<(), ProbeError> {
+    match status {
+        status if status.is_success() => Ok(()),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(ProbeError::Rejected(status)),
+        StatusCode::BAD_REQUEST if provider == Provider::Gemini => {
+            Err(ProbeError::Rejected(status))
+        }
+        status => Err(ProbeError::Unavailable(format!("HTTP {}", status))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checks(results: Vec<(&str, CheckResult)>) -> BTreeMap<String, CheckResult> {
+        results
+            .into_iter()
+            .map(|(name, result)| (name.to_string(), result))
+            .collect()
+    }
+
+    #[test]
+    fn test_readiness_status() {
+        let ok = || CheckResult::passed(Duration::from_millis(3));
+
+        let ready = Readiness::new(checks(vec![("redis", ok()), ("config", ok())]));
+        assert_eq!(ready.status, ReadyStatus::Ready);
+
+        let degraded = Readiness::new(checks(vec![
+            ("redis", ok()),
+            (
+                "provider:gemini",
+                CheckResult::failed(false, Duration::ZERO, "HTTP 503"),
+            ),
+        ]));
+        assert_eq!(degraded.status, ReadyStatus::Degraded);
+        assert!(degraded.is_ready());
+
+        let not_ready = Readiness::new(checks(vec![
+            (
+                "redis",
+                CheckResult::critical(Err("connection refused"), Duration::ZERO),
+            ),
+            ("config", ok()),
+        ]));
+        assert_eq!(not_ready.status, ReadyStatus::NotReady);
+        assert!(!not_ready.is_ready());
+
+        assert!(!Readiness::draining().is_ready());
+    }
+
+    #[test]
+    fn test_classify_probe_status() {
+        assert_eq!(classify(Provider::OpenAi, StatusCode::OK), Ok(()));
+        assert_eq!(
+            classify(Provider::Gemini, StatusCode::BAD_REQUEST),
+            Err(ProbeError::Rejected(StatusCode::BAD_REQUEST))
+        );
+        assert_eq!(
+            classify(Provider::Anthropic, StatusCode::UNAUTHORIZED),
+            Err(ProbeError::Rejected(StatusCode::UNAUTHORIZED))
+        );
+        assert!(matches!(
+            classify(Provider::OpenAi, StatusCode::SERVICE_UNAVAILABLE),
+            Err(ProbeError::Unavailable(_))
+        ));
+    }
+}
@@ -0,0 +1,218 @@
+//! # Métricas (Prometheus)
+//!
+//! Registro global de métricas expuesto en `GET /metrics`:
+//!
+//! | Métrica | Labels |
+//! |---------|--------|
+//! | `http_requests_total` / `http_request_duration_seconds` | `route`, `method`, `status`, `error_code` |
+//! | `llm_calls_total` / `llm_call_duration_seconds` | `provider`, `model`, `outcome`, `error_code` |
+//! | `llm_tokens_total` | `provider`, `model`, `kind` (`input`/`output`) |
+//! | `tool_calls_total` / `tool_call_duration_seconds` | `tool`, `outcome` |
+//! | `redis_operation_duration_seconds` | `operation`, `outcome` |
+//! | `active_sessions` | — |
+//!
+//! `error_code` es el de `ErrorKind::error_code` (vacío si no hubo error).
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Una sesión cuenta como activa si tuvo un turno en esta ventana.
+const ACTIVE_SESSION_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Buckets para llamadas a modelos y herramientas (de 100ms a 2 min).
+const SLOW_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 40.0, 60.0, 120.0];
+
+/// Buckets para operaciones de Redis (de 0.5ms a 1s).
+const FAST_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 1.0,
+];
+
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests: IntCounterVec,
+    pub http_duration: HistogramVec,
+    pub llm_calls: IntCounterVec,
+    pub llm_duration: HistogramVec,
+    pub llm_tokens: IntCounterVec,
+    pub tool_calls: IntCounterVec,
+    pub tool_duration: HistogramVec,
+    pub redis_duration: HistogramVec,
+    active_sessions: IntGauge,
+    sessions: Mutex<HashMap<String, Instant>>,
+}
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+pub fn get() -> &'static Metrics {
+    METRICS.get_or_init(Metrics::new)
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Peticiones HTTP atendidas"),
+            &["route", "method", "status", "error_code"],
+        )
+        .expect("invalid metric");
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Latencia de peticiones HTTP",
+            )
+            .buckets(SLOW_BUCKETS.to_vec()),
+            &["route", "method", "status"],
+        )
+        .expect("invalid metric");
+        let llm_calls = IntCounterVec::new(
+            Opts::new("llm_calls_total", "Llamadas de completion a modelos"),
+            &["provider", "model", "outcome", "error_code"],
+        )
+        .expect("invalid metric");
+        let llm_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "llm_call_duration_seconds",
+                "Latencia de llamadas a modelos",
+            )
+            .buckets(SLOW_BUCKETS.to_vec()),
+            &["provider", "model"],
+        )
+        .expect("invalid metric");
+        let llm_tokens = IntCounterVec::new(
+            Opts::new("llm_tokens_total", "Tokens consumidos por modelo"),
+            &["provider", "model", "kind"],
+        )
+        .expect("invalid metric");
+        let tool_calls = IntCounterVec::new(
+            Opts::new(
+                "tool_calls_total",
+                "Invocaciones de herramientas y especialistas",
+            ),
+            &["tool", "outcome"],
+        )
+        .expect("invalid metric");
+        let tool_duration = HistogramVec::new(
+            HistogramOpts::new("tool_call_duration_seconds", "Latencia de herramientas")
+                .buckets(SLOW_BUCKETS.to_vec()),
+            &["tool"],
+        )
+        .expect("invalid metric");
+        let redis_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "redis_operation_duration_seconds",
+                "Latencia de operaciones de Redis",
+            )
+            .buckets(FAST_BUCKETS.to_vec()),
+            &["operation", "outcome"],
+        )
+        .expect("invalid metric");
+        let active_sessions = IntGauge::new(
+            "active_sessions",
+            "Sesiones con actividad en los últimos 15 minutos",
+        )
+        .expect("invalid metric");
+
+        for collector in [
+            Box::new(http_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_duration.clone()),
+            Box::new(llm_calls.clone()),
+            Box::new(llm_duration.clone()),
+            Box::new(llm_tokens.clone()),
+            Box::new(tool_calls.clone()),
+            Box::new(tool_duration.clone()),
+            Box::new(redis_duration.clone()),
+            Box::new(active_sessions.clone()),
+        ] {
+            registry.register(collector).expect("duplicate metric");
+        }
+
+        Self {
+            registry,
+            http_requests,
+            http_duration,
+            llm_calls,
+            llm_duration,
+            llm_tokens,
+            tool_calls,
+            tool_duration,
+            redis_duration,
+            active_sessions,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Marca la sesión como activa.
+    pub fn touch_session(&self, session_id: &str) {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.insert(session_id.to_string(), Instant::now());
+    }
+
+    /// Actualiza el gauge de sesiones activas descartando las inactivas.
+    fn refresh_active_sessions(&self) {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.retain(|_, last_seen| last_seen.elapsed() < ACTIVE_SESSION_WINDOW);
+        self.active_sessions.set(sessions.len() as i64);
+    }
+
+    /// Serializa todas las métricas en el formato de texto de Prometheus.
+    pub fn render(&self) -> String {
+        self.refresh_active_sessions();
+
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+pub fn outcome<T, E>(result: &Result<T, E>) -> &'static str {
+    if result.is_ok() {
+        "ok"
+    } else {
+        "error"
+    }
+}
+
+/// Mide una operación de Redis.
+pub async fn time_redis<T, E, F>(operation: &'static str, future: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let start = Instant::now();
+    let result = future.await;
+    get()
+        .redis_duration
+        .with_label_values(&[operation, outcome(&result)])
+        .observe(start.elapsed().as_secs_f64());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_includes_recorded_metrics() {
+        let metrics = Metrics::new();
+        metrics
+            .http_requests
+            .with_label_values(&["/chat", "POST", "400", "VALIDATION_ERROR"])
+            .inc();
+        metrics.touch_session("session-1");
+        metrics.touch_session("session-2");
+        metrics.touch_session("session-1");
+
+        let output = metrics.render();
+        assert!(output.contains(
+            r#"http_requests_total{error_code="VALIDATION_ERROR",method="POST",route="/chat",status="400"} 1"#
+        ));
+        assert!(output.contains("active_sessions 2"));
+    }
+}
@@ -1,3 +1,11 @@
+pub mod audit;
+pub mod encryption;
 pub mod errors;
+pub mod hash;
+pub mod health;
+pub mod metrics;
+pub mod redaction;
 pub mod redis;
+pub mod shutdown;
 pub mod telemetry;
+pub mod usage;
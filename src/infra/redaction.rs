@@ -0,0 +1,443 @@
+//! # Redacción de PII
+//!
+//! Detecta datos personales (emails, teléfonos, tarjetas, identificaciones
+//! oficiales, direcciones y IDs de cliente) y los reemplaza de dos formas:
+//!
+//! - **Máscara** (`[EMAIL]`): irreversible. Se usa en logs, trazas y exportaciones.
+//! - **Token** (`[[EMAIL_1a2b3c4d]]`): reversible mediante un `Vault` por sesión.
+//!   Se usa en el historial persistido; al leerlo se restauran los valores reales
+//!   para que el orquestador y los especialistas trabajen con los datos correctos.
+//!
+//! Qué se redacta y dónde se configura con `PII_DETECTORS`, `PII_REDACT_LOGS`,
+//! `PII_REDACT_HISTORY` y `PII_REDACT_EXPORTS`.
+
+use crate::infra::hash::stable_hash;
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// ============================================================================
+// 1. TIPOS DE PII
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PiiKind {
+    Card,
+    Email,
+    NationalId,
+    CustomerId,
+    Address,
+    Phone,
+}
+
+impl PiiKind {
+    /// Orden de prioridad cuando dos detecciones se solapan.
+    pub const ALL: [PiiKind; 6] = [
+        PiiKind::Card,
+        PiiKind::Email,
+        PiiKind::NationalId,
+        PiiKind::CustomerId,
+        PiiKind::Address,
+        PiiKind::Phone,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PiiKind::Card => "CARD",
+            PiiKind::Email => "EMAIL",
+            PiiKind::NationalId => "NATIONAL_ID",
+            PiiKind::CustomerId => "CUSTOMER_ID",
+            PiiKind::Address => "ADDRESS",
+            PiiKind::Phone => "PHONE",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "card" => Some(PiiKind::Card),
+            "email" => Some(PiiKind::Email),
+            "national_id" => Some(PiiKind::NationalId),
+            "customer_id" => Some(PiiKind::CustomerId),
+            "address" => Some(PiiKind::Address),
+            "phone" => Some(PiiKind::Phone),
+            _ => None,
+        }
+    }
+
+    fn pattern(&self) -> &'static str {
+        match self {
+            PiiKind::Card => r"\b\d(?:[ -]?\d){12,18}\b",
+            PiiKind::Email => r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b",
+            // CURP, RFC (MX), DNI/NIE (ES), SSN (US)
+            PiiKind::NationalId => concat!(
+                r"(?i)\b[A-Z]{4}\d{6}[HM][A-Z]{5}[A-Z0-9]\d\b",
+                r"|\b[A-ZÑ&]{3,4}\d{6}[A-Z0-9]{3}\b",
+                r"|\b[XYZ]?\d{7,8}[A-Z]\b",
+                r"|\b\d{3}-\d{2}-\d{4}\b"
+            ),
+            PiiKind::CustomerId => r"(?i)\b(?:CLI|CUST|CLIENTE)-\d{3,}\b",
+            PiiKind::Address => concat!(
+                r"(?i)\b(?:calle|c/|avenida|av\.?|avda\.?|blvd\.?|boulevard|bulevar|calzada|calz\.?|",
+                r"carretera|camino|privada|priv\.?|paseo|callejón|street|st\.|avenue|ave\.?|road|rd\.)",
+                r"\s+[\p{L}\d .'-]{1,40}?\s*(?:#|no\.?|núm\.?|num\.?|número)?\s*\d{1,5}[a-z]?\b",
+                r"(?:\s*,?\s*(?:int\.?|interior|depto\.?|apt\.?)\s*\d+[a-z]?)?"
+            ),
+            PiiKind::Phone => r"(?:\+\d{1,3}[\s.-]?)?\(?\b\d{2,3}\)?[\s.-]?\d{3,4}[\s.-]?\d{4}\b",
+        }
+    }
+}
+
+/// Indica si `text` es, completo, un identificador de cliente (`CLI-12345`).
+pub fn is_customer_id(text: &str) -> bool {
+    static CUSTOMER_ID: OnceLock<Regex> = OnceLock::new();
+    CUSTOMER_ID
+        .get_or_init(|| {
+            Regex::new(&format!(r"^(?:{})$", PiiKind::CustomerId.pattern()))
+                .expect("Invalid customer id regex")
+        })
+        .is_match(text.trim())
+}
+
+// ============================================================================
+// 2. VAULT (TOKENIZACIÓN REVERSIBLE)
+// ============================================================================
+
+/// Relación `token → valor original` de una sesión.
+#[derive(Debug, Clone, Default)]
+pub struct Vault {
+    entries: HashMap<String, String>,
+}
+
+impl Vault {
+    pub fn from_entries(entries: HashMap<String, String>) -> Self {
+        Self { entries }
+    }
+
+    pub fn entries(&self) -> &HashMap<String, String> {
+        &self.entries
+    }
+
+    /// Restaura los valores originales de los tokens presentes en `text`.
+    pub fn detokenize(&self, text: &str) -> String {
+        token_regex()
+            .replace_all(text, |caps: &regex::Captures| {
+                self.entries
+                    .get(&caps[0])
+                    .cloned()
+                    .unwrap_or_else(|| caps[0].to_string())
+            })
+            .into_owned()
+    }
+}
+
+fn token_regex() -> &'static Regex {
+    static TOKEN: OnceLock<Regex> = OnceLock::new();
+    TOKEN.get_or_init(|| Regex::new(r"\[\[([A-Z_]+)_[0-9a-f]{8}\]\]").expect("Invalid token regex"))
+}
+
+/// Reemplaza tokens por su máscara (`[[EMAIL_1a2b3c4d]]` → `[EMAIL]`).
+pub fn mask_tokens(text: &str) -> Cow<'_, str> {
+    token_regex().replace_all(text, "[$1]")
+}
+
+// ============================================================================
+// 3. REDACTOR
+// ============================================================================
+
+#[derive(Debug)]
+pub struct Redactor {
+    detectors: Vec<(PiiKind, Regex)>,
+    pub redact_logs: bool,
+    pub redact_history: bool,
+    pub redact_exports: bool,
+}
+
+static REDACTOR: OnceLock<Redactor> = OnceLock::new();
+
+/// Redactor global configurado desde el entorno.
+pub fn get() -> &'static Redactor {
+    REDACTOR.get_or_init(Redactor::from_config)
+}
+
+impl Redactor {
+    pub fn new(kinds: &[PiiKind]) -> Self {
+        let detectors = PiiKind::ALL
+            .into_iter()
+            .filter(|kind| kinds.contains(kind))
+            .map(|kind| {
+                (
+                    kind,
+                    Regex::new(kind.pattern()).expect("Invalid PII pattern"),
+                )
+            })
+            .collect();
+
+        Self {
+            detectors,
+            redact_logs: true,
+            redact_history: true,
+            redact_exports: true,
+        }
+    }
+
+    fn from_config() -> Self {
+        let config = crate::config::get();
+        let kinds: Vec<PiiKind> = config
+            .privacy
+            .pii_detectors
+            .split(',')
+            .filter_map(PiiKind::parse)
+            .collect();
+
+        Self {
+            redact_logs: config.privacy.redact_logs,
+            redact_history: config.privacy.redact_history,
+            redact_exports: config.privacy.redact_exports,
+            ..Self::new(&kinds)
+        }
+    }
+
+    /// Detecta PII en `text`: rangos de bytes sin solapamiento, en orden.
+    pub fn detect(&self, text: &str) -> Vec<(PiiKind, std::ops::Range<usize>)> {
+        let mut found: Vec<(PiiKind, std::ops::Range<usize>)> = Vec::new();
+
+        for (kind, regex) in &self.detectors {
+            for m in regex.find_iter(text) {
+                if *kind == PiiKind::Card && !luhn_valid(m.as_str()) {
+                    continue;
+                }
+                let overlaps = found
+                    .iter()
+                    .any(|(_, r)| r.start < m.end() && m.start() < r.end);
+                if !overlaps {
+                    found.push((*kind, m.range()));
+                }
+            }
+        }
+
+        found.sort_by_key(|(_, range)| range.start);
+        found
+    }
+
+    /// Reemplaza la PII por máscaras irreversibles (`[EMAIL]`). También
+    /// enmascara tokens de vault que ya estuvieran en el texto.
+    pub fn mask<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let text = mask_tokens(text);
+        let found = self.detect(&text);
+        if found.is_empty() {
+            return text;
+        }
+
+        Cow::Owned(replace_ranges(&text, &found, |kind, _| {
+            format!("[{}]", kind.label())
+        }))
+    }
+
+    /// Reemplaza la PII por tokens reversibles y los registra en `vault`.
+    /// El token depende de la sesión y del valor, así que el mismo dato
+    /// produce siempre el mismo token dentro de una sesión.
+    pub fn tokenize(&self, session_id: &str, text: &str, vault: &mut Vault) -> String {
+        let found = self.detect(text);
+        if found.is_empty() {
+            return text.to_string();
+        }
+
+        replace_ranges(text, &found, |kind, value| {
+            let seed = format!("{}:{}", session_id, value);
+            let token = format!(
+                "[[{}_{:08x}]]",
+                kind.label(),
+                stable_hash(seed.as_bytes()) as u32
+            );
+            vault.entries.insert(token.clone(), value.to_string());
+            token
+        })
+    }
+}
+
+/// Claves de un log JSON de Cloud Logging que nunca contienen PII y deben
+/// conservarse intactas (metadatos de la entrada, correlación de trazas).
+const LOG_METADATA_KEYS: [&str; 7] = [
+    "time",
+    "severity",
+    "target",
+    "name",
+    "session_id",
+    "message_id",
+    "variant",
+];
+
+impl Redactor {
+    /// Enmascara los strings de un documento JSON (una línea de log),
+    /// preservando los metadatos de la entrada.
+    pub fn mask_json(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(text) => {
+                if let Cow::Owned(masked) = self.mask(text) {
+                    *text = masked;
+                }
+            }
+            serde_json::Value::Array(items) => {
+                items.iter_mut().for_each(|item| self.mask_json(item))
+            }
+            serde_json::Value::Object(fields) => {
+                for (key, field) in fields.iter_mut() {
+                    if LOG_METADATA_KEYS.contains(&key.as_str())
+                        || key.starts_with("logging.googleapis.com/")
+                    {
+                        continue;
+                    }
+                    self.mask_json(field);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn replace_ranges<F>(text: &str, found: &[(PiiKind, std::ops::Range<usize>)], mut f: F) -> String
+where
+    F: FnMut(PiiKind, &str) -> String,
+{
+    let mut output = String::with_capacity(text.len());
+    let mut cursor = 0;
+
+    for (kind, range) in found {
+        output.push_str(&text[cursor..range.start]);
+        output.push_str(&f(*kind, &text[range.clone()]));
+        cursor = range.end;
+    }
+    output.push_str(&text[cursor..]);
+
+    output
+}
+
+/// Algoritmo de Luhn para descartar secuencias numéricas que no son tarjetas.
+fn luhn_valid(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            if i % 2 == 1 {
+                let doubled = d * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                d
+            }
+        })
+        .sum();
+
+    sum.is_multiple_of(10)
+}
+
+// ============================================================================
+// 4. TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor() -> Redactor {
+        Redactor::new(&PiiKind::ALL)
+    }
+
+    #[test]
+    fn test_mask_detects_each_kind() {
+        let r = redactor();
+
+        assert_eq!(
+            r.mask("escríbeme a ana.perez@correo.com"),
+            "escríbeme a [EMAIL]"
+        );
+        assert_eq!(r.mask("mi cel es +52 55 1234 5678"), "mi cel es [PHONE]");
+        assert_eq!(r.mask("tarjeta 4111 1111 1111 1111"), "tarjeta [CARD]");
+        assert_eq!(r.mask("CURP GOMC800101HDFRRR09"), "CURP [NATIONAL_ID]");
+        assert_eq!(
+            r.mask("soy el cliente CLI-12345"),
+            "soy el cliente [CUSTOMER_ID]"
+        );
+        assert_eq!(
+            r.mask("envíalo a Av. Reforma 222, CDMX"),
+            "envíalo a [ADDRESS], CDMX"
+        );
+    }
+
+    #[test]
+    fn test_card_requires_luhn() {
+        let r = Redactor::new(&[PiiKind::Card]);
+        assert_eq!(
+            r.mask("pedido 1234 5678 9012 3456"),
+            "pedido 1234 5678 9012 3456"
+        );
+    }
+
+    #[test]
+    fn test_tokenize_roundtrip() {
+        let r = redactor();
+        let mut vault = Vault::default();
+        let original = "Soy CLI-12345, mi correo es ana@correo.com";
+
+        let tokenized = r.tokenize("session-1", original, &mut vault);
+        assert!(!tokenized.contains("ana@correo.com"));
+        assert!(tokenized.contains("[[EMAIL_"));
+        assert_eq!(vault.detokenize(&tokenized), original);
+
+        // Mismo valor, misma sesión → mismo token.
+        let again = r.tokenize("session-1", "ana@correo.com", &mut vault);
+        assert!(tokenized.contains(&again));
+    }
+
+    #[test]
+    fn test_mask_replaces_existing_tokens() {
+        let r = redactor();
+        let mut vault = Vault::default();
+        let tokenized = r.tokenize("s", "ana@correo.com", &mut vault);
+        assert_eq!(r.mask(&tokenized), "[EMAIL]");
+    }
+
+    #[test]
+    fn test_mask_json_keeps_log_metadata() {
+        let r = redactor();
+        let mut line = serde_json::json!({
+            "severity": "INFO",
+            "session_id": "abc",
+            "logging.googleapis.com/trace": "projects/p/traces/1",
+            "message": "cliente ana@correo.com",
+            "span": { "name": "chat", "prompt": "llama al 55 1234 5678" },
+        });
+
+        r.mask_json(&mut line);
+
+        assert_eq!(line["message"], "cliente [EMAIL]");
+        assert_eq!(line["span"]["prompt"], "llama al [PHONE]");
+        assert_eq!(line["span"]["name"], "chat");
+        assert_eq!(line["logging.googleapis.com/trace"], "projects/p/traces/1");
+    }
+
+    #[test]
+    fn test_disabled_detectors_are_ignored() {
+        let r = Redactor::new(&[PiiKind::Email]);
+        assert_eq!(r.mask("CLI-12345"), "CLI-12345");
+    }
+
+    #[test]
+    fn test_is_customer_id() {
+        assert!(is_customer_id("CLI-12345"));
+        assert!(is_customer_id(" cliente-0042 "));
+        assert!(!is_customer_id("N/A"));
+        assert!(!is_customer_id("desconocido"));
+        assert!(!is_customer_id("soy CLI-12345"));
+    }
+}
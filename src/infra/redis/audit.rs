@@ -0,0 +1,122 @@
+//! Registro de auditoría en Redis (ver `infra/audit.rs`).
+//!
+//! ## Claves
+//! - `{base}:audit` — stream con un campo `event` (JSON) por entrada. No
+//!   expira ni se recorta: el registro es de sólo escritura.
+
+use super::RedisProvider;
+use crate::infra::audit::{AuditEvent, AuditFilter, AuditSink};
+use crate::infra::metrics::time_redis;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+/// Entradas leídas por vuelta al consultar.
+const QUERY_BATCH: usize = 200;
+
+impl RedisProvider {
+    fn audit_key(&self) -> String {
+        format!("{}:audit", self.base_path)
+    }
+}
+
+#[async_trait]
+impl AuditSink for RedisProvider {
+    async fn append(&self, event: &AuditEvent) -> Result<()> {
+        let mut con = self.connection.clone();
+        time_redis(
+            "audit_append",
+            redis::cmd("XADD")
+                .arg(self.audit_key())
+                .arg("*")
+                .arg("event")
+                .arg(serde_json::to_string(event)?)
+                .query_async::<String>(&mut con),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Recorre el stream hacia atrás desde `to`: los ids son milisegundos
+    /// epoch, así que el rango de fechas se resuelve en Redis.
+    async fn query(&self, filter: &AuditFilter) -> Result<Vec<AuditEvent>> {
+        let mut con = self.connection.clone();
+        let key = self.audit_key();
+        let start = filter.from.saturating_mul(1_000).to_string();
+        let mut end = filter
+            .to
+            .saturating_mul(1_000)
+            .saturating_add(999)
+            .to_string();
+
+        let mut events = Vec::new();
+        while events.len() < filter.limit {
+            let entries: Vec<(String, HashMap<String, String>)> = time_redis(
+                "audit_query",
+                redis::cmd("XREVRANGE")
+                    .arg(&key)
+                    .arg(&end)
+                    .arg(&start)
+                    .arg("COUNT")
+                    .arg(QUERY_BATCH)
+                    .query_async(&mut con),
+            )
+            .await?;
+            let fetched = entries.len();
+
+            for (_, fields) in &entries {
+                let Some(event) = fields
+                    .get("event")
+                    .and_then(|json| serde_json::from_str::<AuditEvent>(json).ok())
+                else {
+                    continue;
+                };
+                if filter.matches(&event) && events.len() < filter.limit {
+                    events.push(event);
+                }
+            }
+
+            match entries.last() {
+                Some((id, _)) if fetched == QUERY_BATCH => end = format!("({}", id),
+                _ => break,
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::audit::AuditAction;
+    use crate::infra::redis::unix_now;
+
+    #[tokio::test]
+    async fn test_stream_query_filters_newest_first() {
+        let (provider, memory) = RedisProvider::in_memory(3_600, 0);
+        for (n, caller) in ["web", "mobile", "web"].iter().enumerate() {
+            let event = AuditEvent::new(AuditAction::DamageClaim, "damage_specialist", caller)
+                .with_session(&format!("s{}", n));
+            provider.append(&event).await.unwrap();
+        }
+
+        let filter = AuditFilter {
+            from: 0,
+            to: unix_now(),
+            caller: Some("web".to_string()),
+            limit: 10,
+            ..Default::default()
+        };
+        let events = provider.query(&filter).await.unwrap();
+        let sessions: Vec<&str> = events
+            .iter()
+            .filter_map(|e| e.session_id.as_deref())
+            .collect();
+        assert_eq!(sessions, ["s2", "s0"]);
+        assert!(!memory.store().ttls.contains_key("TEST:audit"));
+
+        let limited = AuditFilter { limit: 1, ..filter };
+        assert_eq!(provider.query(&limited).await.unwrap().len(), 1);
+    }
+}
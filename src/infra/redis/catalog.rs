@@ -0,0 +1,120 @@
+//! Catálogo de artículos importado con `POST /admin/catalog`, compartido por
+//! todas las instancias (ver `agents/tools/catalog.rs`).
+//!
+//! ## Claves
+//! - `{base}:catalog` — `StoredCatalog` (JSON) de la última importación. No expira.
+//! - `{base}:catalog:version` — versión de esa importación, para detectar un
+//!   catálogo nuevo sin descargarlo.
+
+use super::{unix_now, RedisProvider};
+use crate::agents::tools::catalog::{Catalog, CatalogItem};
+use crate::infra::errors::{DomainError, DomainResult};
+use crate::infra::metrics::time_redis;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct StoredCatalog {
+    version: String,
+    imported_at: u64,
+    items: Vec<CatalogItem>,
+}
+
+/// Respuesta de `POST /admin/catalog`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CatalogImport {
+    pub version: String,
+    pub items: usize,
+    pub imported_at: u64,
+}
+
+impl RedisProvider {
+    fn catalog_key(&self) -> String {
+        format!("{}:catalog", self.base_path)
+    }
+
+    fn catalog_version_key(&self) -> String {
+        format!("{}:catalog:version", self.base_path)
+    }
+
+    /// Guarda el catálogo y su versión en una transacción.
+    pub async fn save_catalog(&self, catalog: &Catalog) -> DomainResult<CatalogImport> {
+        let mut con = self.connection.clone();
+        let stored = StoredCatalog {
+            version: catalog.version().to_string(),
+            imported_at: unix_now(),
+            items: catalog.items().to_vec(),
+        };
+
+        time_redis(
+            "save_catalog",
+            redis::pipe()
+                .atomic()
+                .set(self.catalog_key(), serde_json::to_string(&stored)?)
+                .ignore()
+                .set(self.catalog_version_key(), &stored.version)
+                .ignore()
+                .query_async::<()>(&mut con),
+        )
+        .await?;
+
+        Ok(CatalogImport {
+            version: stored.version,
+            items: stored.items.len(),
+            imported_at: stored.imported_at,
+        })
+    }
+
+    /// Catálogo importado, si hay uno y su versión no es `current`.
+    pub async fn load_catalog(&self, current: &str) -> DomainResult<Option<Catalog>> {
+        let mut con = self.connection.clone();
+        let version: Option<String> = time_redis(
+            "catalog_version",
+            con.get::<_, Option<String>>(self.catalog_version_key()),
+        )
+        .await?;
+        if version.is_none_or(|v| v == current) {
+            return Ok(None);
+        }
+
+        let payload: Option<String> = time_redis(
+            "load_catalog",
+            con.get::<_, Option<String>>(self.catalog_key()),
+        )
+        .await?;
+        let Some(payload) = payload else {
+            return Ok(None);
+        };
+        let stored: StoredCatalog = serde_json::from_str(&payload)?;
+        Catalog::new(stored.items)
+            .map(Some)
+            .map_err(|e| DomainError::internal(format!("Catálogo guardado inválido: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_catalog_round_trip() {
+        let (provider, memory) = RedisProvider::in_memory(3_600, 0);
+        assert!(provider.load_catalog("").await.unwrap().is_none());
+
+        let csv = "sku,name,replacement_price,currency,stock\nL-1,Lámpara,100,MXN,2\n";
+        let catalog = Catalog::from_csv(csv).unwrap();
+        let import = provider.save_catalog(&catalog).await.unwrap();
+        assert_eq!(import.items, 1);
+        assert_eq!(import.version, catalog.version());
+        assert_eq!(memory.store().transactions, 1);
+
+        let loaded = provider.load_catalog("otra").await.unwrap().unwrap();
+        assert_eq!(loaded.items(), catalog.items());
+        // Sin cambios de versión no se descarga.
+        assert!(provider
+            .load_catalog(catalog.version())
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,250 @@
+//! Conexión a Redis según la topología (`store.topology`):
+//!
+//! - `standalone`: `ConnectionManager` sobre `REDIS_URL`. Si Redis se reinicia,
+//!   reconecta solo con backoff exponencial (`REDIS_RECONNECT_RETRIES`,
+//!   `REDIS_RECONNECT_MAX_DELAY_MS`) en lugar de fallar hasta reciclar el proceso.
+//! - `sentinel`: `REDIS_URL` lista los sentinels (`redis://s1:26379,redis://s2:26379`)
+//!   y `REDIS_SENTINEL_SERVICE` el master. Ante un failover (conexión rechazada
+//!   o `READONLY`) se vuelve a resolver el master.
+//! - `cluster`: `REDIS_URL` lista nodos semilla; el cliente sigue `MOVED`/`ASK`
+//!   y reintenta. Todas las claves comparten el hash tag `{base_path}` y viven
+//!   en un solo slot: el cluster aporta alta disponibilidad, no reparto de las
+//!   sesiones entre nodos. Un tag por sesión (`{base}:{session_id}`) no basta,
+//!   porque cada `MULTI` de un turno también actualiza índices globales (por
+//!   caller, dueño, estado, titular, feedback y cifrado) en la misma
+//!   transacción. Repartir las sesiones exigiría sacar esos índices de la
+//!   transacción y aceptar que queden desalineados tras un corte.
+//!
+//! `rediss://` activa TLS en cualquier topología. Las credenciales de la
+//! primera URL se usan también para el master resuelto por Sentinel.
+
+use crate::config::{RedisTopology, StoreSection};
+use redis::aio::{ConnectionLike, ConnectionManager, ConnectionManagerConfig};
+use redis::cluster::ClusterClientBuilder;
+use redis::cluster_async::ClusterConnection;
+use redis::sentinel::{SentinelClient, SentinelNodeConnectionInfo, SentinelServerType};
+use redis::{
+    Cmd, ConnectionAddr, ErrorKind, IntoConnectionInfo, Pipeline, RedisError, RedisFuture,
+    RedisResult, TlsMode, Value,
+};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+#[derive(Clone)]
+pub enum RedisConnection {
+    Standalone(ConnectionManager),
+    Sentinel(SentinelConnection),
+    Cluster(ClusterConnection),
+    #[cfg(test)]
+    Memory(super::memory::MemoryConnection),
+}
+
+impl RedisConnection {
+    pub async fn connect(store: &StoreSection) -> RedisResult<Self> {
+        let urls = store.redis_urls();
+        let timeout = Duration::from_millis(store.timeout_ms);
+        let manager_config = ConnectionManagerConfig::new()
+            .set_connection_timeout(timeout)
+            .set_response_timeout(timeout)
+            .set_number_of_retries(store.reconnect_retries)
+            .set_max_delay(store.reconnect_max_delay_ms);
+
+        match store.topology {
+            RedisTopology::Standalone => {
+                let client = redis::Client::open(urls[0])?;
+                let manager = ConnectionManager::new_with_config(client, manager_config).await?;
+                Ok(RedisConnection::Standalone(manager))
+            }
+            RedisTopology::Sentinel => {
+                let node = urls[0].into_connection_info()?;
+                let tls_mode = match node.addr {
+                    ConnectionAddr::TcpTls { insecure, .. } => Some(if insecure {
+                        TlsMode::Insecure
+                    } else {
+                        TlsMode::Secure
+                    }),
+                    _ => None,
+                };
+                let client = SentinelClient::build(
+                    urls,
+                    store.sentinel_service.clone(),
+                    Some(SentinelNodeConnectionInfo {
+                        tls_mode,
+                        redis_connection_info: Some(node.redis),
+                    }),
+                    SentinelServerType::Master,
+                )?;
+                let connection = SentinelConnection::connect(client, manager_config).await?;
+                Ok(RedisConnection::Sentinel(connection))
+            }
+            RedisTopology::Cluster => {
+                let client = ClusterClientBuilder::new(urls)
+                    .connection_timeout(timeout)
+                    .response_timeout(timeout)
+                    .retries(store.reconnect_retries as u32)
+                    .max_retry_wait(store.reconnect_max_delay_ms)
+                    .build()?;
+                Ok(RedisConnection::Cluster(
+                    client.get_async_connection().await?,
+                ))
+            }
+        }
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            RedisConnection::Standalone(con) => con.req_packed_command(cmd),
+            RedisConnection::Sentinel(con) => con.req_packed_command(cmd),
+            RedisConnection::Cluster(con) => con.req_packed_command(cmd),
+            #[cfg(test)]
+            RedisConnection::Memory(con) => con.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            RedisConnection::Standalone(con) => con.req_packed_commands(cmd, offset, count),
+            RedisConnection::Sentinel(con) => con.req_packed_commands(cmd, offset, count),
+            RedisConnection::Cluster(con) => con.req_packed_commands(cmd, offset, count),
+            #[cfg(test)]
+            RedisConnection::Memory(con) => con.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            RedisConnection::Standalone(con) => con.get_db(),
+            RedisConnection::Sentinel(con) => con.get_db(),
+            RedisConnection::Cluster(con) => con.get_db(),
+            #[cfg(test)]
+            RedisConnection::Memory(con) => con.get_db(),
+        }
+    }
+}
+
+// ============================================================================
+// SENTINEL
+// ============================================================================
+
+/// `ConnectionManager` hacia el master actual. Tras un failover, el primer
+/// comando que falla provoca una nueva consulta a los sentinels.
+#[derive(Clone)]
+pub struct SentinelConnection {
+    sentinel: Arc<tokio::sync::Mutex<SentinelClient>>,
+    config: ConnectionManagerConfig,
+    master: Arc<RwLock<ConnectionManager>>,
+}
+
+impl SentinelConnection {
+    async fn connect(
+        mut sentinel: SentinelClient,
+        config: ConnectionManagerConfig,
+    ) -> RedisResult<Self> {
+        let master = Self::resolve(&mut sentinel, &config).await?;
+        Ok(Self {
+            sentinel: Arc::new(tokio::sync::Mutex::new(sentinel)),
+            config,
+            master: Arc::new(RwLock::new(master)),
+        })
+    }
+
+    async fn resolve(
+        sentinel: &mut SentinelClient,
+        config: &ConnectionManagerConfig,
+    ) -> RedisResult<ConnectionManager> {
+        let client = sentinel.async_get_client().await?;
+        ConnectionManager::new_with_config(client, config.clone()).await
+    }
+
+    fn master(&self) -> ConnectionManager {
+        self.master
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    async fn failover(&self) -> RedisResult<ConnectionManager> {
+        let mut sentinel = self.sentinel.lock().await;
+        let master = Self::resolve(&mut sentinel, &self.config).await?;
+        *self.master.write().unwrap_or_else(|e| e.into_inner()) = master.clone();
+        tracing::warn!("Redis master re-resolved through Sentinel");
+        Ok(master)
+    }
+
+    /// Ejecuta `request` contra el master. Sólo se reintenta si el comando no
+    /// llegó a ejecutarse (conexión rechazada o réplica en sólo lectura).
+    async fn run<T, F, Fut>(&self, request: F) -> RedisResult<T>
+    where
+        F: Fn(ConnectionManager) -> Fut,
+        Fut: Future<Output = RedisResult<T>>,
+    {
+        match request(self.master()).await {
+            Err(e) if needs_failover(&e) => {
+                let master = self.failover().await?;
+                if e.kind() == ErrorKind::ReadOnly || e.is_connection_refusal() {
+                    request(master).await
+                } else {
+                    Err(e)
+                }
+            }
+            result => result,
+        }
+    }
+}
+
+fn needs_failover(error: &RedisError) -> bool {
+    error.kind() == ErrorKind::ReadOnly
+        || error.is_connection_refusal()
+        || error.is_connection_dropped()
+}
+
+impl ConnectionLike for SentinelConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(self.run(move |mut con| async move { con.req_packed_command(cmd).await }))
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(
+            self.run(
+                move |mut con| async move { con.req_packed_commands(cmd, offset, count).await },
+            ),
+        )
+    }
+
+    fn get_db(&self) -> i64 {
+        self.master().get_db()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failover_errors() {
+        assert!(needs_failover(&RedisError::from((
+            ErrorKind::ReadOnly,
+            "You can't write against a read only replica."
+        ))));
+        assert!(needs_failover(&RedisError::from(std::io::Error::from(
+            std::io::ErrorKind::ConnectionRefused
+        ))));
+        assert!(!needs_failover(&RedisError::from((
+            ErrorKind::TypeError,
+            "WRONGTYPE"
+        ))));
+    }
+}
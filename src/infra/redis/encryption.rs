@@ -0,0 +1,607 @@
+//! Data keys de las sesiones cifradas (ver `infra/encryption.rs`).
+//!
+//! ## Claves
+//! - `{base}:{session_id}:dek` — DEK envuelta por la master key, con el TTL de la sesión.
+//! - `{base}:{session_id}:dek:retired` — DEK anterior a la última re-cifrado,
+//!   también envuelta. Abre lo que un turno en curso haya cifrado con ella.
+//! - `{base}:encryption:sessions` — sorted set `session_id` por fecha de la última
+//!   escritura, usado por la rotación para encontrar las DEKs vigentes.
+//!
+//! La re-cifrado (`reencrypt_sessions`) recorre además los índices de cada
+//! caller para encontrar las sesiones guardadas en claro, que no tienen DEK.
+
+use super::sessions::SEALED_FIELDS;
+use super::{unix_now, RedisProvider};
+use crate::infra::encryption::{self as crypto, DataKey, Encryption, WrappedKey};
+use crate::infra::errors::DomainResult;
+use crate::infra::metrics::time_redis;
+use anyhow::{Context, Result};
+use redis::AsyncCommands;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+/// DEKs leídas por vuelta durante la rotación.
+const ROTATION_BATCH: usize = 100;
+
+/// Resultado de `POST /admin/encryption/rotate`.
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct KeyRotation {
+    pub active_key: String,
+    /// DEKs re-envueltas con la master key activa.
+    pub rewrapped: usize,
+    /// DEKs que ya usaban la master key activa.
+    pub up_to_date: usize,
+    /// Sesiones con una DEK nueva y su contenido re-cifrado (`reencrypt=true`).
+    pub reencrypted: usize,
+    /// Sesiones guardadas en claro que se cifraron (`reencrypt=true`).
+    pub migrated: usize,
+    /// DEKs que no se pudieron desenvolver (master key ausente del keyring) o
+    /// sesiones que no se pudieron re-cifrar.
+    pub failed: usize,
+}
+
+/// Lista de mensajes, vault de PII, metadatos, DEKs envueltas (vigente y
+/// retirada) y TTL restante de una sesión.
+type ReencryptionRead = (
+    Vec<String>,
+    HashMap<String, String>,
+    HashMap<String, String>,
+    Option<String>,
+    Option<String>,
+    i64,
+);
+
+/// Resultado de re-cifrar una sesión.
+enum Reencryption {
+    Rotated,
+    Migrated,
+    Expired,
+}
+
+impl RedisProvider {
+    pub(super) fn dek_key(&self, session_id: &str) -> String {
+        format!("{}:dek", self.get_key(session_id))
+    }
+
+    pub(super) fn retired_dek_key(&self, session_id: &str) -> String {
+        format!("{}:retired", self.dek_key(session_id))
+    }
+
+    pub(super) fn encryption_index_key(&self) -> String {
+        format!("{}:encryption:sessions", self.base_path)
+    }
+
+    /// Desenvuelve la DEK leída de Redis. `None` si la sesión no tiene DEK
+    /// (nueva o guardada sin cifrado).
+    pub(super) async fn open_data_key(&self, wrapped: Option<String>) -> Result<Option<DataKey>> {
+        let Some(wrapped) = wrapped else {
+            return Ok(None);
+        };
+        let Some(encryption) = &self.encryption else {
+            anyhow::bail!("the session is encrypted but ENCRYPTION_KEYRING is not configured");
+        };
+        Ok(Some(encryption.open(&WrappedKey::decode(&wrapped)?).await?))
+    }
+
+    /// DEKs con las que se leen los valores de la sesión: la vigente y, si
+    /// hubo una re-cifrado, la retirada. Vacío si la sesión no tiene DEK.
+    pub(super) async fn open_data_keys(
+        &self,
+        wrapped: Option<String>,
+        retired: Option<String>,
+    ) -> Result<Vec<DataKey>> {
+        let mut data_keys = Vec::new();
+        for wrapped in [wrapped, retired] {
+            data_keys.extend(self.open_data_key(wrapped).await?);
+        }
+        Ok(data_keys)
+    }
+
+    /// DEK con la que se cifran los mensajes nuevos de la sesión; la crea si
+    /// no existe. `None` si el cifrado está deshabilitado.
+    pub(super) async fn session_data_key(&self, session_id: &str) -> Result<Option<DataKey>> {
+        let Some(encryption) = &self.encryption else {
+            return Ok(None);
+        };
+        let mut con = self.connection.clone();
+        let key = self.dek_key(session_id);
+
+        let existing: Option<String> = time_redis("get_data_key", con.get(&key)).await?;
+        if existing.is_some() {
+            return self.open_data_key(existing).await;
+        }
+
+        let (data_key, wrapped) = encryption.generate().await?;
+        let created: bool = time_redis(
+            "create_data_key",
+            redis::cmd("SET")
+                .arg(&key)
+                .arg(wrapped.encode())
+                .arg("NX")
+                .arg("EX")
+                .arg(self.ttl)
+                .query_async::<Option<String>>(&mut con),
+        )
+        .await?
+        .is_some();
+        if created {
+            return Ok(Some(data_key));
+        }
+
+        // Otro turno concurrente creó la DEK primero: se usa la suya.
+        let existing: Option<String> = time_redis("get_data_key", con.get(&key)).await?;
+        self.open_data_key(existing).await
+    }
+
+    /// Agrega al pipeline la renovación del TTL de la DEK y del índice de rotación.
+    pub(super) fn queue_data_key_refresh(&self, pipe: &mut redis::Pipeline, session_id: &str) {
+        let now = unix_now();
+        let index = self.encryption_index_key();
+        pipe.expire(self.dek_key(session_id), self.ttl as i64)
+            .ignore()
+            .expire(self.retired_dek_key(session_id), self.ttl as i64)
+            .ignore()
+            .zadd(&index, session_id, now)
+            .ignore()
+            .zrembyscore(&index, "-inf", now.saturating_sub(self.ttl))
+            .ignore();
+    }
+
+    /// Re-envuelve con la master key activa las DEKs (vigentes y retiradas)
+    /// de todas las sesiones vigentes. Los mensajes no se tocan: la DEK no
+    /// cambia.
+    pub async fn rotate_data_keys(&self) -> DomainResult<KeyRotation> {
+        let Some(encryption) = &self.encryption else {
+            return Err(crate::infra::errors::DomainError::validation(
+                "El cifrado en reposo no está habilitado",
+            ));
+        };
+        let mut con = self.connection.clone();
+        let index = self.encryption_index_key();
+        let sessions: Vec<String> =
+            time_redis("rotate_data_keys", con.zrange(&index, 0, -1)).await?;
+
+        let mut report = KeyRotation {
+            active_key: encryption.active_key_id().to_string(),
+            ..Default::default()
+        };
+        for batch in sessions.chunks(ROTATION_BATCH) {
+            let keys: Vec<String> = batch
+                .iter()
+                .flat_map(|id| [self.dek_key(id), self.retired_dek_key(id)])
+                .collect();
+            let wrapped: Vec<Option<String>> =
+                time_redis("rotate_data_keys", con.mget(&keys)).await?;
+
+            let mut pipe = redis::pipe();
+            for ((session_id, keys), wrapped) in
+                batch.iter().zip(keys.chunks(2)).zip(wrapped.chunks(2))
+            {
+                // Sesión expirada: se quita del índice.
+                if wrapped[0].is_none() {
+                    pipe.zrem(&index, session_id).ignore();
+                    continue;
+                }
+                for (key, wrapped) in keys.iter().zip(wrapped) {
+                    let Some(wrapped) = wrapped else {
+                        continue;
+                    };
+                    let rewrapped = match WrappedKey::decode(wrapped) {
+                        Ok(wrapped) => encryption.rewrap(&wrapped).await,
+                        Err(e) => Err(e),
+                    };
+                    match rewrapped {
+                        Ok(Some(rewrapped)) => {
+                            pipe.cmd("SET")
+                                .arg(key)
+                                .arg(rewrapped.encode())
+                                .arg("XX")
+                                .arg("KEEPTTL")
+                                .ignore();
+                            report.rewrapped += 1;
+                        }
+                        Ok(None) => report.up_to_date += 1,
+                        Err(e) => {
+                            tracing::warn!(session_id, "Failed to rewrap data key: {:#}", e);
+                            report.failed += 1;
+                        }
+                    }
+                }
+            }
+            time_redis("rotate_data_keys", pipe.query_async::<()>(&mut con)).await?;
+        }
+
+        tracing::info!(
+            active_key = %report.active_key,
+            rewrapped = report.rewrapped,
+            failed = report.failed,
+            "Data keys rotated"
+        );
+        Ok(report)
+    }
+
+    /// Da una DEK nueva a cada sesión vigente y re-cifra con ella el historial,
+    /// el vault y el título y el resumen. Las sesiones guardadas en claro se
+    /// cifran por primera vez. `callers` son los índices por caller donde
+    /// buscar las sesiones que todavía no tienen DEK.
+    pub async fn reencrypt_sessions(&self, callers: &[String]) -> DomainResult<KeyRotation> {
+        let Some(encryption) = &self.encryption else {
+            return Err(crate::infra::errors::DomainError::validation(
+                "El cifrado en reposo no está habilitado",
+            ));
+        };
+        let mut con = self.connection.clone();
+        let mut pipe = redis::pipe();
+        pipe.zrange(self.encryption_index_key(), 0, -1);
+        for caller in callers {
+            pipe.zrange(self.caller_index_key(caller), 0, -1);
+        }
+        let indexes: Vec<Vec<String>> =
+            time_redis("reencrypt_sessions", pipe.query_async(&mut con)).await?;
+        let sessions: BTreeSet<String> = indexes.into_iter().flatten().collect();
+
+        let mut report = KeyRotation {
+            active_key: encryption.active_key_id().to_string(),
+            ..Default::default()
+        };
+        for session_id in &sessions {
+            match self.reencrypt_session(encryption, session_id).await {
+                Ok(Reencryption::Rotated) => report.reencrypted += 1,
+                Ok(Reencryption::Migrated) => report.migrated += 1,
+                Ok(Reencryption::Expired) => {}
+                Err(e) => {
+                    tracing::warn!(session_id, "Failed to re-encrypt session: {:#}", e);
+                    report.failed += 1;
+                }
+            }
+        }
+
+        tracing::info!(
+            active_key = %report.active_key,
+            reencrypted = report.reencrypted,
+            migrated = report.migrated,
+            failed = report.failed,
+            "Sessions re-encrypted"
+        );
+        Ok(report)
+    }
+
+    async fn reencrypt_session(
+        &self,
+        encryption: &Encryption,
+        session_id: &str,
+    ) -> Result<Reencryption> {
+        let mut con = self.connection.clone();
+        let key = self.get_key(session_id);
+        let vault_key = self.vault_key(session_id);
+        let meta_key = self.meta_key(session_id);
+
+        let (messages, vault, meta, wrapped, retired, ttl): ReencryptionRead = time_redis(
+            "reencrypt_session",
+            redis::pipe()
+                .lrange(&key, 0, -1)
+                .hgetall(&vault_key)
+                .hgetall(&meta_key)
+                .get(self.dek_key(session_id))
+                .get(self.retired_dek_key(session_id))
+                .ttl(&meta_key)
+                .query_async(&mut con),
+        )
+        .await?;
+
+        if messages.is_empty() && meta.is_empty() {
+            time_redis(
+                "reencrypt_session",
+                con.zrem::<_, _, ()>(self.encryption_index_key(), session_id),
+            )
+            .await?;
+            return Ok(Reencryption::Expired);
+        }
+        // Se conserva el TTL que le queda a la sesión.
+        let ttl = if ttl > 0 { ttl } else { self.ttl as i64 };
+        let old_keys = self.open_data_keys(wrapped.clone(), retired).await?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        let (outcome, data_key) = match wrapped {
+            // Sesión en claro: la DEK se crea como en un turno (`SET NX`), así
+            // un turno concurrente usa la misma.
+            None => {
+                let data_key = self
+                    .session_data_key(session_id)
+                    .await?
+                    .context("encryption is disabled")?;
+                (Reencryption::Migrated, data_key)
+            }
+            // La DEK anterior queda como retirada, envuelta con la KEK activa.
+            Some(wrapped) => {
+                let wrapped = WrappedKey::decode(&wrapped)?;
+                let retired = encryption.rewrap(&wrapped).await?.unwrap_or(wrapped);
+                let (data_key, new_wrapped) = encryption.generate().await?;
+                pipe.cmd("SET")
+                    .arg(self.dek_key(session_id))
+                    .arg(new_wrapped.encode())
+                    .arg("EX")
+                    .arg(ttl)
+                    .ignore()
+                    .cmd("SET")
+                    .arg(self.retired_dek_key(session_id))
+                    .arg(retired.encode())
+                    .arg("EX")
+                    .arg(ttl)
+                    .ignore();
+                (Reencryption::Rotated, data_key)
+            }
+        };
+        let reseal = |value: &String| data_key.encrypt(&crypto::decode(value.clone(), &old_keys)?);
+
+        if !messages.is_empty() {
+            let mut sealed = messages.iter().map(reseal).collect::<Result<Vec<_>>>()?;
+            // Se reemplazan los mensajes leídos en lugar de toda la lista: los
+            // que agregue un turno mientras tanto se conservan al final.
+            for message in &messages {
+                pipe.lrem(&key, 1, message).ignore();
+            }
+            sealed.reverse();
+            pipe.lpush(&key, sealed).ignore();
+            self.queue_history_trim(&mut pipe, &key);
+            pipe.expire(&key, ttl).ignore();
+        }
+        if !vault.is_empty() {
+            let entries = vault
+                .iter()
+                .map(|(token, value)| Ok((token, reseal(value)?)))
+                .collect::<Result<Vec<_>>>()?;
+            pipe.hset_multiple(&vault_key, &entries).ignore();
+        }
+        let fields = SEALED_FIELDS
+            .iter()
+            .filter_map(|field| Some((*field, reseal(meta.get(*field)?))))
+            .map(|(field, value)| Ok((field, value?)))
+            .collect::<Result<Vec<_>>>()?;
+        if !fields.is_empty() {
+            pipe.hset_multiple(&meta_key, &fields).ignore();
+        }
+        pipe.expire(self.dek_key(session_id), ttl)
+            .ignore()
+            .zadd(self.encryption_index_key(), session_id, unix_now())
+            .ignore();
+
+        time_redis("reencrypt_session", pipe.query_async::<()>(&mut con)).await?;
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::encryption::{Encryption, LocalKeyring};
+    use crate::infra::redis::memory::MemoryConnection;
+    use crate::infra::redis::sessions::{
+        Intent, Sentiment, SessionFilter, SessionRef, SessionSummary,
+    };
+    use crate::infra::redis::{ChatMessage, Role};
+    use base64::Engine;
+    use std::sync::Arc;
+
+    fn keyring(keys: &[(&str, u8)]) -> Encryption {
+        let spec = keys
+            .iter()
+            .map(|(id, byte)| {
+                let key = base64::engine::general_purpose::STANDARD.encode([*byte; 32]);
+                format!("{}={}", id, key)
+            })
+            .collect::<Vec<_>>()
+            .join(";");
+        Encryption::new(Arc::new(LocalKeyring::parse(&spec).unwrap()))
+    }
+
+    async fn chat(provider: &RedisProvider, id: &str, content: &str) {
+        let session = SessionRef::new(id, None, "web", None);
+        let messages = vec![ChatMessage::new(Role::User, content)];
+        provider.add_messages(&session, messages).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_history_is_encrypted_at_rest() {
+        let (mut provider, memory) = RedisProvider::in_memory(3_600, 0);
+        provider.encryption = Some(keyring(&[("k1", 1)]));
+        chat(&provider, "s1", "Mi pedido #99 llegó roto").await;
+        chat(&provider, "s1", "¿Me devuelven el dinero?").await;
+
+        {
+            let store = memory.store();
+            for payload in &store.lists["TEST:s1"] {
+                let payload = String::from_utf8_lossy(payload);
+                assert!(payload.starts_with("enc:v1:"));
+                assert!(!payload.contains("pedido"));
+            }
+            assert_eq!(store.ttls["TEST:s1:dek"], 3_600);
+        }
+
+        let history = provider.get_history("s1").await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].content, "Mi pedido #99 llegó roto");
+
+        // Sin keyring, la sesión cifrada no se puede leer.
+        provider.encryption = None;
+        assert!(provider.get_history("s1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rotation_rewraps_data_keys() {
+        let (mut provider, _) = RedisProvider::in_memory(3_600, 0);
+        provider.encryption = Some(keyring(&[("k1", 1)]));
+        chat(&provider, "s1", "hola").await;
+        chat(&provider, "s2", "buenas").await;
+
+        provider.encryption = Some(keyring(&[("k1", 1), ("k2", 2)]));
+        let report = provider.rotate_data_keys().await.unwrap();
+        assert_eq!(report.active_key, "k2");
+        assert_eq!(report.rewrapped, 2);
+        assert_eq!(report.failed, 0);
+
+        // Con sólo la master key nueva, el historial sigue legible.
+        provider.encryption = Some(keyring(&[("k2", 2)]));
+        assert_eq!(provider.get_history("s1").await.unwrap()[0].content, "hola");
+        let report = provider.rotate_data_keys().await.unwrap();
+        assert_eq!(report.up_to_date, 2);
+    }
+
+    fn summary(text: &str) -> SessionSummary {
+        SessionSummary {
+            text: text.to_string(),
+            intent: Intent::Damage,
+            sentiment: Sentiment::Negative,
+            message_count: 1,
+            generated_at: unix_now(),
+        }
+    }
+
+    fn raw(memory: &MemoryConnection, key: &str, field: &str) -> String {
+        String::from_utf8_lossy(&memory.store().hashes[key][field]).into_owned()
+    }
+
+    #[tokio::test]
+    async fn test_title_and_summary_are_encrypted_at_rest() {
+        let (mut provider, memory) = RedisProvider::in_memory(3_600, 0);
+        provider.encryption = Some(keyring(&[("k1", 1)]));
+        chat(&provider, "s1", "Mi silla llegó rota").await;
+        provider
+            .save_summary("s1", "Silla rota", &summary("Silla rota en el envío"))
+            .await
+            .unwrap();
+
+        for field in ["title", "summary"] {
+            let value = raw(&memory, "TEST:s1:meta", field);
+            assert!(value.starts_with("enc:v1:"), "{}", value);
+        }
+        let meta = provider.get_session("s1").await.unwrap().unwrap();
+        assert_eq!(meta.title.as_deref(), Some("Silla rota"));
+        assert_eq!(meta.summary.unwrap().text, "Silla rota en el envío");
+
+        provider
+            .update_session("s1", "web", None, Some("Reclamo VIP".to_string()), None)
+            .await
+            .unwrap();
+        assert!(raw(&memory, "TEST:s1:meta", "title").starts_with("enc:v1:"));
+        let (sessions, _) = provider
+            .list_sessions("web", &SessionFilter::default(), 10, None)
+            .await
+            .unwrap();
+        assert_eq!(sessions[0].title.as_deref(), Some("Reclamo VIP"));
+    }
+
+    #[tokio::test]
+    async fn test_reencrypt_replaces_data_keys() {
+        let (mut provider, memory) = RedisProvider::in_memory(3_600, 0);
+        provider.encryption = Some(keyring(&[("k1", 1)]));
+        chat(&provider, "s1", "Escríbanme a ana@example.com").await;
+        provider
+            .save_summary("s1", "Contacto", &summary("Pidió contacto por correo"))
+            .await
+            .unwrap();
+        let (old_dek, old_message) = {
+            let store = memory.store();
+            (
+                String::from_utf8_lossy(&store.strings["TEST:s1:dek"]).into_owned(),
+                store.lists["TEST:s1"][0].clone(),
+            )
+        };
+
+        provider.encryption = Some(keyring(&[("k1", 1), ("k2", 2)]));
+        let report = provider
+            .reencrypt_sessions(&["web".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(
+            (report.reencrypted, report.migrated, report.failed),
+            (1, 0, 0)
+        );
+
+        let new_dek = {
+            let store = memory.store();
+            assert_ne!(store.lists["TEST:s1"][0], old_message);
+            assert_eq!(store.ttls["TEST:s1"], 3_600);
+            String::from_utf8_lossy(&store.strings["TEST:s1:dek"]).into_owned()
+        };
+        assert_ne!(new_dek, old_dek);
+        assert!(new_dek.starts_with("k2:"));
+        let retired =
+            String::from_utf8_lossy(&memory.store().strings["TEST:s1:dek:retired"]).into_owned();
+        assert!(retired.starts_with("k2:"));
+
+        // Un turno en curso cifró su mensaje con la DEK anterior.
+        let encryption = provider.encryption.clone().unwrap();
+        let old_key = encryption
+            .open(&WrappedKey::decode(&old_dek).unwrap())
+            .await
+            .unwrap();
+        let late = ChatMessage::new(Role::Assistant, "Te escribimos hoy");
+        memory.store().lists.get_mut("TEST:s1").unwrap().push(
+            old_key
+                .encrypt(&serde_json::to_string(&late).unwrap())
+                .unwrap()
+                .into_bytes(),
+        );
+        let history = provider.get_history("s1").await.unwrap();
+        assert_eq!(history[0].content, "Escríbanme a ana@example.com");
+        assert_eq!(history[1].content, "Te escribimos hoy");
+
+        // La rotación siguiente lo re-cifra: ya no hace falta la primera DEK.
+        provider
+            .reencrypt_sessions(&["web".to_string()])
+            .await
+            .unwrap();
+        provider.encryption = Some(keyring(&[("k2", 2)]));
+        assert_eq!(provider.get_history("s1").await.unwrap().len(), 2);
+        let meta = provider.get_session("s1").await.unwrap().unwrap();
+        assert_eq!(meta.summary.unwrap().text, "Pidió contacto por correo");
+    }
+
+    #[tokio::test]
+    async fn test_reencrypt_migrates_plaintext_sessions() {
+        let (mut provider, memory) = RedisProvider::in_memory(3_600, 0);
+        chat(&provider, "s1", "Escríbanme a ana@example.com").await;
+        provider
+            .save_summary("s1", "Contacto", &summary("Pidió contacto por correo"))
+            .await
+            .unwrap();
+        assert!(!raw(&memory, "TEST:s1:meta", "title").starts_with("enc:v1:"));
+
+        provider.encryption = Some(keyring(&[("k1", 1)]));
+        let report = provider
+            .reencrypt_sessions(&["web".to_string()])
+            .await
+            .unwrap();
+        assert_eq!((report.reencrypted, report.migrated), (0, 1));
+
+        {
+            let store = memory.store();
+            let values = store.lists["TEST:s1"]
+                .iter()
+                .chain(store.hashes["TEST:s1:pii"].values())
+                .chain(["title", "summary"].map(|f| &store.hashes["TEST:s1:meta"][f]));
+            for value in values {
+                let value = String::from_utf8_lossy(value);
+                assert!(value.starts_with("enc:v1:"), "{}", value);
+            }
+            assert!(store.sorted_sets["TEST:encryption:sessions"].contains_key("s1"));
+        }
+        let history = provider.get_history("s1").await.unwrap();
+        assert_eq!(history[0].content, "Escríbanme a ana@example.com");
+        let meta = provider.get_session("s1").await.unwrap().unwrap();
+        assert_eq!(meta.title.as_deref(), Some("Contacto"));
+
+        // Una sesión nueva y una ya expirada no cuentan.
+        chat(&provider, "s2", "hola").await;
+        memory.store().lists.remove("TEST:s2");
+        memory.store().hashes.remove("TEST:s2:meta");
+        let report = provider
+            .reencrypt_sessions(&["web".to_string()])
+            .await
+            .unwrap();
+        assert_eq!((report.reencrypted, report.migrated), (1, 0));
+        assert!(!memory.store().sorted_sets["TEST:encryption:sessions"].contains_key("s2"));
+    }
+}
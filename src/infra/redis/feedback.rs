@@ -0,0 +1,221 @@
+//! Feedback de usuarios sobre respuestas del asistente.
+//!
+//! ## Claves
+//! - `{base}:{session_id}:feedback` — hash `message_id → Feedback (JSON)`, con el TTL de la sesión.
+//! - `{base}:feedback:index` — sorted set `session_id` por fecha del último feedback,
+//!   usado por la exportación para encontrar sesiones con feedback. Cada escritura
+//!   descarta las entradas más antiguas que el TTL de la sesión.
+
+use super::{ChatMessage, RedisProvider};
+use crate::infra::errors::DomainResult;
+use crate::infra::metrics::time_redis;
+use crate::infra::redaction;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedbackCategory {
+    Accuracy,
+    Helpfulness,
+    Tone,
+    Safety,
+    Other,
+}
+
+/// Valoración de un mensaje del asistente.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Feedback {
+    pub session_id: String,
+    pub message_id: String,
+    pub rating: Rating,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<FeedbackCategory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Variante (A/B) que generó el mensaje valorado.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    pub created_at: u64,
+}
+
+/// Feedback junto con la conversación hasta el mensaje valorado (inclusive).
+#[derive(Serialize, Debug, Clone)]
+pub struct FeedbackExport {
+    pub feedback: Feedback,
+    pub transcript: Vec<ChatMessage>,
+}
+
+impl RedisProvider {
+    pub(super) fn feedback_key(&self, session_id: &str) -> String {
+        format!("{}:feedback", self.get_key(session_id))
+    }
+
+    pub(super) fn feedback_index_key(&self) -> String {
+        format!("{}:feedback:index", self.base_path)
+    }
+
+    /// Guarda (o reemplaza) el feedback de un mensaje.
+    pub async fn save_feedback(&self, feedback: &Feedback) -> DomainResult<()> {
+        let mut con = self.connection.clone();
+        let key = self.feedback_key(&feedback.session_id);
+        let index = self.feedback_index_key();
+        let payload = serde_json::to_string(feedback)?;
+
+        time_redis(
+            "save_feedback",
+            redis::pipe()
+                .atomic()
+                .hset(&key, &feedback.message_id, payload)
+                .ignore()
+                .expire(&key, self.ttl as i64)
+                .ignore()
+                .zadd(&index, &feedback.session_id, feedback.created_at)
+                .ignore()
+                // Con el hash ya expirado, la entrada no tiene nada que exportar.
+                .zrembyscore(&index, "-inf", feedback.created_at.saturating_sub(self.ttl))
+                .ignore()
+                .query_async::<()>(&mut con),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_session_feedback(&self, session_id: &str) -> DomainResult<Vec<Feedback>> {
+        let mut con = self.connection.clone();
+        let entries: Vec<String> = time_redis(
+            "get_session_feedback",
+            con.hvals(self.feedback_key(session_id)),
+        )
+        .await?;
+
+        entries
+            .iter()
+            .map(|json| serde_json::from_str(json).map_err(Into::into))
+            .collect()
+    }
+
+    /// Exporta el feedback recibido entre `since` y `until` (segundos epoch)
+    /// con la conversación que lo rodea, ordenado por fecha. Si `PII_REDACT_EXPORTS`
+    /// está activo, la PII de la conversación y los comentarios se enmascara.
+    pub async fn export_feedback(
+        &self,
+        since: u64,
+        until: u64,
+    ) -> DomainResult<Vec<FeedbackExport>> {
+        let mut con = self.connection.clone();
+        let sessions: Vec<String> = time_redis(
+            "export_feedback",
+            con.zrangebyscore(self.feedback_index_key(), since, until),
+        )
+        .await?;
+
+        let redactor = redaction::get();
+        let mut exports = Vec::new();
+        for session_id in sessions {
+            let feedback = self.get_session_feedback(&session_id).await?;
+            if feedback.is_empty() {
+                continue;
+            }
+
+            let history = if redactor.redact_exports {
+                let mut history = self.get_stored_history(&session_id).await?;
+                for message in &mut history {
+                    message.content = redactor.mask(&message.content).into_owned();
+                }
+                history
+            } else {
+                self.get_history(&session_id).await?
+            };
+
+            for mut item in feedback {
+                if item.created_at < since || item.created_at > until {
+                    continue;
+                }
+                if redactor.redact_exports {
+                    item.comment = item.comment.map(|c| redactor.mask(&c).into_owned());
+                }
+                let transcript = transcript_until(&history, &item.message_id);
+                exports.push(FeedbackExport {
+                    feedback: item,
+                    transcript,
+                });
+            }
+        }
+
+        exports.sort_by_key(|e| e.feedback.created_at);
+        Ok(exports)
+    }
+}
+
+/// Conversación desde el inicio hasta `message_id` (inclusive).
+fn transcript_until(history: &[ChatMessage], message_id: &str) -> Vec<ChatMessage> {
+    match history.iter().position(|m| m.id == message_id) {
+        Some(index) => history[..=index].to_vec(),
+        None => history.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::redis::Role;
+
+    #[test]
+    fn test_transcript_until_message() {
+        let history = vec![
+            ChatMessage::new(Role::User, "hola"),
+            ChatMessage::new(Role::Assistant, "¿en qué te ayudo?"),
+            ChatMessage::new(Role::User, "nada"),
+        ];
+
+        let transcript = transcript_until(&history, &history[1].id);
+        assert_eq!(transcript.len(), 2);
+        assert_eq!(transcript[1].role, Role::Assistant);
+    }
+
+    #[test]
+    fn test_feedback_serialization() {
+        let json = r#"{"session_id":"s","message_id":"m","rating":"down","category":"accuracy","created_at":1}"#;
+        let feedback: Feedback = serde_json::from_str(json).unwrap();
+        assert_eq!(feedback.rating, Rating::Down);
+        assert_eq!(feedback.category, Some(FeedbackCategory::Accuracy));
+        assert!(feedback.comment.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_feedback_index_drops_expired_sessions() {
+        let (provider, memory) = RedisProvider::in_memory(3_600, 0);
+        let feedback = |session_id: &str, created_at: u64| Feedback {
+            session_id: session_id.to_string(),
+            message_id: "m1".to_string(),
+            rating: Rating::Up,
+            category: None,
+            comment: None,
+            variant: None,
+            created_at,
+        };
+
+        provider
+            .save_feedback(&feedback("vieja", 1_000))
+            .await
+            .unwrap();
+        provider
+            .save_feedback(&feedback("reciente", 1_000 + 7_200))
+            .await
+            .unwrap();
+
+        let store = memory.store();
+        let index = &store.sorted_sets["TEST:feedback:index"];
+        assert!(index.contains_key("reciente"));
+        assert!(!index.contains_key("vieja"));
+    }
+}
@@ -0,0 +1,510 @@
+//! Redis en memoria para los tests de `RedisProvider`: implementa los comandos
+//! que usa el provider y ejecuta los pipelines `MULTI`/`EXEC` de una vez,
+//! respondiendo como el servidor real. Registra los round-trips para poder
+//! verificar que las escrituras son atómicas. Un comando no soportado
+//! responde con un error de Redis (y hace fallar el pipeline que lo incluya).
+
+use super::connection::RedisConnection;
+use super::RedisProvider;
+use redis::aio::ConnectionLike;
+use redis::{Arg, Cmd, ErrorKind, Pipeline, RedisError, RedisFuture, RedisResult, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+pub struct MemoryStore {
+    pub strings: HashMap<String, Vec<u8>>,
+    pub lists: HashMap<String, Vec<Vec<u8>>>,
+    pub hashes: HashMap<String, BTreeMap<String, Vec<u8>>>,
+    pub sorted_sets: HashMap<String, HashMap<String, f64>>,
+    /// Streams: entradas `(id, campos)` en orden de inserción.
+    pub streams: HashMap<String, Vec<StreamEntry>>,
+    /// TTL (segundos) asignado con `EXPIRE`; no expira realmente.
+    pub ttls: HashMap<String, i64>,
+    /// Round-trips recibidos: comandos sueltos más pipelines.
+    pub round_trips: usize,
+    pub transactions: usize,
+}
+
+/// Id de una entrada de stream: `milisegundos-secuencia`.
+pub type StreamId = (u64, u64);
+pub type StreamEntry = (StreamId, Vec<(String, Vec<u8>)>);
+
+#[derive(Clone, Default)]
+pub struct MemoryConnection {
+    store: Arc<Mutex<MemoryStore>>,
+}
+
+impl MemoryConnection {
+    pub fn store(&self) -> std::sync::MutexGuard<'_, MemoryStore> {
+        self.store.lock().unwrap()
+    }
+}
+
+impl RedisProvider {
+    /// Provider sobre un Redis en memoria vacío, con prefijo `TEST`.
+    pub fn in_memory(ttl: u64, max_messages: u64) -> (Self, MemoryConnection) {
+        let memory = MemoryConnection::default();
+        let provider = Self {
+            connection: RedisConnection::Memory(memory.clone()),
+            base_path: "TEST".to_string(),
+            ttl,
+            max_messages,
+            encryption: None,
+        };
+        (provider, memory)
+    }
+}
+
+impl MemoryStore {
+    fn exists(&self, key: &str) -> bool {
+        self.strings.contains_key(key)
+            || self.lists.contains_key(key)
+            || self.hashes.contains_key(key)
+            || self.sorted_sets.contains_key(key)
+            || self.streams.contains_key(key)
+    }
+
+    fn execute(&mut self, cmd: &Cmd) -> RedisResult<Value> {
+        let args: Vec<String> = cmd
+            .args_iter()
+            .map(|arg| match arg {
+                Arg::Simple(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+                Arg::Cursor => "0".to_string(),
+            })
+            .collect();
+        let int = |value: &str| value.parse::<i64>().unwrap_or_default();
+
+        let value = match args[0].to_uppercase().as_str() {
+            "PING" => Value::SimpleString("PONG".to_string()),
+            "GET" => self
+                .strings
+                .get(&args[1])
+                .map_or(Value::Nil, |v| Value::BulkString(v.clone())),
+            "MGET" => Value::Array(
+                args[1..]
+                    .iter()
+                    .map(|key| {
+                        self.strings
+                            .get(key)
+                            .map_or(Value::Nil, |v| Value::BulkString(v.clone()))
+                    })
+                    .collect(),
+            ),
+            // Opciones soportadas: `NX`, `XX`, `EX segundos` y `KEEPTTL`.
+            "SET" => {
+                let options: Vec<String> = args[3..].iter().map(|o| o.to_uppercase()).collect();
+                let exists = self.strings.contains_key(&args[1]);
+                let has = |option: &str| options.iter().any(|o| o == option);
+                if (has("NX") && exists) || (has("XX") && !exists) {
+                    return Ok(Value::Nil);
+                }
+                self.strings
+                    .insert(args[1].clone(), args[2].as_bytes().to_vec());
+                if let Some(i) = options.iter().position(|o| o == "EX") {
+                    self.ttls.insert(args[1].clone(), int(&args[4 + i]));
+                } else if !has("KEEPTTL") {
+                    self.ttls.remove(&args[1]);
+                }
+                Value::Okay
+            }
+            "INCRBYFLOAT" => {
+                let current = self
+                    .strings
+                    .get(&args[1])
+                    .map_or(0.0, |v| String::from_utf8_lossy(v).parse().unwrap_or(0.0));
+                let value = current + args[2].parse::<f64>().unwrap_or(0.0);
+                self.strings
+                    .insert(args[1].clone(), value.to_string().into_bytes());
+                Value::BulkString(value.to_string().into_bytes())
+            }
+            "RPUSH" => {
+                let list = self.lists.entry(args[1].clone()).or_default();
+                list.extend(args[2..].iter().map(|v| v.as_bytes().to_vec()));
+                Value::Int(list.len() as i64)
+            }
+            "LRANGE" => {
+                let list = self.lists.get(&args[1]).cloned().unwrap_or_default();
+                let (start, stop) = range(list.len(), int(&args[2]), int(&args[3]));
+                Value::Array(
+                    list.get(start..stop)
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|v| Value::BulkString(v.clone()))
+                        .collect(),
+                )
+            }
+            "LPUSH" => {
+                let list = self.lists.entry(args[1].clone()).or_default();
+                for value in &args[2..] {
+                    list.insert(0, value.as_bytes().to_vec());
+                }
+                Value::Int(list.len() as i64)
+            }
+            // Sólo `count` positivo: quita desde el inicio.
+            "LREM" => {
+                let Some(list) = self.lists.get_mut(&args[1]) else {
+                    return Ok(Value::Int(0));
+                };
+                let mut removed = 0;
+                while removed < int(&args[2]) {
+                    let Some(i) = list.iter().position(|v| *v == args[3].as_bytes()) else {
+                        break;
+                    };
+                    list.remove(i);
+                    removed += 1;
+                }
+                if list.is_empty() {
+                    self.lists.remove(&args[1]);
+                    self.ttls.remove(&args[1]);
+                }
+                Value::Int(removed)
+            }
+            "TTL" => {
+                let key = &args[1];
+                let exists = self.exists(key);
+                match self.ttls.get(key) {
+                    Some(ttl) if exists => Value::Int(*ttl),
+                    _ if exists => Value::Int(-1),
+                    _ => Value::Int(-2),
+                }
+            }
+            "LTRIM" => {
+                if let Some(list) = self.lists.get_mut(&args[1]) {
+                    let (start, stop) = range(list.len(), int(&args[2]), int(&args[3]));
+                    *list = list.get(start..stop).unwrap_or_default().to_vec();
+                }
+                Value::Okay
+            }
+            "EXPIRE" => {
+                let key = &args[1];
+                let exists = self.exists(key);
+                if exists {
+                    self.ttls.insert(key.clone(), int(&args[2]));
+                }
+                Value::Int(i64::from(exists))
+            }
+            "HSET" | "HMSET" => {
+                let hash = self.hashes.entry(args[1].clone()).or_default();
+                let mut added = 0;
+                for pair in args[2..].chunks(2) {
+                    if hash
+                        .insert(pair[0].clone(), pair[1].as_bytes().to_vec())
+                        .is_none()
+                    {
+                        added += 1;
+                    }
+                }
+                if args[0].eq_ignore_ascii_case("HMSET") {
+                    Value::Okay
+                } else {
+                    Value::Int(added)
+                }
+            }
+            "HSETNX" => {
+                let hash = self.hashes.entry(args[1].clone()).or_default();
+                let added = !hash.contains_key(&args[2]);
+                if added {
+                    hash.insert(args[2].clone(), args[3].as_bytes().to_vec());
+                }
+                Value::Int(i64::from(added))
+            }
+            "HINCRBY" => {
+                let hash = self.hashes.entry(args[1].clone()).or_default();
+                let current = hash
+                    .get(&args[2])
+                    .map_or(0, |v| int(&String::from_utf8_lossy(v)));
+                let value = current + int(&args[3]);
+                hash.insert(args[2].clone(), value.to_string().into_bytes());
+                Value::Int(value)
+            }
+            "ZADD" => {
+                let set = self.sorted_sets.entry(args[1].clone()).or_default();
+                let mut added = 0;
+                for pair in args[2..].chunks(2) {
+                    if set.insert(pair[1].clone(), score(&pair[0])).is_none() {
+                        added += 1;
+                    }
+                }
+                Value::Int(added)
+            }
+            "ZREM" => {
+                let set = self.sorted_sets.entry(args[1].clone()).or_default();
+                let removed = args[2..]
+                    .iter()
+                    .filter(|m| set.remove(*m).is_some())
+                    .count();
+                Value::Int(removed as i64)
+            }
+            "ZREMRANGEBYSCORE" => {
+                let set = self.sorted_sets.entry(args[1].clone()).or_default();
+                let (min, max) = (score(&args[2]), score(&args[3]));
+                let before = set.len();
+                set.retain(|_, s| *s < min || *s > max);
+                Value::Int((before - set.len()) as i64)
+            }
+            "ZRANGE" => {
+                let mut entries: Vec<(&String, &f64)> = self
+                    .sorted_sets
+                    .get(&args[1])
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                entries.sort_by(|a, b| a.1.total_cmp(b.1).then_with(|| a.0.cmp(b.0)));
+                let (start, stop) = range(entries.len(), int(&args[2]), int(&args[3]));
+                Value::Array(
+                    entries[start..stop]
+                        .iter()
+                        .map(|(member, _)| Value::BulkString(member.as_bytes().to_vec()))
+                        .collect(),
+                )
+            }
+            // Sólo la forma `key min max`.
+            "ZRANGEBYSCORE" => {
+                let (min, max) = (score(&args[2]), score(&args[3]));
+                let mut entries: Vec<(&String, &f64)> = self
+                    .sorted_sets
+                    .get(&args[1])
+                    .into_iter()
+                    .flatten()
+                    .filter(|(_, s)| **s >= min && **s <= max)
+                    .collect();
+                entries.sort_by(|a, b| a.1.total_cmp(b.1).then_with(|| a.0.cmp(b.0)));
+                Value::Array(
+                    entries
+                        .into_iter()
+                        .map(|(member, _)| Value::BulkString(member.as_bytes().to_vec()))
+                        .collect(),
+                )
+            }
+            // Sólo la forma `key max min WITHSCORES LIMIT offset count`.
+            "ZREVRANGEBYSCORE" => {
+                let (max, min) = (score(&args[2]), score(&args[3]));
+                let mut entries: Vec<(&String, &f64)> = self
+                    .sorted_sets
+                    .get(&args[1])
+                    .into_iter()
+                    .flatten()
+                    .filter(|(_, s)| **s >= min && **s <= max)
+                    .collect();
+                entries.sort_by(|a, b| b.1.total_cmp(a.1).then_with(|| b.0.cmp(a.0)));
+                Value::Array(
+                    entries
+                        .into_iter()
+                        .skip(int(&args[6]) as usize)
+                        .take(int(&args[7]) as usize)
+                        .flat_map(|(member, score)| {
+                            [
+                                Value::BulkString(member.as_bytes().to_vec()),
+                                Value::BulkString(score.to_string().into_bytes()),
+                            ]
+                        })
+                        .collect(),
+                )
+            }
+            "HGETALL" => Value::Array(
+                self.hashes
+                    .get(&args[1])
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|(field, value)| {
+                        [
+                            Value::BulkString(field.as_bytes().to_vec()),
+                            Value::BulkString(value.clone()),
+                        ]
+                    })
+                    .collect(),
+            ),
+            "HDEL" => {
+                let hash = self.hashes.entry(args[1].clone()).or_default();
+                let removed = args[2..]
+                    .iter()
+                    .filter(|f| hash.remove(*f).is_some())
+                    .count();
+                Value::Int(removed as i64)
+            }
+            "HLEN" => Value::Int(self.hashes.get(&args[1]).map_or(0, |h| h.len() as i64)),
+            "HVALS" => Value::Array(
+                self.hashes
+                    .get(&args[1])
+                    .into_iter()
+                    .flatten()
+                    .map(|(_, value)| Value::BulkString(value.clone()))
+                    .collect(),
+            ),
+            // Sólo ids autogenerados (`*`).
+            "XADD" => {
+                let millis = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default();
+                let stream = self.streams.entry(args[1].clone()).or_default();
+                let id = match stream.last() {
+                    Some(&((last, seq), _)) if last >= millis => (last, seq + 1),
+                    _ => (millis, 0),
+                };
+                let fields = args[3..]
+                    .chunks(2)
+                    .map(|pair| (pair[0].clone(), pair[1].as_bytes().to_vec()))
+                    .collect();
+                stream.push((id, fields));
+                Value::BulkString(format!("{}-{}", id.0, id.1).into_bytes())
+            }
+            // `key end start [COUNT n]`; admite `+`, `-` y `(` (exclusivo).
+            "XREVRANGE" => {
+                let end = stream_bound(&args[2], u64::MAX);
+                let start = stream_bound(&args[3], 0);
+                let count = args.get(5).map_or(usize::MAX, |count| int(count) as usize);
+                Value::Array(
+                    self.streams
+                        .get(&args[1])
+                        .into_iter()
+                        .flatten()
+                        .rev()
+                        .filter(|(id, _)| start.contains(id) && end.contains(id))
+                        .take(count)
+                        .map(|((millis, seq), fields)| {
+                            Value::Array(vec![
+                                Value::BulkString(format!("{}-{}", millis, seq).into_bytes()),
+                                Value::Array(
+                                    fields
+                                        .iter()
+                                        .flat_map(|(field, value)| {
+                                            [
+                                                Value::BulkString(field.as_bytes().to_vec()),
+                                                Value::BulkString(value.clone()),
+                                            ]
+                                        })
+                                        .collect(),
+                                ),
+                            ])
+                        })
+                        .collect(),
+                )
+            }
+            "DEL" => {
+                let mut removed = 0;
+                for key in &args[1..] {
+                    let existed = self.strings.remove(key).is_some()
+                        | self.lists.remove(key).is_some()
+                        | self.hashes.remove(key).is_some()
+                        | self.sorted_sets.remove(key).is_some()
+                        | self.streams.remove(key).is_some();
+                    self.ttls.remove(key);
+                    removed += i64::from(existed);
+                }
+                Value::Int(removed)
+            }
+            other => {
+                return Err(RedisError::from((
+                    ErrorKind::ResponseError,
+                    "comando no soportado en memoria",
+                    other.to_string(),
+                )))
+            }
+        };
+        Ok(value)
+    }
+}
+
+/// Cota de `XREVRANGE`. `default_seq` completa los ids sin secuencia: `0`
+/// para el inicio del rango y `u64::MAX` para el final.
+fn stream_bound(value: &str, default_seq: u64) -> StreamBound {
+    let (exclusive, value) = match value.strip_prefix('(') {
+        Some(value) => (true, value),
+        None => (false, value),
+    };
+    let id = match value {
+        "-" => (0, 0),
+        "+" => (u64::MAX, u64::MAX),
+        _ => match value.split_once('-') {
+            Some((millis, seq)) => (millis.parse().unwrap_or(0), seq.parse().unwrap_or(0)),
+            None => (value.parse().unwrap_or(0), default_seq),
+        },
+    };
+    StreamBound {
+        id,
+        exclusive,
+        upper: default_seq == u64::MAX,
+    }
+}
+
+struct StreamBound {
+    id: StreamId,
+    exclusive: bool,
+    upper: bool,
+}
+
+impl StreamBound {
+    fn contains(&self, id: &StreamId) -> bool {
+        match (self.upper, self.exclusive) {
+            (true, false) => *id <= self.id,
+            (true, true) => *id < self.id,
+            (false, false) => *id >= self.id,
+            (false, true) => *id > self.id,
+        }
+    }
+}
+
+/// Score de un sorted set (admite `-inf` y `+inf`).
+fn score(value: &str) -> f64 {
+    value.parse().unwrap_or_default()
+}
+
+/// Índices `[start, stop)` de un rango inclusivo de Redis (admite negativos).
+fn range(len: usize, start: i64, stop: i64) -> (usize, usize) {
+    let len = len as i64;
+    let resolve = |i: i64| if i < 0 { (len + i).max(0) } else { i.min(len) };
+    let start = resolve(start);
+    let stop = (resolve(stop) + 1).min(len);
+    (start as usize, stop.max(start) as usize)
+}
+
+impl ConnectionLike for MemoryConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        let mut store = self.store();
+        store.round_trips += 1;
+        let value = store.execute(cmd);
+        Box::pin(async move { value })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        pipeline: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        let mut store = self.store();
+        store.round_trips += 1;
+        let results = match pipeline
+            .cmd_iter()
+            .map(|cmd| store.execute(cmd))
+            .collect::<RedisResult<Vec<Value>>>()
+        {
+            Ok(results) => results,
+            Err(e) => return Box::pin(async move { Err(e) }),
+        };
+
+        // Un `MULTI`/`EXEC` responde OK, un QUEUED por comando y el array de resultados.
+        let responses = if pipeline.is_transaction() {
+            store.transactions += 1;
+            let mut responses = vec![Value::Okay];
+            responses.extend(
+                results
+                    .iter()
+                    .map(|_| Value::SimpleString("QUEUED".to_string())),
+            );
+            responses.push(Value::Array(results));
+            responses
+        } else {
+            results
+        };
+
+        let values = responses.into_iter().skip(offset).take(count).collect();
+        Box::pin(async move { Ok(values) })
+    }
+
+    fn get_db(&self) -> i64 {
+        0
+    }
+}
@@ -0,0 +1,403 @@
+pub mod audit;
+pub mod catalog;
+pub mod connection;
+pub mod encryption;
+pub mod feedback;
+#[cfg(test)]
+mod memory;
+pub mod sessions;
+pub mod subjects;
+pub mod usage;
+
+use crate::config::RedisTopology;
+use crate::infra::encryption::{self as crypto, DataKey, Encryption};
+use crate::infra::metrics::time_redis;
+use crate::infra::redaction::{self, Vault};
+use anyhow::Result;
+use connection::RedisConnection;
+use serde::{Deserialize, Serialize};
+use sessions::{SessionMeta, SessionRef};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Role {
+    User,
+    System,
+    Assistant,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChatMessage {
+    /// Identificador del mensaje. Vacío en mensajes guardados antes de existir este campo.
+    #[serde(default)]
+    pub id: String,
+    pub role: Role,
+    pub content: String,
+    /// Variante de agentes (A/B) que atendió el turno.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+}
+
+impl ChatMessage {
+    pub fn new<C: Into<String>>(role: Role, content: C) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            role,
+            content: content.into(),
+            variant: None,
+        }
+    }
+
+    pub fn with_variant<V: Into<String>>(mut self, variant: V) -> Self {
+        self.variant = Some(variant.into());
+        self
+    }
+}
+
+/// Segundos desde epoch (UTC).
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Lista de mensajes, vault de PII, metadatos y DEKs envueltas (vigente y
+/// retirada) de una sesión.
+type ConversationRead = (
+    Vec<String>,
+    HashMap<String, String>,
+    HashMap<String, String>,
+    Option<String>,
+    Option<String>,
+);
+
+#[derive(Clone)]
+pub struct RedisProvider {
+    connection: RedisConnection,
+    base_path: String,
+    ttl: u64,
+    max_messages: u64,
+    /// Cifrado del historial en reposo (`None` sin `ENCRYPTION_KEYRING`).
+    encryption: Option<Encryption>,
+}
+
+impl RedisProvider {
+    pub async fn new() -> Result<Self> {
+        let config = &crate::config::get().store;
+
+        // Reconecta solo si Redis se reinicia; las operaciones que superan
+        // `REDIS_TIMEOUT_MS` fallan con `RedisKind::Timeout`.
+        let connection = RedisConnection::connect(config).await?;
+
+        // En Cluster, el hash tag deja todas las claves en el mismo slot. Es
+        // intencional: las transacciones de un turno, del feedback y del
+        // borrado de un titular escriben a la vez claves de la sesión e índices
+        // globales, y `MGET` lee DEKs de varias sesiones. Un tag por sesión
+        // haría fallar esas operaciones con `CROSSSLOT` (ver `connection.rs`).
+        let base_path = match config.topology {
+            RedisTopology::Cluster => format!("{{{}}}", config.base_path),
+            _ => config.base_path.clone(),
+        };
+
+        Ok(Self {
+            connection,
+            base_path,
+            ttl: config.session_ttl,
+            max_messages: config.history_max_messages,
+            encryption: Encryption::from_config()?,
+        })
+    }
+
+    /// `PING` al servidor, usado por el readiness check.
+    pub async fn ping(&self) -> Result<()> {
+        let mut con = self.connection.clone();
+        time_redis("ping", redis::cmd("PING").query_async::<()>(&mut con)).await?;
+        Ok(())
+    }
+
+    fn get_key(&self, session_id: &str) -> String {
+        format!("{}:{}", self.base_path, session_id)
+    }
+
+    /// Vault de PII de la sesión: `token → valor original`.
+    fn vault_key(&self, session_id: &str) -> String {
+        format!("{}:pii", self.get_key(session_id))
+    }
+
+    /// Historial con los valores reales (tokens de PII restaurados).
+    /// Es lo que debe recibir el orquestador.
+    pub async fn get_history(&self, session_id: &str) -> Result<Vec<ChatMessage>> {
+        Ok(self.get_conversation(session_id).await?.0)
+    }
+
+    /// Historial restaurado junto con los metadatos de la sesión, en una sola
+    /// lectura. Los metadatos son `None` si la sesión es nueva.
+    pub async fn get_conversation(
+        &self,
+        session_id: &str,
+    ) -> Result<(Vec<ChatMessage>, Option<SessionMeta>)> {
+        let mut con = self.connection.clone();
+
+        let (messages, vault, mut meta, wrapped_key, retired_key): ConversationRead = time_redis(
+            "get_history",
+            redis::pipe()
+                .lrange(self.get_key(session_id), 0, -1)
+                .hgetall(self.vault_key(session_id))
+                .hgetall(self.meta_key(session_id))
+                .get(self.dek_key(session_id))
+                .get(self.retired_dek_key(session_id))
+                .query_async(&mut con),
+        )
+        .await?;
+
+        let data_keys = self.open_data_keys(wrapped_key, retired_key).await?;
+        let vault = vault
+            .into_iter()
+            .map(|(token, value)| Ok((token, crypto::decode(value, &data_keys)?)))
+            .collect::<Result<HashMap<_, _>>>()?;
+        let vault = Vault::from_entries(vault);
+        let mut history = parse_messages(messages, &data_keys)?;
+        sessions::open_sealed_fields(&mut meta, &data_keys)?;
+        if !vault.entries().is_empty() {
+            for message in &mut history {
+                message.content = vault.detokenize(&message.content);
+            }
+        }
+
+        Ok((history, SessionMeta::from_fields(session_id, meta)))
+    }
+
+    /// Historial tal como está almacenado: con tokens de PII si
+    /// `PII_REDACT_HISTORY` estaba activo al guardarlo.
+    pub async fn get_stored_history(&self, session_id: &str) -> Result<Vec<ChatMessage>> {
+        let mut con = self.connection.clone();
+        let (messages, wrapped_key, retired_key): (Vec<String>, Option<String>, Option<String>) =
+            time_redis(
+                "get_stored_history",
+                redis::pipe()
+                    .lrange(self.get_key(session_id), 0, -1)
+                    .get(self.dek_key(session_id))
+                    .get(self.retired_dek_key(session_id))
+                    .query_async(&mut con),
+            )
+            .await?;
+
+        let data_keys = self.open_data_keys(wrapped_key, retired_key).await?;
+        parse_messages(messages, &data_keys)
+    }
+
+    /// Agrega un turno al historial y actualiza los metadatos e índices de la
+    /// sesión (ver `sessions.rs`).
+    pub async fn add_messages(
+        &self,
+        session: &SessionRef,
+        messages: Vec<ChatMessage>,
+    ) -> Result<()> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        let session_id = session.id.as_str();
+        let variant = messages.last().and_then(|m| m.variant.clone());
+        let count = messages.len();
+
+        let mut con = self.connection.clone();
+        let key = self.get_key(session_id);
+
+        let redactor = redaction::get();
+        let mut vault = Vault::default();
+        let messages: Vec<ChatMessage> = if redactor.redact_history {
+            messages
+                .into_iter()
+                .map(|mut msg| {
+                    msg.content = redactor.tokenize(session_id, &msg.content, &mut vault);
+                    msg
+                })
+                .collect()
+        } else {
+            messages
+        };
+
+        let data_key = self.session_data_key(session_id).await?;
+        let serialized: Vec<String> = messages
+            .iter()
+            .map(|message| crypto::encode(serde_json::to_string(message)?, data_key.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Una sola transacción: un corte a mitad de camino no deja la lista sin
+        // TTL, mensajes con tokens cuyo valor no llegó al vault ni índices
+        // desalineados con el historial.
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        let vault_key = self.vault_key(session_id);
+        if !vault.entries().is_empty() {
+            let entries: Vec<(&String, String)> = vault
+                .entries()
+                .iter()
+                .map(|(token, value)| {
+                    Ok((token, crypto::encode(value.clone(), data_key.as_ref())?))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            pipe.hset_multiple(&vault_key, &entries).ignore();
+        }
+        // Aunque el turno no traiga PII: el vault vive lo que el historial que
+        // lo referencia.
+        pipe.expire(&vault_key, self.ttl as i64).ignore();
+        pipe.rpush(&key, serialized).ignore();
+        self.queue_history_trim(&mut pipe, &key);
+        pipe.expire(&key, self.ttl as i64).ignore();
+        if data_key.is_some() {
+            self.queue_data_key_refresh(&mut pipe, session_id);
+        }
+        self.queue_session_update(&mut pipe, session, variant.as_deref(), count);
+
+        time_redis("add_messages", pipe.query_async::<()>(&mut con)).await?;
+        Ok(())
+    }
+
+    /// Agrega al pipeline el recorte del historial a `HISTORY_MAX_MESSAGES`.
+    fn queue_history_trim(&self, pipe: &mut redis::Pipeline, key: &str) {
+        if self.max_messages > 0 {
+            // Se recorta por turnos completos (pregunta y respuesta): el
+            // historial nunca empieza con una respuesta sin su pregunta.
+            let keep = (self.max_messages - self.max_messages % 2).max(2);
+            pipe.ltrim(key, -(keep as isize), -1).ignore();
+        }
+    }
+}
+
+fn parse_messages(messages: Vec<String>, data_keys: &[DataKey]) -> Result<Vec<ChatMessage>> {
+    messages
+        .into_iter()
+        .map(|payload| {
+            let json = crypto::decode(payload, data_keys)?;
+            serde_json::from_str(&json).map_err(Into::into)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_memory(max_messages: u64) -> (RedisProvider, memory::MemoryConnection) {
+        RedisProvider::in_memory(3_600, max_messages)
+    }
+
+    fn session(id: &str) -> SessionRef {
+        SessionRef::new(id, None, "web", None)
+    }
+
+    fn turn(n: usize) -> Vec<ChatMessage> {
+        vec![
+            ChatMessage::new(Role::User, format!("pregunta {}", n)),
+            ChatMessage::new(Role::Assistant, format!("respuesta {}", n)),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_add_messages_is_one_transaction() {
+        let (provider, memory) = in_memory(200);
+        provider
+            .add_messages(&session("s1"), turn(1))
+            .await
+            .unwrap();
+
+        let store = memory.store();
+        assert_eq!(store.round_trips, 1);
+        assert_eq!(store.transactions, 1);
+        assert_eq!(store.lists["TEST:s1"].len(), 2);
+        assert_eq!(store.ttls["TEST:s1"], 3_600);
+        assert_eq!(store.ttls["TEST:s1:meta"], 3_600);
+
+        let updated_at: u64 = String::from_utf8_lossy(&store.hashes["TEST:s1:meta"]["updated_at"])
+            .parse()
+            .unwrap();
+        assert!(updated_at.abs_diff(unix_now()) <= 1);
+    }
+
+    #[tokio::test]
+    async fn test_history_is_trimmed_to_max_messages() {
+        let (provider, _) = in_memory(5);
+        for n in 0..4 {
+            provider
+                .add_messages(&session("s1"), turn(n))
+                .await
+                .unwrap();
+        }
+
+        // Un límite impar se redondea hacia abajo a turnos completos.
+        let history = provider.get_history("s1").await.unwrap();
+        assert_eq!(history[0].role, Role::User);
+        let contents: Vec<&str> = history.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            ["pregunta 2", "respuesta 2", "pregunta 3", "respuesta 3"]
+        );
+
+        let (unbounded, _) = in_memory(0);
+        for n in 0..4 {
+            unbounded
+                .add_messages(&session("s1"), turn(n))
+                .await
+                .unwrap();
+        }
+        assert_eq!(unbounded.get_history("s1").await.unwrap().len(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_vault_ttl_is_refreshed_by_turns_without_pii() {
+        let (provider, memory) = in_memory(0);
+        let pii_turn = vec![
+            ChatMessage::new(Role::User, "Escríbanme a ana@example.com"),
+            ChatMessage::new(Role::Assistant, "Anotado."),
+        ];
+        provider
+            .add_messages(&session("s1"), pii_turn)
+            .await
+            .unwrap();
+        assert!(memory.store().hashes.contains_key("TEST:s1:pii"));
+
+        // Simula el paso del tiempo: al vault le queda poco TTL.
+        memory.store().ttls.insert("TEST:s1:pii".to_string(), 5);
+        provider
+            .add_messages(&session("s1"), turn(2))
+            .await
+            .unwrap();
+        {
+            let store = memory.store();
+            assert_eq!(store.ttls["TEST:s1:pii"], store.ttls["TEST:s1"]);
+        }
+
+        let history = provider.get_history("s1").await.unwrap();
+        assert_eq!(history[0].content, "Escríbanme a ana@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_empty_append_skips_redis() {
+        let (provider, memory) = in_memory(200);
+        provider
+            .add_messages(&session("s1"), Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(memory.store().round_trips, 0);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_rejects_unsupported_commands() {
+        let (_, mut memory) = in_memory(0);
+        let result: redis::RedisResult<()> = redis::cmd("FLUSHALL").query_async(&mut memory).await;
+        assert_eq!(result.unwrap_err().kind(), redis::ErrorKind::ResponseError);
+
+        let result: redis::RedisResult<()> = redis::pipe()
+            .atomic()
+            .cmd("SET")
+            .arg("k")
+            .arg("v")
+            .cmd("FLUSHALL")
+            .query_async(&mut memory)
+            .await;
+        assert!(result.is_err());
+    }
+}
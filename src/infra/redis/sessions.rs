@@ -0,0 +1,757 @@
+//! Metadatos de sesión e índices para listar las conversaciones de un cliente.
+//!
+//! ## Claves
+//! - `{base}:{session_id}:meta` — hash con los metadatos (`SessionMeta`), con
+//!   el TTL de la sesión. Se actualiza en la misma transacción que el historial.
+//!   Con cifrado, el título y el resumen se guardan cifrados con la DEK de la
+//!   sesión (ver `infra/encryption.rs`).
+//! - `{base}:sessions:{caller}` — sorted set `session_id` por `updated_at` con
+//!   todas las sesiones del caller.
+//! - `{base}:sessions:{caller}:owner:{owner}` — ídem, por dueño.
+//! - `{base}:sessions:{caller}:status:{status}` — ídem, por estado.
+//!
+//! Los índices son la vía de acceso, no la fuente de verdad: el listado
+//! vuelve a filtrar contra el hash de cada sesión, así que una entrada vieja
+//! (sesión expirada o que cambió de estado) se descarta al leer. Las entradas
+//! más antiguas que `SESSION_TTL` se podan en cada escritura.
+
+use super::{unix_now, RedisProvider};
+use crate::infra::encryption::{self as crypto, DataKey};
+use crate::infra::errors::{DomainError, DomainResult};
+use crate::infra::metrics::time_redis;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStatus {
+    #[default]
+    Open,
+    /// Derivada a un agente humano.
+    Escalated,
+    Closed,
+}
+
+impl SessionStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SessionStatus::Open => "open",
+            SessionStatus::Escalated => "escalated",
+            SessionStatus::Closed => "closed",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "open" => Some(SessionStatus::Open),
+            "escalated" => Some(SessionStatus::Escalated),
+            "closed" => Some(SessionStatus::Closed),
+            _ => None,
+        }
+    }
+}
+
+/// Motivo principal de la conversación.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Intent {
+    AddressChange,
+    Damage,
+    Other,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Sentiment {
+    Positive,
+    Neutral,
+    Negative,
+}
+
+/// Resumen generado por el modelo de `SUMMARY_MODEL` (ver `agents/summary.rs`).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub text: String,
+    pub intent: Intent,
+    pub sentiment: Sentiment,
+    /// Mensajes de la sesión al momento de resumirla.
+    pub message_count: u64,
+    pub generated_at: u64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SessionMeta {
+    pub session_id: String,
+    /// Cliente final dueño de la conversación (`user_id` del primer turno o,
+    /// si no se envió, el caller).
+    pub owner: String,
+    /// Nombre de la API key que creó la sesión.
+    pub caller: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub status: SessionStatus,
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    /// Mensajes escritos en la sesión, incluidos los que ya se descartaron
+    /// por `HISTORY_MAX_MESSAGES`.
+    pub message_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<SessionSummary>,
+    /// Titulares, además del dueño, a los que se vinculó la sesión (ver `subjects.rs`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub subjects: Vec<String>,
+    /// Precios cotizados por las herramientas en la sesión: el tope de
+    /// `refund_cap` en los turnos siguientes (ver `guardrails/output.rs`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub quoted_prices: Vec<f64>,
+}
+
+impl SessionMeta {
+    /// Lee el hash de metadatos. `None` si la sesión no existe o es anterior
+    /// a los metadatos (sin `caller`).
+    pub fn from_fields(session_id: &str, mut fields: HashMap<String, String>) -> Option<Self> {
+        let caller = fields.remove("caller")?;
+        let number = |value: Option<&String>| value.and_then(|v| v.parse().ok()).unwrap_or(0);
+
+        Some(Self {
+            session_id: session_id.to_string(),
+            owner: fields.remove("owner").unwrap_or_else(|| caller.clone()),
+            created_at: number(fields.get("created_at")),
+            updated_at: number(fields.get("updated_at")),
+            title: fields.remove("title").filter(|t| !t.is_empty()),
+            status: fields
+                .get("status")
+                .and_then(|s| SessionStatus::parse(s))
+                .unwrap_or_default(),
+            tags: fields
+                .get("tags")
+                .and_then(|t| serde_json::from_str(t).ok())
+                .unwrap_or_default(),
+            variant: fields.remove("variant"),
+            message_count: number(fields.get("message_count")),
+            summary: fields
+                .get("summary")
+                .and_then(|s| serde_json::from_str(s).ok()),
+            subjects: fields
+                .get("subjects")
+                .and_then(|s| serde_json::from_str(s).ok())
+                .unwrap_or_default(),
+            quoted_prices: fields
+                .get("quoted_prices")
+                .and_then(|s| serde_json::from_str(s).ok())
+                .unwrap_or_default(),
+            caller,
+        })
+    }
+}
+
+/// Campos de texto libre de los metadatos: se cifran con la DEK de la sesión.
+pub(super) const SEALED_FIELDS: [&str; 2] = ["title", "summary"];
+
+/// Descifra en el hash de metadatos los campos de `SEALED_FIELDS`.
+pub(super) fn open_sealed_fields(
+    fields: &mut HashMap<String, String>,
+    data_keys: &[DataKey],
+) -> anyhow::Result<()> {
+    for field in SEALED_FIELDS {
+        if let Some(value) = fields.remove(field) {
+            fields.insert(field.to_string(), crypto::decode(value, data_keys)?);
+        }
+    }
+    Ok(())
+}
+
+/// Sesión en la que se escribe un turno: dueño y estado para mantener los índices.
+#[derive(Debug, Clone)]
+pub struct SessionRef {
+    pub id: String,
+    pub caller: String,
+    pub owner: String,
+    pub status: SessionStatus,
+    /// Titulares vinculados a la sesión además del dueño (ver `subjects.rs`).
+    pub subjects: Vec<String>,
+    /// Tickets emitidos en el turno.
+    pub tickets: Vec<String>,
+    /// Precios cotizados en la sesión, incluidos los del turno.
+    pub quoted_prices: Vec<f64>,
+}
+
+impl SessionRef {
+    /// Una sesión existente conserva su dueño y estado; una nueva pertenece a
+    /// `user_id` o, si no se envió, al caller.
+    pub fn new(id: &str, meta: Option<&SessionMeta>, caller: &str, user_id: Option<&str>) -> Self {
+        match meta {
+            Some(meta) => Self {
+                id: id.to_string(),
+                caller: meta.caller.clone(),
+                owner: meta.owner.clone(),
+                status: meta.status,
+                subjects: meta.subjects.clone(),
+                tickets: Vec::new(),
+                quoted_prices: meta.quoted_prices.clone(),
+            },
+            None => Self {
+                id: id.to_string(),
+                caller: caller.to_string(),
+                owner: user_id.unwrap_or(caller).to_string(),
+                status: SessionStatus::Open,
+                subjects: Vec::new(),
+                tickets: Vec::new(),
+                quoted_prices: Vec::new(),
+            },
+        }
+    }
+
+    /// El dueño como titular de datos: `None` si la sesión quedó a nombre del
+    /// caller porque no se envió `user_id` (ver `subjects.rs`).
+    pub fn explicit_owner(&self) -> Option<&String> {
+        (self.owner != self.caller).then_some(&self.owner)
+    }
+
+    /// Agrega los titulares y tickets registrados durante el turno.
+    pub fn with_links(mut self, subjects: Vec<String>, tickets: Vec<String>) -> Self {
+        for subject in subjects {
+            if subject != self.owner && !self.subjects.contains(&subject) {
+                self.subjects.push(subject);
+            }
+        }
+        self.tickets = tickets;
+        self
+    }
+
+    /// Agrega los precios cotizados en el turno. Se conservan los
+    /// `MAX_QUOTED_PRICES` más recientes.
+    pub fn with_quotes(mut self, prices: Vec<f64>) -> Self {
+        for price in prices {
+            self.quoted_prices.retain(|p| *p != price);
+            self.quoted_prices.push(price);
+        }
+        let excess = self.quoted_prices.len().saturating_sub(MAX_QUOTED_PRICES);
+        self.quoted_prices.drain(..excess);
+        self
+    }
+}
+
+/// Precios cotizados que guarda una sesión.
+const MAX_QUOTED_PRICES: usize = 20;
+
+/// Filtros de `GET /sessions`.
+#[derive(Debug, Default)]
+pub struct SessionFilter {
+    pub owner: Option<String>,
+    pub status: Option<SessionStatus>,
+    /// Sólo sesiones con actividad desde este instante (segundos epoch).
+    pub since: Option<u64>,
+}
+
+/// Posición en el listado: `updated_at` y id de la última sesión devuelta.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionCursor {
+    pub updated_at: u64,
+    pub session_id: String,
+}
+
+impl fmt::Display for SessionCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.updated_at, self.session_id)
+    }
+}
+
+impl std::str::FromStr for SessionCursor {
+    type Err = DomainError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        value
+            .split_once(':')
+            .and_then(|(updated_at, session_id)| {
+                Some(Self {
+                    updated_at: updated_at.parse().ok()?,
+                    session_id: session_id.to_string(),
+                })
+            })
+            .filter(|cursor| !cursor.session_id.is_empty())
+            .ok_or_else(|| DomainError::validation("Cursor inválido"))
+    }
+}
+
+/// Entradas leídas de un índice por vuelta al paginar.
+const LIST_BATCH: usize = 50;
+
+impl RedisProvider {
+    pub(super) fn meta_key(&self, session_id: &str) -> String {
+        format!("{}:meta", self.get_key(session_id))
+    }
+
+    pub(super) fn caller_index_key(&self, caller: &str) -> String {
+        format!("{}:sessions:{}", self.base_path, caller)
+    }
+
+    pub(super) fn owner_index_key(&self, caller: &str, owner: &str) -> String {
+        format!("{}:owner:{}", self.caller_index_key(caller), owner)
+    }
+
+    pub(super) fn status_index_key(&self, caller: &str, status: SessionStatus) -> String {
+        format!(
+            "{}:status:{}",
+            self.caller_index_key(caller),
+            status.as_str()
+        )
+    }
+
+    /// Agrega al pipeline (ya en `MULTI`) la actualización de metadatos e
+    /// índices por un turno con `count` mensajes nuevos.
+    pub(super) fn queue_session_update(
+        &self,
+        pipe: &mut redis::Pipeline,
+        session: &SessionRef,
+        variant: Option<&str>,
+        count: usize,
+    ) {
+        let now = unix_now();
+        let ttl = self.ttl as i64;
+        let meta_key = self.meta_key(&session.id);
+
+        pipe.hset_nx(&meta_key, "caller", &session.caller)
+            .ignore()
+            .hset_nx(&meta_key, "owner", &session.owner)
+            .ignore()
+            .hset_nx(&meta_key, "created_at", now)
+            .ignore()
+            .hset_nx(&meta_key, "status", session.status.as_str())
+            .ignore()
+            .hset(&meta_key, "updated_at", now)
+            .ignore()
+            .hincr(&meta_key, "message_count", count)
+            .ignore();
+        if let Some(variant) = variant {
+            pipe.hset(&meta_key, "variant", variant).ignore();
+        }
+        if !session.subjects.is_empty() {
+            let subjects =
+                serde_json::to_string(&session.subjects).expect("Failed to serialize subjects");
+            pipe.hset(&meta_key, "subjects", subjects).ignore();
+        }
+        if !session.quoted_prices.is_empty() {
+            let prices = serde_json::to_string(&session.quoted_prices)
+                .expect("Failed to serialize quoted prices");
+            pipe.hset(&meta_key, "quoted_prices", prices).ignore();
+        }
+        pipe.expire(&meta_key, ttl).ignore();
+
+        for index in [
+            self.caller_index_key(&session.caller),
+            self.owner_index_key(&session.caller, &session.owner),
+            self.status_index_key(&session.caller, session.status),
+        ] {
+            pipe.zadd(&index, &session.id, now)
+                .ignore()
+                .zrembyscore(&index, "-inf", now.saturating_sub(self.ttl))
+                .ignore()
+                .expire(&index, ttl)
+                .ignore();
+        }
+        self.queue_subject_links(pipe, session, now);
+    }
+
+    pub async fn get_session(&self, session_id: &str) -> DomainResult<Option<SessionMeta>> {
+        let mut con = self.connection.clone();
+        let (fields, wrapped_key, retired_key): (
+            HashMap<String, String>,
+            Option<String>,
+            Option<String>,
+        ) = time_redis(
+            "get_session",
+            redis::pipe()
+                .hgetall(self.meta_key(session_id))
+                .get(self.dek_key(session_id))
+                .get(self.retired_dek_key(session_id))
+                .query_async(&mut con),
+        )
+        .await?;
+        Ok(self
+            .open_meta(session_id, fields, wrapped_key, retired_key)
+            .await?)
+    }
+
+    /// Metadatos con el título y el resumen descifrados. Las DEKs sólo se
+    /// abren si hay campos cifrados.
+    async fn open_meta(
+        &self,
+        session_id: &str,
+        mut fields: HashMap<String, String>,
+        wrapped_key: Option<String>,
+        retired_key: Option<String>,
+    ) -> anyhow::Result<Option<SessionMeta>> {
+        let sealed = SEALED_FIELDS
+            .iter()
+            .any(|field| fields.get(*field).is_some_and(|v| crypto::is_encrypted(v)));
+        if sealed {
+            let data_keys = self.open_data_keys(wrapped_key, retired_key).await?;
+            open_sealed_fields(&mut fields, &data_keys)?;
+        }
+        Ok(SessionMeta::from_fields(session_id, fields))
+    }
+
+    /// Guarda el resumen de la sesión. El título generado no reemplaza uno
+    /// existente (por ejemplo, asignado con `PATCH /sessions/{id}`).
+    pub async fn save_summary(
+        &self,
+        session_id: &str,
+        title: &str,
+        summary: &SessionSummary,
+    ) -> DomainResult<()> {
+        let mut con = self.connection.clone();
+        let meta_key = self.meta_key(session_id);
+        let data_key = self.session_data_key(session_id).await?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .hset_nx(
+                &meta_key,
+                "title",
+                crypto::encode(title.to_string(), data_key.as_ref())?,
+            )
+            .ignore()
+            .hset(
+                &meta_key,
+                "summary",
+                crypto::encode(serde_json::to_string(summary)?, data_key.as_ref())?,
+            )
+            .ignore()
+            .expire(&meta_key, self.ttl as i64)
+            .ignore();
+        if data_key.is_some() {
+            self.queue_data_key_refresh(&mut pipe, session_id);
+        }
+        time_redis("save_summary", pipe.query_async::<()>(&mut con)).await?;
+
+        Ok(())
+    }
+
+    /// Cambia estado, título o etiquetas de una sesión del caller.
+    pub async fn update_session(
+        &self,
+        session_id: &str,
+        caller: &str,
+        status: Option<SessionStatus>,
+        title: Option<String>,
+        tags: Option<Vec<String>>,
+    ) -> DomainResult<SessionMeta> {
+        let mut con = self.connection.clone();
+        let mut meta = self
+            .get_session(session_id)
+            .await?
+            .filter(|meta| meta.caller == caller)
+            .ok_or_else(|| DomainError::session_not_found(session_id))?;
+
+        let meta_key = self.meta_key(session_id);
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        if let Some(status) = status.filter(|s| *s != meta.status) {
+            let index = self.status_index_key(caller, status);
+            pipe.hset(&meta_key, "status", status.as_str())
+                .ignore()
+                .zrem(self.status_index_key(caller, meta.status), session_id)
+                .ignore()
+                .zadd(&index, session_id, meta.updated_at)
+                .ignore()
+                .expire(&index, self.ttl as i64)
+                .ignore();
+            meta.status = status;
+        }
+        if let Some(title) = title {
+            let data_key = self.session_data_key(session_id).await?;
+            pipe.hset(
+                &meta_key,
+                "title",
+                crypto::encode(title.clone(), data_key.as_ref())?,
+            )
+            .ignore();
+            if data_key.is_some() {
+                self.queue_data_key_refresh(&mut pipe, session_id);
+            }
+            meta.title = Some(title).filter(|t| !t.is_empty());
+        }
+        if let Some(tags) = tags {
+            pipe.hset(&meta_key, "tags", serde_json::to_string(&tags)?)
+                .ignore();
+            meta.tags = tags;
+        }
+
+        time_redis("update_session", pipe.query_async::<()>(&mut con)).await?;
+        Ok(meta)
+    }
+
+    /// Sesiones del caller, de la más reciente a la más antigua. Devuelve
+    /// hasta `limit` sesiones y el cursor para la página siguiente, si la hay.
+    pub async fn list_sessions(
+        &self,
+        caller: &str,
+        filter: &SessionFilter,
+        limit: usize,
+        cursor: Option<&SessionCursor>,
+    ) -> DomainResult<(Vec<SessionMeta>, Option<SessionCursor>)> {
+        let mut con = self.connection.clone();
+
+        // El índice más selectivo disponible; el resto de filtros se aplica
+        // sobre los metadatos.
+        let index = match (&filter.owner, filter.status) {
+            (Some(owner), _) => self.owner_index_key(caller, owner),
+            (None, Some(status)) => self.status_index_key(caller, status),
+            (None, None) => self.caller_index_key(caller),
+        };
+        let max = cursor.map_or("+inf".to_string(), |c| c.updated_at.to_string());
+        let min = filter.since.map_or("-inf".to_string(), |s| s.to_string());
+
+        let mut sessions: Vec<(SessionMeta, u64)> = Vec::new();
+        let mut offset = 0;
+        while sessions.len() <= limit {
+            let entries: Vec<(String, f64)> = time_redis(
+                "list_sessions",
+                con.zrevrangebyscore_limit_withscores(
+                    &index,
+                    &max,
+                    &min,
+                    offset as isize,
+                    LIST_BATCH as isize,
+                ),
+            )
+            .await?;
+            let fetched = entries.len();
+            offset += fetched;
+
+            // Con el mismo `updated_at`, Redis ordena por id descendente: las
+            // sesiones con id >= al del cursor ya se devolvieron.
+            let entries: Vec<(String, u64)> = entries
+                .into_iter()
+                .map(|(id, score)| (id, score as u64))
+                .filter(|(id, score)| {
+                    cursor.is_none_or(|c| *score < c.updated_at || *id < c.session_id)
+                })
+                .collect();
+
+            let mut pipe = redis::pipe();
+            for (id, _) in &entries {
+                pipe.hgetall(self.meta_key(id))
+                    .get(self.dek_key(id))
+                    .get(self.retired_dek_key(id));
+            }
+            let values: Vec<redis::Value> = if entries.is_empty() {
+                Vec::new()
+            } else {
+                time_redis("list_sessions", pipe.query_async(&mut con)).await?
+            };
+
+            for ((id, score), values) in entries.into_iter().zip(values.chunks(3)) {
+                let fields: HashMap<String, String> = redis::from_redis_value(&values[0])?;
+                let wrapped_key: Option<String> = redis::from_redis_value(&values[1])?;
+                let retired_key: Option<String> = redis::from_redis_value(&values[2])?;
+                let Some(meta) = self
+                    .open_meta(&id, fields, wrapped_key, retired_key)
+                    .await?
+                else {
+                    continue;
+                };
+                let matches = meta.caller == caller
+                    && filter.owner.as_ref().is_none_or(|o| *o == meta.owner)
+                    && filter.status.is_none_or(|s| s == meta.status);
+                if matches {
+                    sessions.push((meta, score));
+                }
+            }
+
+            if fetched < LIST_BATCH {
+                break;
+            }
+        }
+
+        let next = (sessions.len() > limit).then(|| {
+            sessions.truncate(limit);
+            let (meta, score) = &sessions[limit - 1];
+            SessionCursor {
+                updated_at: *score,
+                session_id: meta.session_id.clone(),
+            }
+        });
+
+        Ok((sessions.into_iter().map(|(meta, _)| meta).collect(), next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::redis::{ChatMessage, Role};
+
+    async fn chat(provider: &RedisProvider, id: &str, caller: &str, user_id: Option<&str>) {
+        let meta = provider.get_session(id).await.unwrap();
+        let session = SessionRef::new(id, meta.as_ref(), caller, user_id);
+        let messages = vec![
+            ChatMessage::new(Role::User, "hola").with_variant("default"),
+            ChatMessage::new(Role::Assistant, "¿en qué te ayudo?").with_variant("default"),
+        ];
+        provider.add_messages(&session, messages).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_metadata_is_kept_across_turns() {
+        let (provider, _) = RedisProvider::in_memory(3_600, 0);
+        chat(&provider, "s1", "web", Some("cliente-1")).await;
+        chat(&provider, "s1", "web", Some("otro")).await;
+
+        let meta = provider.get_session("s1").await.unwrap().unwrap();
+        assert_eq!(meta.owner, "cliente-1");
+        assert_eq!(meta.caller, "web");
+        assert_eq!(meta.status, SessionStatus::Open);
+        assert_eq!(meta.variant.as_deref(), Some("default"));
+        assert_eq!(meta.message_count, 4);
+        assert!(meta.created_at > 0 && meta.updated_at >= meta.created_at);
+
+        assert!(provider.get_session("s2").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_quoted_prices_are_kept_across_turns() {
+        let (provider, _) = RedisProvider::in_memory(3_600, 0);
+        let first = SessionRef::new("s1", None, "web", None).with_quotes(vec![899.0, 350.0]);
+        provider
+            .add_messages(&first, vec![ChatMessage::new(Role::User, "hola")])
+            .await
+            .unwrap();
+
+        let meta = provider.get_session("s1").await.unwrap().unwrap();
+        assert_eq!(meta.quoted_prices, vec![899.0, 350.0]);
+
+        let next = SessionRef::new("s1", Some(&meta), "web", None).with_quotes(vec![899.0, 120.0]);
+        assert_eq!(next.quoted_prices, vec![350.0, 899.0, 120.0]);
+        let many = next.with_quotes((0..30).map(f64::from).collect());
+        assert_eq!(many.quoted_prices.len(), MAX_QUOTED_PRICES);
+        assert_eq!(many.quoted_prices.last(), Some(&29.0));
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions_filters_and_paginates() {
+        let (provider, _) = RedisProvider::in_memory(3_600, 0);
+        for id in ["a", "b", "c"] {
+            chat(&provider, id, "web", Some("cliente-1")).await;
+        }
+        chat(&provider, "d", "web", Some("cliente-2")).await;
+        chat(&provider, "e", "mobile", Some("cliente-1")).await;
+
+        // Mismo `updated_at`: el orden por id descendente desempata.
+        let filter = SessionFilter {
+            owner: Some("cliente-1".to_string()),
+            ..Default::default()
+        };
+        let (page, cursor) = provider
+            .list_sessions("web", &filter, 2, None)
+            .await
+            .unwrap();
+        let ids: Vec<&str> = page.iter().map(|m| m.session_id.as_str()).collect();
+        assert_eq!(ids, ["c", "b"]);
+
+        let cursor: SessionCursor = cursor.unwrap().to_string().parse().unwrap();
+        let (page, cursor) = provider
+            .list_sessions("web", &filter, 2, Some(&cursor))
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].session_id, "a");
+        assert!(cursor.is_none());
+
+        let (all, _) = provider
+            .list_sessions("web", &SessionFilter::default(), 10, None)
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 4);
+
+        let future = SessionFilter {
+            since: Some(unix_now() + 60),
+            ..Default::default()
+        };
+        let (none, _) = provider
+            .list_sessions("web", &future, 10, None)
+            .await
+            .unwrap();
+        assert!(none.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_status_change_moves_index() {
+        let (provider, _) = RedisProvider::in_memory(3_600, 0);
+        chat(&provider, "s1", "web", None).await;
+
+        let meta = provider
+            .update_session("s1", "web", Some(SessionStatus::Escalated), None, None)
+            .await
+            .unwrap();
+        assert_eq!(meta.status, SessionStatus::Escalated);
+        assert_eq!(meta.owner, "web");
+
+        let by_status = |status| SessionFilter {
+            status: Some(status),
+            ..Default::default()
+        };
+        let (open, _) = provider
+            .list_sessions("web", &by_status(SessionStatus::Open), 10, None)
+            .await
+            .unwrap();
+        assert!(open.is_empty());
+        let (escalated, _) = provider
+            .list_sessions("web", &by_status(SessionStatus::Escalated), 10, None)
+            .await
+            .unwrap();
+        assert_eq!(escalated.len(), 1);
+
+        // Otro caller no ve ni modifica la sesión.
+        assert!(provider
+            .update_session("s1", "mobile", None, Some("x".to_string()), None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_summary_keeps_manual_title() {
+        let (provider, _) = RedisProvider::in_memory(3_600, 0);
+        chat(&provider, "s1", "web", None).await;
+        chat(&provider, "s2", "web", None).await;
+        provider
+            .update_session("s2", "web", None, Some("Reclamo VIP".to_string()), None)
+            .await
+            .unwrap();
+
+        let summary = SessionSummary {
+            text: "El cliente saludó.".to_string(),
+            intent: Intent::Other,
+            sentiment: Sentiment::Positive,
+            message_count: 2,
+            generated_at: unix_now(),
+        };
+        for id in ["s1", "s2"] {
+            provider
+                .save_summary(id, "Saludo inicial", &summary)
+                .await
+                .unwrap();
+        }
+
+        let s1 = provider.get_session("s1").await.unwrap().unwrap();
+        assert_eq!(s1.title.as_deref(), Some("Saludo inicial"));
+        assert_eq!(s1.summary, Some(summary));
+        let s2 = provider.get_session("s2").await.unwrap().unwrap();
+        assert_eq!(s2.title.as_deref(), Some("Reclamo VIP"));
+    }
+
+    #[test]
+    fn test_cursor_parsing() {
+        let cursor: SessionCursor = "1700000000:abc:1".parse().unwrap();
+        assert_eq!(cursor.updated_at, 1_700_000_000);
+        assert_eq!(cursor.session_id, "abc:1");
+        assert!("abc".parse::<SessionCursor>().is_err());
+        assert!("12:".parse::<SessionCursor>().is_err());
+    }
+}
@@ -0,0 +1,468 @@
+//! Índice por titular de datos, para atender pedidos de acceso (exportación)
+//! y de supresión (borrado).
+//!
+//! Un titular es el dueño de una sesión (el `user_id` del chat) o un cliente
+//! mencionado en ella: el `customer_id` de un cambio de dirección (ver
+//! `TurnContext::record_subject`). Sólo se indexan ids explícitos: una sesión
+//! sin `user_id` queda a nombre del caller, que agrupa a todos los usuarios de
+//! esa API key y no identifica a nadie.
+//!
+//! El borrado elimina todas las sesiones vinculadas al titular, también las de
+//! otros dueños que sólo lo mencionan: su historial, vault y resumen guardan
+//! los datos del titular mezclados con los del dueño, sin forma de separarlos.
+//! La exportación, en cambio, sólo incluye las sesiones de las que es dueño,
+//! para no entregarle conversaciones de otras personas.
+//!
+//! ## Claves
+//! - `{base}:subjects:{subject_id}` — sorted set `session_id` por fecha de la
+//!   última escritura, con el TTL de la sesión.
+//! - `{base}:subjects:{subject_id}:tickets` — hash `ticket_id → session_id`
+//!   con los tickets emitidos en esas sesiones.
+//! - `{base}:erasures:log` — sorted set de `SubjectErasure` (JSON) por fecha.
+//!   Constancia de cada borrado; no expira.
+//!
+//! El feedback se alcanza a través de las sesiones. El consumo (`usage:log`)
+//! se conserva: sólo guarda ids de sesión y tokens, y sostiene la facturación.
+
+use super::feedback::Feedback;
+use super::sessions::{SessionMeta, SessionRef};
+use super::{unix_now, ChatMessage, RedisProvider};
+use crate::infra::errors::DomainResult;
+use crate::infra::metrics::time_redis;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Ticket emitido en una sesión del titular.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TicketLink {
+    pub ticket_id: String,
+    pub session_id: String,
+}
+
+/// Una sesión del titular con todo lo que se guarda de ella.
+#[derive(Serialize, Debug)]
+pub struct SessionArchive {
+    pub session: SessionMeta,
+    /// Historial con los valores reales: el titular tiene derecho a verlos.
+    pub messages: Vec<ChatMessage>,
+    pub feedback: Vec<Feedback>,
+}
+
+/// Respuesta de `GET /subjects/{id}/export`.
+#[derive(Serialize, Debug)]
+pub struct SubjectExport {
+    pub subject_id: String,
+    pub exported_at: u64,
+    pub sessions: Vec<SessionArchive>,
+    pub tickets: Vec<TicketLink>,
+}
+
+/// Constancia de un borrado (`DELETE /subjects/{id}`).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SubjectErasure {
+    pub subject_id: String,
+    /// Sesiones borradas (historial, vault de PII, metadatos y data key).
+    pub sessions: usize,
+    /// De ellas, sesiones de otros titulares que lo mencionaban.
+    #[serde(default)]
+    pub mentioned: usize,
+    pub feedback: usize,
+    pub tickets: usize,
+    pub erased_at: u64,
+}
+
+impl RedisProvider {
+    fn subject_key(&self, subject_id: &str) -> String {
+        format!("{}:subjects:{}", self.base_path, subject_id)
+    }
+
+    fn subject_tickets_key(&self, subject_id: &str) -> String {
+        format!("{}:tickets", self.subject_key(subject_id))
+    }
+
+    fn erasures_key(&self) -> String {
+        format!("{}:erasures:log", self.base_path)
+    }
+
+    /// Agrega al pipeline (ya en `MULTI`) el vínculo de la sesión con su dueño
+    /// (si es un `user_id` explícito) y con los demás titulares, y los tickets
+    /// emitidos en el turno.
+    pub(super) fn queue_subject_links(
+        &self,
+        pipe: &mut redis::Pipeline,
+        session: &SessionRef,
+        now: u64,
+    ) {
+        let ttl = self.ttl as i64;
+        let links: Vec<(&String, &String)> = session
+            .tickets
+            .iter()
+            .map(|ticket| (ticket, &session.id))
+            .collect();
+
+        for subject in session
+            .explicit_owner()
+            .into_iter()
+            .chain(&session.subjects)
+        {
+            let index = self.subject_key(subject);
+            let tickets = self.subject_tickets_key(subject);
+            pipe.zadd(&index, &session.id, now)
+                .ignore()
+                .zrembyscore(&index, "-inf", now.saturating_sub(self.ttl))
+                .ignore()
+                .expire(&index, ttl)
+                .ignore();
+            if !links.is_empty() {
+                pipe.hset_multiple(&tickets, &links).ignore();
+            }
+            pipe.expire(&tickets, ttl).ignore();
+        }
+    }
+
+    /// Sesiones (de la más antigua a la más reciente) y tickets del titular.
+    async fn subject_links(
+        &self,
+        subject_id: &str,
+    ) -> DomainResult<(Vec<String>, Vec<TicketLink>)> {
+        let mut con = self.connection.clone();
+        let (sessions, tickets): (Vec<String>, HashMap<String, String>) = time_redis(
+            "subject_links",
+            redis::pipe()
+                .zrange(self.subject_key(subject_id), 0, -1)
+                .hgetall(self.subject_tickets_key(subject_id))
+                .query_async(&mut con),
+        )
+        .await?;
+
+        let mut tickets: Vec<TicketLink> = tickets
+            .into_iter()
+            .map(|(ticket_id, session_id)| TicketLink {
+                ticket_id,
+                session_id,
+            })
+            .collect();
+        tickets.sort_by(|a, b| a.ticket_id.cmp(&b.ticket_id));
+        Ok((sessions, tickets))
+    }
+
+    /// Todo lo guardado del titular: las sesiones de las que es dueño con
+    /// historial, metadatos y feedback, y sus tickets. Las sesiones ya
+    /// expiradas y las de otros dueños que sólo lo mencionan se omiten.
+    pub async fn export_subject(&self, subject_id: &str) -> DomainResult<SubjectExport> {
+        let (session_ids, tickets) = self.subject_links(subject_id).await?;
+
+        let mut sessions = Vec::new();
+        for session_id in session_ids {
+            let (messages, meta) = self.get_conversation(&session_id).await?;
+            let Some(session) = meta.filter(|meta| meta.owner == subject_id) else {
+                continue;
+            };
+            let feedback = self.get_session_feedback(&session_id).await?;
+            sessions.push(SessionArchive {
+                session,
+                messages,
+                feedback,
+            });
+        }
+
+        Ok(SubjectExport {
+            subject_id: subject_id.to_string(),
+            exported_at: unix_now(),
+            sessions,
+            tickets,
+        })
+    }
+
+    /// Borra las sesiones vinculadas al titular, propias o que lo mencionan
+    /// (historial, vault de PII, metadatos, data key y feedback), y las quita
+    /// de todos los índices. Deja constancia del borrado, todo en una
+    /// transacción.
+    pub async fn erase_subject(&self, subject_id: &str) -> DomainResult<SubjectErasure> {
+        let mut con = self.connection.clone();
+        let (session_ids, tickets) = self.subject_links(subject_id).await?;
+
+        let mut reads = redis::pipe();
+        for session_id in &session_ids {
+            reads
+                .hgetall(self.meta_key(session_id))
+                .hlen(self.feedback_key(session_id));
+        }
+        let values: Vec<redis::Value> = if session_ids.is_empty() {
+            Vec::new()
+        } else {
+            time_redis("erase_subject", reads.query_async(&mut con)).await?
+        };
+
+        let mut erasure = SubjectErasure {
+            subject_id: subject_id.to_string(),
+            sessions: 0,
+            mentioned: 0,
+            feedback: 0,
+            tickets: tickets.len(),
+            erased_at: unix_now(),
+        };
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (session_id, values) in session_ids.iter().zip(values.chunks(2)) {
+            let fields: HashMap<String, String> = redis::from_redis_value(&values[0])?;
+            let meta = SessionMeta::from_fields(session_id, fields);
+
+            let feedback: usize = redis::from_redis_value(&values[1])?;
+            erasure.feedback += feedback;
+            pipe.del(&[
+                self.get_key(session_id),
+                self.vault_key(session_id),
+                self.meta_key(session_id),
+                self.dek_key(session_id),
+                self.retired_dek_key(session_id),
+                self.feedback_key(session_id),
+            ])
+            .ignore()
+            .zrem(self.feedback_index_key(), session_id)
+            .ignore()
+            .zrem(self.encryption_index_key(), session_id)
+            .ignore();
+
+            let Some(meta) = meta else {
+                continue;
+            };
+            erasure.sessions += 1;
+            if meta.owner != subject_id {
+                erasure.mentioned += 1;
+            }
+            pipe.zrem(self.caller_index_key(&meta.caller), session_id)
+                .ignore()
+                .zrem(self.owner_index_key(&meta.caller, &meta.owner), session_id)
+                .ignore()
+                .zrem(self.status_index_key(&meta.caller, meta.status), session_id)
+                .ignore();
+            // Otros titulares vinculados a la misma sesión, incluido su dueño.
+            let ticket_ids: Vec<&String> = tickets
+                .iter()
+                .filter(|t| t.session_id == *session_id)
+                .map(|t| &t.ticket_id)
+                .collect();
+            let owner = (meta.owner != meta.caller).then_some(&meta.owner);
+            for other in meta
+                .subjects
+                .iter()
+                .chain(owner)
+                .filter(|s| *s != subject_id)
+            {
+                pipe.zrem(self.subject_key(other), session_id).ignore();
+                if !ticket_ids.is_empty() {
+                    pipe.hdel(self.subject_tickets_key(other), &ticket_ids)
+                        .ignore();
+                }
+            }
+        }
+        pipe.del(&[
+            self.subject_key(subject_id),
+            self.subject_tickets_key(subject_id),
+        ])
+        .ignore()
+        .zadd(
+            self.erasures_key(),
+            serde_json::to_string(&erasure)?,
+            erasure.erased_at,
+        )
+        .ignore();
+
+        time_redis("erase_subject", pipe.query_async::<()>(&mut con)).await?;
+
+        tracing::info!(
+            target: "audit",
+            subject_id,
+            sessions = erasure.sessions,
+            mentioned = erasure.mentioned,
+            feedback = erasure.feedback,
+            tickets = erasure.tickets,
+            "Subject data erased"
+        );
+        Ok(erasure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::redis::feedback::Rating;
+    use crate::infra::redis::sessions::{Intent, Sentiment, SessionSummary};
+    use crate::infra::redis::Role;
+
+    async fn chat(
+        provider: &RedisProvider,
+        id: &str,
+        user_id: &str,
+        subjects: &[&str],
+        tickets: &[&str],
+    ) -> ChatMessage {
+        let meta = provider.get_session(id).await.unwrap();
+        let session = SessionRef::new(id, meta.as_ref(), "web", Some(user_id)).with_links(
+            subjects.iter().map(|s| s.to_string()).collect(),
+            tickets.iter().map(|t| t.to_string()).collect(),
+        );
+        let reply = ChatMessage::new(Role::Assistant, "Listo, registré tu solicitud.");
+        let messages = vec![
+            ChatMessage::new(Role::User, "Soy CLI-12345, mi pedido llegó roto"),
+            reply.clone(),
+        ];
+        provider.add_messages(&session, messages).await.unwrap();
+        reply
+    }
+
+    async fn rate(provider: &RedisProvider, session_id: &str, message: &ChatMessage) {
+        let feedback = Feedback {
+            session_id: session_id.to_string(),
+            message_id: message.id.clone(),
+            rating: Rating::Up,
+            category: None,
+            comment: None,
+            variant: None,
+            created_at: unix_now(),
+        };
+        provider.save_feedback(&feedback).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_includes_only_owned_sessions() {
+        let (provider, _) = RedisProvider::in_memory(3_600, 0);
+        let reply = chat(&provider, "s1", "cliente-1", &[], &["DMG-1"]).await;
+        rate(&provider, "s1", &reply).await;
+        // La sesión de otro usuario menciona al cliente en un cambio de dirección.
+        chat(&provider, "s2", "agente-7", &["cliente-1"], &[]).await;
+        chat(&provider, "s3", "cliente-2", &[], &[]).await;
+
+        let export = provider.export_subject("cliente-1").await.unwrap();
+        let ids: Vec<&str> = export
+            .sessions
+            .iter()
+            .map(|s| s.session.session_id.as_str())
+            .collect();
+        // s2 es de agente-7: no se le entrega al cliente.
+        assert_eq!(ids, ["s1"]);
+        assert_eq!(export.sessions[0].messages.len(), 2);
+        assert_eq!(export.sessions[0].feedback.len(), 1);
+        assert_eq!(
+            export.tickets,
+            [TicketLink {
+                ticket_id: "DMG-1".to_string(),
+                session_id: "s1".to_string(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_erase_purges_sessions_and_indexes() {
+        let (provider, memory) = RedisProvider::in_memory(3_600, 0);
+        let reply = chat(&provider, "s1", "cliente-1", &[], &["DMG-1"]).await;
+        rate(&provider, "s1", &reply).await;
+        chat(&provider, "s2", "agente-7", &["cliente-1"], &["DMG-2"]).await;
+        chat(&provider, "s3", "cliente-2", &[], &[]).await;
+
+        let erasure = provider.erase_subject("cliente-1").await.unwrap();
+        assert_eq!(erasure.sessions, 2);
+        assert_eq!(erasure.mentioned, 1);
+        assert_eq!(erasure.feedback, 1);
+        assert_eq!(erasure.tickets, 2);
+
+        assert!(provider.get_session("s1").await.unwrap().is_none());
+        assert!(provider.get_session("s3").await.unwrap().is_some());
+        // La sesión de agente-7 mencionaba al cliente: también se borra y sale
+        // del índice de su dueño.
+        assert!(provider.get_session("s2").await.unwrap().is_none());
+        assert!(provider.get_history("s2").await.unwrap().is_empty());
+        let export = provider.export_subject("agente-7").await.unwrap();
+        assert!(export.sessions.is_empty());
+        assert!(export.tickets.is_empty());
+
+        let store = memory.store();
+        for key in ["TEST:s1:feedback", "TEST:subjects:cliente-1"] {
+            assert!(!store.hashes.contains_key(key) && !store.sorted_sets.contains_key(key));
+        }
+        assert!(!store.hashes.contains_key("TEST:subjects:cliente-1:tickets"));
+        assert!(!store.sorted_sets["TEST:sessions:web"].contains_key("s1"));
+        assert!(store.sorted_sets["TEST:sessions:web"].contains_key("s3"));
+
+        let log = &store.sorted_sets["TEST:erasures:log"];
+        let record: SubjectErasure = serde_json::from_str(log.keys().next().unwrap()).unwrap();
+        assert_eq!(record, erasure);
+    }
+
+    #[tokio::test]
+    async fn test_users_sharing_an_api_key_are_separate_subjects() {
+        let (provider, memory) = RedisProvider::in_memory(3_600, 0);
+        chat(&provider, "s1", "ana", &[], &[]).await;
+        chat(&provider, "s2", "beto", &[], &[]).await;
+        // Sin `user_id`, la sesión queda a nombre del caller y no se indexa.
+        let anonymous = SessionRef::new("s3", None, "web", None);
+        provider
+            .add_messages(&anonymous, vec![ChatMessage::new(Role::User, "hola")])
+            .await
+            .unwrap();
+        assert!(!memory.store().sorted_sets.contains_key("TEST:subjects:web"));
+        assert!(provider
+            .export_subject("web")
+            .await
+            .unwrap()
+            .sessions
+            .is_empty());
+
+        let erasure = provider.erase_subject("ana").await.unwrap();
+        assert_eq!((erasure.sessions, erasure.mentioned), (1, 0));
+        assert!(provider.get_session("s1").await.unwrap().is_none());
+        for id in ["s2", "s3"] {
+            assert!(provider.get_session(id).await.unwrap().is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_erase_leaves_no_trace_of_a_mentioned_subject() {
+        let (provider, memory) = RedisProvider::in_memory(3_600, 0);
+        // agente-7 atiende al cliente CLI-12345: el historial, el vault y el
+        // resumen de su sesión guardan los datos del cliente.
+        chat(&provider, "s1", "agente-7", &["CLI-12345"], &["DMG-1"]).await;
+        let summary = SessionSummary {
+            text: "CLI-12345 reporta un pedido roto".to_string(),
+            intent: Intent::Damage,
+            sentiment: Sentiment::Negative,
+            message_count: 2,
+            generated_at: unix_now(),
+        };
+        provider
+            .save_summary("s1", "Pedido roto de CLI-12345", &summary)
+            .await
+            .unwrap();
+
+        provider.erase_subject("CLI-12345").await.unwrap();
+
+        let store = memory.store();
+        let mut values: Vec<(String, String)> = Vec::new();
+        for (key, value) in &store.strings {
+            values.push((key.clone(), String::from_utf8_lossy(value).into_owned()));
+        }
+        for (key, items) in &store.lists {
+            for item in items {
+                values.push((key.clone(), String::from_utf8_lossy(item).into_owned()));
+            }
+        }
+        for (key, fields) in &store.hashes {
+            for (field, value) in fields {
+                values.push((key.clone(), field.clone()));
+                values.push((key.clone(), String::from_utf8_lossy(value).into_owned()));
+            }
+        }
+        for (key, members) in &store.sorted_sets {
+            // La constancia del borrado conserva el id a propósito.
+            if key != "TEST:erasures:log" {
+                values.extend(members.keys().map(|m| (key.clone(), m.clone())));
+            }
+        }
+        for (key, value) in values {
+            assert!(!key.contains("CLI-12345"), "{}", key);
+            assert!(!value.contains("CLI-12345"), "{} = {}", key, value);
+        }
+    }
+}
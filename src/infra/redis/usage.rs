@@ -0,0 +1,237 @@
+//! Consumo de tokens y costo por turno, sesión y caller.
+//!
+//! ## Claves
+//! - `{base}:usage:log` — sorted set de `UsageRecord` (JSON) por fecha. Un
+//!   registro por modelo y turno (o resumen de sesión); se conservan
+//!   `USAGE_RETENTION_DAYS` días.
+//!   Es la fuente de `GET /usage` (por sesión, caller, modelo o día).
+//! - `{base}:usage:caller:{caller}:{AAAA-MM}` — gasto acumulado (USD) del
+//!   caller en el mes, usado para los presupuestos.
+
+use super::{unix_now, RedisProvider};
+use crate::infra::errors::DomainResult;
+use crate::infra::metrics::time_redis;
+use crate::infra::usage::{month_key, TurnUsage, UsageRecord};
+use redis::AsyncCommands;
+
+/// Los acumulados mensuales sobreviven al mes siguiente para poder consultarlos.
+const MONTHLY_TTL_SECONDS: i64 = 62 * 86_400;
+
+impl RedisProvider {
+    fn usage_log_key(&self) -> String {
+        format!("{}:usage:log", self.base_path)
+    }
+
+    fn caller_month_key(&self, caller: &str, timestamp: u64) -> String {
+        format!(
+            "{}:usage:caller:{}:{}",
+            self.base_path,
+            caller,
+            month_key(timestamp)
+        )
+    }
+
+    /// Guarda el consumo acumulado en un turno con fecha de ahora.
+    pub async fn record_turn_usage(
+        &self,
+        request_id: &str,
+        session_id: &str,
+        caller: &str,
+        usage: &TurnUsage,
+    ) -> DomainResult<()> {
+        let created_at = unix_now();
+        let records: Vec<UsageRecord> = usage
+            .models
+            .iter()
+            .map(|model| UsageRecord {
+                request_id: request_id.to_string(),
+                session_id: session_id.to_string(),
+                caller: caller.to_string(),
+                usage: model.clone(),
+                created_at,
+            })
+            .collect();
+        self.record_usage(&records).await
+    }
+
+    /// Guarda el consumo de un turno (un registro por modelo).
+    pub async fn record_usage(&self, records: &[UsageRecord]) -> DomainResult<()> {
+        let Some(first) = records.first() else {
+            return Ok(());
+        };
+
+        let mut con = self.connection.clone();
+        let log_key = self.usage_log_key();
+        let month_key = self.caller_month_key(&first.caller, first.created_at);
+        let retention = crate::config::get().store.usage_retention_days * 86_400;
+        let cost: f64 = records.iter().map(|r| r.usage.cost_usd).sum();
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for record in records {
+            pipe.zadd(&log_key, serde_json::to_string(record)?, record.created_at)
+                .ignore();
+        }
+        pipe.zrembyscore(&log_key, "-inf", first.created_at.saturating_sub(retention))
+            .ignore()
+            .incr(&month_key, cost)
+            .ignore()
+            .expire(&month_key, MONTHLY_TTL_SECONDS)
+            .ignore();
+
+        time_redis("record_usage", pipe.query_async::<()>(&mut con)).await?;
+        Ok(())
+    }
+
+    /// Registros de consumo entre `from` y `to` (segundos epoch).
+    pub async fn usage_records(&self, from: u64, to: u64) -> DomainResult<Vec<UsageRecord>> {
+        let mut con = self.connection.clone();
+        let entries: Vec<String> = time_redis(
+            "usage_records",
+            con.zrangebyscore(self.usage_log_key(), from, to),
+        )
+        .await?;
+
+        entries
+            .iter()
+            .map(|json| serde_json::from_str(json).map_err(Into::into))
+            .collect()
+    }
+
+    /// Gasto (USD) del caller en el mes de `timestamp`.
+    pub async fn monthly_cost(&self, caller: &str, timestamp: u64) -> DomainResult<f64> {
+        let mut con = self.connection.clone();
+        let cost: Option<f64> = time_redis(
+            "monthly_cost",
+            con.get(self.caller_month_key(caller, timestamp)),
+        )
+        .await?;
+
+        Ok(cost.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::usage::{aggregate, Accounting, GroupBy, ModelUsage};
+
+    /// 2026-10-15 12:00 UTC.
+    const NOW: u64 = 1_792_065_600;
+
+    fn record(request_id: &str, caller: &str, model: &str, cost_usd: f64, at: u64) -> UsageRecord {
+        UsageRecord {
+            request_id: request_id.to_string(),
+            session_id: format!("session-{}", caller),
+            caller: caller.to_string(),
+            usage: ModelUsage {
+                provider: "gemini".to_string(),
+                model: model.to_string(),
+                calls: 1,
+                input_tokens: 1_000,
+                output_tokens: 100,
+                cost_usd,
+            },
+            created_at: at,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_usage_accumulates_monthly_cost() {
+        let (provider, memory) = RedisProvider::in_memory(3_600, 0);
+        let accounting = Accounting::new("", "web=1");
+
+        provider
+            .record_usage(&[
+                record("r1", "web", "gemini-2.5-flash", 0.25, NOW),
+                record("r1", "web", "gemini-2.5-pro", 0.5, NOW),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(memory.store().transactions, 1);
+        let spent = provider.monthly_cost("web", NOW).await.unwrap();
+        assert_eq!(spent, 0.75);
+        assert!(spent < accounting.budget("web").unwrap());
+
+        provider
+            .record_usage(&[record("r2", "web", "gemini-2.5-flash", 0.25, NOW + 60)])
+            .await
+            .unwrap();
+        // Con el segundo turno el caller agota su presupuesto del mes.
+        let spent = provider.monthly_cost("web", NOW).await.unwrap();
+        assert_eq!(spent, 1.0);
+        assert!(spent >= accounting.budget("web").unwrap());
+
+        assert_eq!(provider.monthly_cost("mobile", NOW).await.unwrap(), 0.0);
+        assert_eq!(
+            provider
+                .monthly_cost("web", NOW + 40 * 86_400)
+                .await
+                .unwrap(),
+            0.0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_usage_records_by_range_and_group() {
+        let (provider, _memory) = RedisProvider::in_memory(3_600, 0);
+        for records in [
+            vec![
+                record("r1", "web", "gemini-2.5-flash", 0.1, NOW - 86_400),
+                record("r1", "web", "gemini-2.5-pro", 0.2, NOW - 86_400),
+            ],
+            vec![record("r2", "web", "gemini-2.5-flash", 0.1, NOW)],
+            vec![record("r3", "mobile", "gemini-2.5-flash", 0.3, NOW + 60)],
+        ] {
+            provider.record_usage(&records).await.unwrap();
+        }
+
+        let records = provider.usage_records(NOW, NOW + 3_600).await.unwrap();
+        let ids: Vec<&str> = records.iter().map(|r| r.request_id.as_str()).collect();
+        assert_eq!(ids, ["r2", "r3"]);
+
+        let records = provider.usage_records(0, NOW + 3_600).await.unwrap();
+        let by_caller = aggregate(&records, GroupBy::Caller);
+        assert_eq!(by_caller.len(), 2);
+        assert_eq!(by_caller[0].key, "mobile");
+        assert_eq!(by_caller[1].key, "web");
+        assert_eq!(by_caller[1].totals.requests, 2);
+        assert_eq!(by_caller[1].totals.input_tokens, 3_000);
+
+        let by_model = aggregate(&records, GroupBy::Model);
+        let keys: Vec<&str> = by_model.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(keys, ["gemini-2.5-flash", "gemini-2.5-pro"]);
+        assert_eq!(by_model[0].totals.requests, 3);
+
+        let by_day = aggregate(&records, GroupBy::Day);
+        assert_eq!(by_day.len(), 2);
+        assert_eq!(by_day[1].key, "2026-10-15");
+    }
+
+    #[tokio::test]
+    async fn test_record_turn_usage() {
+        let (provider, _memory) = RedisProvider::in_memory(3_600, 0);
+        let usage = TurnUsage::new(vec![record("", "", "gemini-2.5-flash", 0.5, 0).usage]);
+
+        provider
+            .record_turn_usage("r1", "s1", "web", &usage)
+            .await
+            .unwrap();
+        // Un turno sin llamadas al modelo no escribe nada.
+        provider
+            .record_turn_usage("r2", "s1", "web", &TurnUsage::default())
+            .await
+            .unwrap();
+
+        let records = provider.usage_records(0, u64::MAX).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            (
+                records[0].request_id.as_str(),
+                records[0].session_id.as_str()
+            ),
+            ("r1", "s1")
+        );
+        assert_eq!(provider.monthly_cost("web", unix_now()).await.unwrap(), 0.5);
+    }
+}
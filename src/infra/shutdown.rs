@@ -0,0 +1,132 @@
+//! # Apagado ordenado
+//!
+//! Al recibir SIGTERM/SIGINT (Cloud Run envía SIGTERM y mata el proceso
+//! ~10s después):
+//!
+//! 1. El servicio entra en *drain*: `/health` y `/health/ready` responden 503
+//!    y `/chat` rechaza turnos nuevos con `SERVICE_UNAVAILABLE`.
+//! 2. Se espera hasta `SHUTDOWN_GRACE_SECS` a que terminen (y se persistan)
+//!    los turnos en curso y las tareas de fondo registradas con `spawn`.
+//! 3. El servidor deja de aceptar conexiones y `main` vacía la telemetría.
+//!
+//! Si el plazo vence con trabajo pendiente, `main` aborta lo que quede.
+
+use std::future::Future;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::task_tracker::TrackedFuture;
+use tokio_util::task::TaskTracker;
+
+#[derive(Clone, Default)]
+pub struct Lifecycle {
+    draining: CancellationToken,
+    expired: CancellationToken,
+    tasks: TaskTracker,
+}
+
+impl Lifecycle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.is_cancelled()
+    }
+
+    /// Se resuelve al comenzar el drain. Las tareas de fondo lo usan para salir.
+    pub async fn draining(&self) {
+        self.draining.cancelled().await
+    }
+
+    /// Se resuelve si el plazo de gracia venció con trabajo pendiente.
+    pub async fn grace_expired(&self) {
+        self.expired.cancelled().await
+    }
+
+    /// Registra un turno en curso: el drain lo espera antes de apagar.
+    pub fn track<F: Future>(&self, future: F) -> TrackedFuture<F> {
+        self.tasks.track_future(future)
+    }
+
+    /// Lanza una tarea de fondo que el drain espera. La tarea debe terminar
+    /// por su cuenta al recibir `draining()`.
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tasks.spawn(future)
+    }
+
+    /// Entra en drain y espera hasta `grace` a que termine el trabajo en curso.
+    /// Devuelve `false` si el plazo venció antes.
+    pub async fn drain(&self, grace: Duration) -> bool {
+        self.draining.cancel();
+        self.tasks.close();
+
+        let finished = tokio::time::timeout(grace, self.tasks.wait()).await.is_ok();
+        if !finished {
+            self.expired.cancel();
+        }
+        finished
+    }
+}
+
+/// Espera SIGTERM (Cloud Run, Kubernetes) o SIGINT (Ctrl+C).
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for SIGINT: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("SIGINT received"),
+        _ = terminate => tracing::info!("SIGTERM received"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drain_waits_for_tracked_work() {
+        let lifecycle = Lifecycle::new();
+        let turn = tokio::spawn(lifecycle.track(tokio::time::sleep(Duration::from_millis(20))));
+
+        assert!(lifecycle.drain(Duration::from_secs(1)).await);
+        assert!(lifecycle.is_draining());
+        turn.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_drain_expires_with_pending_work() {
+        let lifecycle = Lifecycle::new();
+        // Una tarea que ignora `draining()` agota el plazo.
+        lifecycle.spawn(tokio::time::sleep(Duration::from_secs(60)));
+
+        assert!(!lifecycle.drain(Duration::from_millis(10)).await);
+        tokio::time::timeout(Duration::from_millis(10), lifecycle.grace_expired())
+            .await
+            .expect("grace period should be expired");
+    }
+}
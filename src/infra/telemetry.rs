@@ -1,56 +1,361 @@
-use opentelemetry_gcloud_trace::GcpCloudTraceExporterBuilder;
+//! # Telemetría
+//!
+//! Logs y trazas con backend configurable (`TELEMETRY_BACKEND`):
+//!
+//! - `gcp`: logs en formato Cloud Logging y trazas a Cloud Trace (por defecto).
+//! - `otlp`: logs JSON en stdout y trazas OTLP (gRPC o HTTP) hacia cualquier collector.
+//! - `console`: logs legibles para desarrollo, sin exportar trazas.
+//! - `json`: logs JSON en stdout, sin exportar trazas.
+//!
+//! Si el exportador elegido no puede inicializarse (ej. `gcp` fuera de GCP),
+//! se degrada a `console` en lugar de quedarse sin logs.
+
+use crate::config::TelemetryBackend;
+use crate::infra::redaction::{self, Redactor};
+use opentelemetry::trace::{Status, TracerProvider};
+use opentelemetry::{InstrumentationScope, KeyValue, Value};
+use opentelemetry_gcloud_trace::{GcpCloudTraceExporter, GcpCloudTraceExporterBuilder};
+use opentelemetry_otlp::{Protocol, WithExportConfig};
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::runtime;
+use opentelemetry_sdk::trace::span_processor_with_async_runtime::BatchSpanProcessor;
+use opentelemetry_sdk::trace::{SdkTracerProvider, SpanData, SpanExporter};
+use opentelemetry_sdk::Resource;
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::time::Duration;
+use tracing::Subscriber;
 use tracing_stackdriver::CloudTraceConfiguration;
-use tracing_subscriber::{layer::SubscriberExt, EnvFilter};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Layer};
+
+/// Mantiene vivo el proveedor de trazas. Debe cerrarse con `shutdown` al
+/// terminar el proceso para exportar los spans pendientes.
+pub struct TelemetryGuard {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl TelemetryGuard {
+    pub async fn shutdown(self) {
+        let Some(provider) = self.provider else {
+            return;
+        };
 
-pub async fn init_tracing() -> anyhow::Result<()> {
-    let config = crate::envs::get();
-    let service_name = &config.service_name;
-    let base_level = &config.debug_level;
+        // `shutdown` bloquea hasta vaciar el batch: fuera de los workers de tokio.
+        let result = tokio::task::spawn_blocking(move || provider.shutdown()).await;
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => eprintln!("Failed to flush traces on shutdown: {}", e),
+            Err(e) => eprintln!("Trace shutdown task failed: {}", e),
+        }
+    }
+}
+
+pub async fn init_tracing() -> TelemetryGuard {
+    let config = crate::config::get();
+    let base_level = &config.telemetry.level;
 
     let env_filter = EnvFilter::new(format!(
         "h2=warn,hyper=warn,tokio_util=warn,tower_http=warn,rig=warn,axum=warn,{}",
         base_level
     ));
 
-    let project_id = &config.project_id;
-
-    // Stackdriver Logging Layer
-    let stackdriver_layer =
-        tracing_stackdriver::layer().with_cloud_trace(CloudTraceConfiguration {
-            project_id: project_id.clone(),
-        });
+    let requested = config.telemetry.backend;
+    let (backend, provider, init_error) = match build_provider(requested).await {
+        Ok(provider) => (requested, provider, None),
+        Err(e) => (TelemetryBackend::Console, None, Some(e.to_string())),
+    };
 
     // OpenTelemetry Trace Layer
-    let builder = GcpCloudTraceExporterBuilder::for_default_project_id()
-        .await?
-        .with_resource(
-            opentelemetry_sdk::Resource::builder()
-                .with_attributes(vec![opentelemetry::KeyValue::new(
-                    opentelemetry_semantic_conventions::resource::SERVICE_NAME,
-                    service_name.to_string(),
-                )])
+    let telemetry_layer = provider.as_ref().map(|provider| {
+        let tracer = provider.tracer_with_scope(
+            InstrumentationScope::builder(config.server.service_name.clone())
+                .with_schema_url("https://opentelemetry.io/schemas/1.23.0")
                 .build(),
         );
-
-    let provider = builder
-        .create_provider()
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to create tracer provider: {}", e))?;
-
-    let tracer_provider: opentelemetry_sdk::trace::Tracer = builder
-        .install(&provider)
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to install tracer: {}", e))?;
-
-    // Open Telemetry
-    let telemetry_layer = tracing_opentelemetry::layer().with_tracer(tracer_provider);
+        tracing_opentelemetry::layer().with_tracer(tracer)
+    });
 
     let subscriber = tracing_subscriber::registry()
         .with(env_filter)
-        .with(stackdriver_layer)
+        .with(log_layer(backend))
         .with(telemetry_layer);
 
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 
-    Ok(())
+    match init_error {
+        Some(error) => tracing::warn!(
+            requested = ?requested,
+            error = %error,
+            "Telemetry backend unavailable, falling back to console"
+        ),
+        None => tracing::info!(backend = ?backend, "Telemetry initialized"),
+    }
+
+    TelemetryGuard { provider }
+}
+
+/// Capa de logs según el backend. Todas escriben a través del `RedactingMakeWriter`.
+fn log_layer<S>(backend: TelemetryBackend) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let redactor = redaction::get();
+    let writer = RedactingMakeWriter {
+        redactor: redactor.redact_logs.then_some(redactor),
+    };
+
+    match backend {
+        // Stackdriver Logging Layer
+        TelemetryBackend::Gcp => tracing_stackdriver::layer()
+            .with_cloud_trace(CloudTraceConfiguration {
+                project_id: crate::config::get().server.project_id.clone(),
+            })
+            .with_writer(writer)
+            .boxed(),
+        TelemetryBackend::Otlp | TelemetryBackend::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_writer(writer)
+            .boxed(),
+        TelemetryBackend::Console => tracing_subscriber::fmt::layer()
+            .pretty()
+            .with_writer(writer)
+            .boxed(),
+    }
+}
+
+/// Crea el proveedor de trazas del backend (`None` si no exporta trazas).
+async fn build_provider(backend: TelemetryBackend) -> anyhow::Result<Option<SdkTracerProvider>> {
+    let config = crate::config::get();
+    let redactor = redaction::get();
+    let redactor = redactor.redact_logs.then_some(redactor);
+
+    let resource = Resource::builder()
+        .with_attributes(vec![KeyValue::new(
+            opentelemetry_semantic_conventions::resource::SERVICE_NAME,
+            config.server.service_name.to_string(),
+        )])
+        .build();
+
+    let provider = match backend {
+        TelemetryBackend::Console | TelemetryBackend::Json => return Ok(None),
+        TelemetryBackend::Gcp => {
+            let project_id = GcpCloudTraceExporterBuilder::for_default_project_id()
+                .await?
+                .google_project_id;
+            let exporter = GcpCloudTraceExporter::new(&project_id, resource)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to create Cloud Trace exporter: {}", e))?;
+
+            batch_provider(RedactingExporter {
+                inner: exporter,
+                redactor,
+            })
+            .build()
+        }
+        TelemetryBackend::Otlp => {
+            let endpoint = config.telemetry.otlp_endpoint();
+            let exporter = match config.telemetry.otlp_protocol.as_str() {
+                "grpc" => opentelemetry_otlp::SpanExporter::builder()
+                    .with_tonic()
+                    .with_endpoint(endpoint)
+                    .build()?,
+                "http/protobuf" => opentelemetry_otlp::SpanExporter::builder()
+                    .with_http()
+                    .with_protocol(Protocol::HttpBinary)
+                    .with_endpoint(otlp_http_traces_url(endpoint))
+                    .build()?,
+                other => anyhow::bail!("Unsupported OTLP protocol '{}'", other),
+            };
+
+            batch_provider(RedactingExporter {
+                inner: exporter,
+                redactor,
+            })
+            .with_resource(resource)
+            .build()
+        }
+    };
+
+    Ok(Some(provider))
+}
+
+fn batch_provider<E: SpanExporter + 'static>(
+    exporter: E,
+) -> opentelemetry_sdk::trace::TracerProviderBuilder {
+    SdkTracerProvider::builder()
+        .with_span_processor(BatchSpanProcessor::builder(exporter, runtime::Tokio).build())
+}
+
+/// El exportador HTTP usa el endpoint tal cual: se completa la ruta de trazas
+/// si sólo se configuró la URL base del collector.
+fn otlp_http_traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/traces", endpoint)
+    }
+}
+
+// ============================================================================
+// REDACCIÓN DE LOGS
+// ============================================================================
+
+/// `MakeWriter` que enmascara PII en cada línea de log antes de escribirla
+/// en stdout. Con `redactor: None` escribe sin modificar.
+struct RedactingMakeWriter {
+    redactor: Option<&'static Redactor>,
+}
+
+impl<'a> MakeWriter<'a> for RedactingMakeWriter {
+    type Writer = RedactingWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            redactor: self.redactor,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+/// Acumula la entrada completa y la escribe (redactada) al soltarse, ya que
+/// el formateador JSON puede emitir una entrada en varias escrituras.
+struct RedactingWriter {
+    redactor: Option<&'static Redactor>,
+    buffer: Vec<u8>,
+}
+
+impl Write for RedactingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for RedactingWriter {
+    fn drop(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let line = match self.redactor {
+            Some(redactor) => redact_line(redactor, &self.buffer),
+            None => std::mem::take(&mut self.buffer),
+        };
+
+        let _ = io::stdout().lock().write_all(&line);
+    }
+}
+
+fn redact_line(redactor: &Redactor, line: &[u8]) -> Vec<u8> {
+    if let Ok(mut json) = serde_json::from_slice::<serde_json::Value>(line) {
+        redactor.mask_json(&mut json);
+        if let Ok(mut output) = serde_json::to_vec(&json) {
+            output.push(b'\n');
+            return output;
+        }
+    }
+
+    // No es JSON válido: se enmascara como texto plano.
+    redactor
+        .mask(&String::from_utf8_lossy(line))
+        .into_owned()
+        .into_bytes()
+}
+
+// ============================================================================
+// REDACCIÓN DE TRAZAS
+// ============================================================================
+
+/// Envuelve el exportador de Cloud Trace y enmascara la PII de los atributos
+/// de spans y eventos, y de las descripciones de error, antes de enviarlos.
+#[derive(Debug)]
+struct RedactingExporter<E> {
+    inner: E,
+    redactor: Option<&'static Redactor>,
+}
+
+impl<E: SpanExporter> SpanExporter for RedactingExporter<E> {
+    async fn export(&self, mut batch: Vec<SpanData>) -> OTelSdkResult {
+        if let Some(redactor) = self.redactor {
+            batch
+                .iter_mut()
+                .for_each(|span| redact_span(redactor, span));
+        }
+        self.inner.export(batch).await
+    }
+
+    fn shutdown_with_timeout(&mut self, timeout: Duration) -> OTelSdkResult {
+        self.inner.shutdown_with_timeout(timeout)
+    }
+
+    fn force_flush(&mut self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.inner.set_resource(resource)
+    }
+}
+
+fn redact_span(redactor: &Redactor, span: &mut SpanData) {
+    redact_attributes(redactor, &mut span.attributes);
+    for event in span.events.events.iter_mut() {
+        redact_attributes(redactor, &mut event.attributes);
+    }
+    if let Status::Error { description } = &mut span.status {
+        if let Cow::Owned(masked) = redactor.mask(description) {
+            *description = masked.into();
+        }
+    }
+}
+
+fn redact_attributes(redactor: &Redactor, attributes: &mut [KeyValue]) {
+    for attribute in attributes {
+        if let Value::String(text) = &attribute.value {
+            if let Cow::Owned(masked) = redactor.mask(text.as_str()) {
+                attribute.value = Value::String(masked.into());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_otlp_http_traces_url() {
+        assert_eq!(
+            otlp_http_traces_url("http://collector:4318/"),
+            "http://collector:4318/v1/traces"
+        );
+        assert_eq!(
+            otlp_http_traces_url("http://collector:4318/v1/traces"),
+            "http://collector:4318/v1/traces"
+        );
+    }
+
+    #[test]
+    fn test_redact_line_masks_json_and_plain_text() {
+        let redactor = Redactor::new(&crate::infra::redaction::PiiKind::ALL);
+
+        let json = redact_line(
+            &redactor,
+            br#"{"severity":"INFO","message":"correo ana@correo.com"}"#,
+        );
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["message"], "correo [EMAIL]");
+        assert_eq!(json["severity"], "INFO");
+
+        let plain = redact_line(&redactor, b"  INFO correo ana@correo.com\n");
+        assert_eq!(String::from_utf8(plain).unwrap(), "  INFO correo [EMAIL]\n");
+    }
 }
@@ -0,0 +1,342 @@
+//! # Consumo de tokens y costo
+//!
+//! Cada completion del orquestador, de los especialistas y de los guardrails
+//! (clasificador y reescritura) se acumula en el `TurnContext` del turno (ver
+//! `AnyModel::with_turn`) y se valora con la tabla de precios por modelo. Al
+//! terminar el turno, incluso por timeout, error o desconexión, el consumo se
+//! guarda en Redis (ver `infra/redis/usage.rs`); si hubo respuesta, también se
+//! devuelve en el `trace` del chat. Los resúmenes de sesión se cargan al
+//! caller de la sesión.
+//!
+//! - `MODEL_PRICES`: `modelo=entrada/salida;...` en USD por millón de tokens.
+//!   Se combina con la tabla embebida (`DEFAULT_PRICES`). Los modelos sin
+//!   precio cuentan tokens con costo 0.
+//! - `USAGE_BUDGETS`: `caller=usd;...`, límite mensual de gasto por caller
+//!   (el nombre de su API key, ver `API_KEYS`). Sin entrada, no hay límite.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::OnceLock;
+
+/// Precios de referencia (USD por millón de tokens: entrada, salida).
+const DEFAULT_PRICES: &[(&str, f64, f64)] = &[
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.5-flash-lite", 0.10, 0.40),
+    ("gpt-4o", 2.50, 10.0),
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("claude-3-5-sonnet-latest", 3.0, 15.0),
+    ("claude-3-5-haiku-latest", 0.80, 4.0),
+    ("claude-sonnet-4-5", 3.0, 15.0),
+];
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+// ============================================================================
+// 1. PRECIOS Y PRESUPUESTOS
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    /// USD por millón de tokens de entrada.
+    pub input: f64,
+    /// USD por millón de tokens de salida.
+    pub output: f64,
+}
+
+pub struct Accounting {
+    prices: HashMap<String, ModelPrice>,
+    budgets: HashMap<String, f64>,
+}
+
+static ACCOUNTING: OnceLock<Accounting> = OnceLock::new();
+
+pub fn get() -> &'static Accounting {
+    ACCOUNTING.get_or_init(Accounting::from_config)
+}
+
+impl Accounting {
+    pub fn new(prices_spec: &str, budgets_spec: &str) -> Self {
+        let mut prices: HashMap<String, ModelPrice> = DEFAULT_PRICES
+            .iter()
+            .map(|(model, input, output)| {
+                (
+                    model.to_string(),
+                    ModelPrice {
+                        input: *input,
+                        output: *output,
+                    },
+                )
+            })
+            .collect();
+
+        for entry in entries(prices_spec) {
+            let parsed = entry.split_once('=').and_then(|(model, price)| {
+                let (input, output) = price.split_once('/')?;
+                let price = ModelPrice {
+                    input: input.trim().parse().ok()?,
+                    output: output.trim().parse().ok()?,
+                };
+                Some((model.trim().to_string(), price))
+            });
+            match parsed {
+                Some((model, price)) => {
+                    prices.insert(model, price);
+                }
+                None => tracing::warn!("Ignoring invalid model price entry: {}", entry),
+            }
+        }
+
+        let budgets = entries(budgets_spec)
+            .filter_map(|entry| {
+                let parsed = entry.split_once('=').and_then(|(caller, usd)| {
+                    Some((caller.trim().to_string(), usd.trim().parse().ok()?))
+                });
+                if parsed.is_none() {
+                    tracing::warn!("Ignoring invalid usage budget entry: {}", entry);
+                }
+                parsed
+            })
+            .collect();
+
+        Self { prices, budgets }
+    }
+
+    pub fn from_config() -> Self {
+        let config = crate::config::get();
+        Self::new(&config.models.prices, &config.limits.usage_budgets)
+    }
+
+    /// Costo en USD de una llamada. Modelos sin precio cuestan 0.
+    pub fn cost(&self, model: &str, input_tokens: u64, output_tokens: u64) -> f64 {
+        self.prices.get(model).map_or(0.0, |price| {
+            (input_tokens as f64 * price.input + output_tokens as f64 * price.output) / 1_000_000.0
+        })
+    }
+
+    /// Límite mensual (USD) del caller, si tiene uno.
+    pub fn budget(&self, caller: &str) -> Option<f64> {
+        self.budgets.get(caller).copied()
+    }
+}
+
+fn entries(spec: &str) -> impl Iterator<Item = &str> {
+    spec.split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+}
+
+// ============================================================================
+// 2. CONSUMO
+// ============================================================================
+
+/// Consumo de un modelo (acumulado en un turno o en un registro guardado).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModelUsage {
+    pub provider: String,
+    pub model: String,
+    pub calls: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Consumo total de un turno, devuelto en el `trace` del chat.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct TurnUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    pub models: Vec<ModelUsage>,
+}
+
+impl TurnUsage {
+    pub fn new(models: Vec<ModelUsage>) -> Self {
+        Self {
+            input_tokens: models.iter().map(|m| m.input_tokens).sum(),
+            output_tokens: models.iter().map(|m| m.output_tokens).sum(),
+            cost_usd: models.iter().map(|m| m.cost_usd).sum(),
+            models,
+        }
+    }
+}
+
+/// Consumo de un modelo en un turno, tal como se guarda en Redis.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UsageRecord {
+    /// Id del mensaje del asistente que generó el turno, o uno propio si el
+    /// turno no respondió o es un resumen de sesión.
+    pub request_id: String,
+    pub session_id: String,
+    pub caller: String,
+    #[serde(flatten)]
+    pub usage: ModelUsage,
+    pub created_at: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    Session,
+    #[default]
+    Caller,
+    Model,
+    Day,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct UsageTotals {
+    /// Turnos distintos (no llamadas al modelo).
+    pub requests: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct UsageGroup {
+    pub key: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+/// Suma los registros por `group_by`, ordenados por clave.
+pub fn aggregate(records: &[UsageRecord], group_by: GroupBy) -> Vec<UsageGroup> {
+    let mut groups: BTreeMap<String, Vec<&UsageRecord>> = BTreeMap::new();
+    for record in records {
+        let key = match group_by {
+            GroupBy::Session => record.session_id.clone(),
+            GroupBy::Caller => record.caller.clone(),
+            GroupBy::Model => record.usage.model.clone(),
+            GroupBy::Day => day_key(record.created_at),
+        };
+        groups.entry(key).or_default().push(record);
+    }
+
+    groups
+        .into_iter()
+        .map(|(key, records)| UsageGroup {
+            key,
+            totals: totals(records),
+        })
+        .collect()
+}
+
+pub fn totals<'a>(records: impl IntoIterator<Item = &'a UsageRecord>) -> UsageTotals {
+    let mut requests = HashSet::new();
+    let mut totals = UsageTotals::default();
+    for record in records {
+        requests.insert(record.request_id.as_str());
+        totals.input_tokens += record.usage.input_tokens;
+        totals.output_tokens += record.usage.output_tokens;
+        totals.cost_usd += record.usage.cost_usd;
+    }
+    totals.requests = requests.len();
+    totals
+}
+
+// ============================================================================
+// 3. FECHAS (UTC)
+// ============================================================================
+
+/// Fecha civil `(año, mes, día)` de un timestamp (segundos epoch, UTC).
+fn civil_date(timestamp: u64) -> (i64, u32, u32) {
+    // Algoritmo `civil_from_days` de Howard Hinnant.
+    let z = (timestamp / SECONDS_PER_DAY) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// `AAAA-MM`, usado para los acumulados mensuales.
+pub fn month_key(timestamp: u64) -> String {
+    let (year, month, _) = civil_date(timestamp);
+    format!("{:04}-{:02}", year, month)
+}
+
+/// `AAAA-MM-DD`.
+pub fn day_key(timestamp: u64) -> String {
+    let (year, month, day) = civil_date(timestamp);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Inicio (00:00 UTC del día 1) del mes de `timestamp`.
+pub fn month_start(timestamp: u64) -> u64 {
+    let (_, _, day) = civil_date(timestamp);
+    let day_start = timestamp - timestamp % SECONDS_PER_DAY;
+    day_start - u64::from(day - 1) * SECONDS_PER_DAY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(request_id: &str, caller: &str, model: &str, cost_usd: f64) -> UsageRecord {
+        UsageRecord {
+            request_id: request_id.to_string(),
+            session_id: "session-1".to_string(),
+            caller: caller.to_string(),
+            usage: ModelUsage {
+                provider: "gemini".to_string(),
+                model: model.to_string(),
+                calls: 1,
+                input_tokens: 100,
+                output_tokens: 10,
+                cost_usd,
+            },
+            created_at: 1_792_337_400,
+        }
+    }
+
+    #[test]
+    fn test_cost_uses_defaults_and_overrides() {
+        let accounting = Accounting::new("gemini-2.5-flash=1/10;mi-modelo=2/4;roto", "web=5;x");
+
+        assert_eq!(accounting.cost("gemini-2.5-flash", 1_000_000, 100_000), 2.0);
+        assert_eq!(accounting.cost("mi-modelo", 500_000, 0), 1.0);
+        assert!(accounting.cost("gemini-2.5-pro", 1_000_000, 0) > 1.0);
+        assert_eq!(accounting.cost("desconocido", 1_000_000, 1_000_000), 0.0);
+        assert_eq!(accounting.budget("web"), Some(5.0));
+        assert_eq!(accounting.budget("mobile"), None);
+    }
+
+    #[test]
+    fn test_aggregate_counts_distinct_requests() {
+        let records = vec![
+            record("r1", "web", "gemini-2.5-pro", 0.5),
+            record("r1", "web", "gemini-2.5-flash", 0.25),
+            record("r2", "mobile", "gemini-2.5-flash", 0.25),
+        ];
+
+        let by_caller = aggregate(&records, GroupBy::Caller);
+        assert_eq!(by_caller.len(), 2);
+        assert_eq!(by_caller[1].key, "web");
+        assert_eq!(by_caller[1].totals.requests, 1);
+        assert_eq!(by_caller[1].totals.input_tokens, 200);
+        assert_eq!(by_caller[1].totals.cost_usd, 0.75);
+
+        let by_model = aggregate(&records, GroupBy::Model);
+        assert_eq!(by_model[0].key, "gemini-2.5-flash");
+        assert_eq!(by_model[0].totals.requests, 2);
+
+        assert_eq!(totals(&records).requests, 2);
+    }
+
+    #[test]
+    fn test_calendar_keys() {
+        // 2026-10-18 15:30:00 UTC
+        assert_eq!(day_key(1_792_337_400), "2026-10-18");
+        assert_eq!(month_key(1_792_337_400), "2026-10");
+        assert_eq!(month_start(1_792_337_400), 1_790_812_800);
+        // 2024-02-29 23:59:00 UTC (bisiesto)
+        assert_eq!(day_key(1_709_251_140), "2024-02-29");
+        assert_eq!(month_start(1_709_251_140), 1_706_745_600);
+    }
+}
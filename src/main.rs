@@ -1,43 +1,103 @@
 mod agents;
 mod api;
-mod envs;
+mod config;
+mod guardrails;
 mod infra;
 mod state;
 
 use std::sync::Arc;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() {
+    // 0. Load configuration (defaults → config/{env}.toml → env vars → flags)
+    let config = match config::init() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
     rustls::crypto::aws_lc_rs::default_provider()
         .install_default()
         .expect("Failed to install default crypto provider");
 
     // 1. Initialize Tracing (Logging)
-    if let Err(e) = infra::telemetry::init_tracing().await {
-        eprintln!("Failed to initialize tracing: {}", e);
-    }
+    let telemetry = infra::telemetry::init_tracing().await;
+    tracing::info!(
+        environment = %config.environment,
+        file = ?config.source,
+        "Configuration loaded"
+    );
 
-    // 2. Initialize Orchestrator
-    let orchestrator = agents::orchestrator::Orchestrator::new();
+    // 2. Initialize Orchestrator (one per A/B variant)
+    let agent_variants =
+        agents::config::AgentsConfig::load().expect("Failed to load agents configuration");
+    let agents = agents::variants::VariantRouter::new(&agent_variants);
+    agents::tools::gazetteer::init().expect("Failed to load gazetteer");
+    agents::tools::shipping_zones::init().expect("Failed to load shipping zones");
+    agents::tools::catalog::init().expect("Failed to load catalog");
 
     // 2.1 Initialize Redis
     let redis_provider = infra::redis::RedisProvider::new()
         .await
         .expect("Failed to initialize Redis");
 
+    // 2.2 Initialize Guardrails
+    let input_guardrails = guardrails::input::InputGuardrails::from_config();
+    let output_guardrails = guardrails::output::OutputGuardrails::from_config();
+
     // 3. Initialize State
-    let state = Arc::new(state::AppState::new(orchestrator, redis_provider));
+    let state = Arc::new(state::AppState::new(
+        agents,
+        redis_provider,
+        input_guardrails,
+        output_guardrails,
+    ));
+    state::spawn_agents_watcher(state.clone());
+    if let Err(e) = state.refresh_catalog().await {
+        tracing::error!("Failed to load imported catalog: {:#}", e);
+    }
+    state::spawn_catalog_watcher(state.clone());
 
     // 4. Setup Router
+    let lifecycle = state.lifecycle.clone();
     let app = api::routes::app_router(state);
 
     // 5. Start Server
-    let config = envs::get();
-    let port = config.port;
+    let port = config.server.port;
     let addr = format!("0.0.0.0:{}", port);
 
     tracing::info!("Server starting on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+
+    // 6. Graceful shutdown: drain in-flight turns, then stop accepting connections
+    let grace = Duration::from_secs(config.server.shutdown_grace_secs);
+    let drain = {
+        let lifecycle = lifecycle.clone();
+        async move {
+            infra::shutdown::signal().await;
+            tracing::info!(grace_secs = grace.as_secs(), "Draining in-flight requests");
+            if !lifecycle.drain(grace).await {
+                tracing::warn!("Grace period expired with requests still in flight");
+            }
+        }
+    };
+
+    tokio::select! {
+        result = axum::serve(listener, app).with_graceful_shutdown(drain) => {
+            if let Err(e) = result {
+                tracing::error!("Server error: {}", e);
+            }
+        }
+        _ = lifecycle.grace_expired() => {
+            tracing::warn!("Aborting remaining requests");
+        }
+    }
+
+    // 7. Flush pending traces
+    telemetry.shutdown().await;
+    tracing::info!("Server stopped");
 }
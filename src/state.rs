@@ -1,13 +1,145 @@
-use crate::agents::orchestrator::Orchestrator;
+use crate::agents::config::{AgentsConfig, ConfigFingerprint};
+use crate::agents::summary::Summarizer;
+use crate::agents::tools::catalog;
+use crate::agents::variants::VariantRouter;
+use crate::guardrails::input::InputGuardrails;
+use crate::guardrails::output::OutputGuardrails;
+use crate::infra::audit::AuditLog;
+use crate::infra::health::ProviderProbes;
 use crate::infra::redis::RedisProvider;
+use crate::infra::shutdown::Lifecycle;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 pub struct AppState {
-    pub orchestrator: Orchestrator,
+    agents: RwLock<Arc<VariantRouter>>,
     pub redis: RedisProvider,
+    pub input_guardrails: InputGuardrails,
+    pub output_guardrails: OutputGuardrails,
+    /// Drain y tareas de fondo (ver `infra/shutdown.rs`).
+    pub lifecycle: Lifecycle,
+    /// Sondas cacheadas a los proveedores LLM (ver `infra/health.rs`).
+    pub provider_probes: ProviderProbes,
+    /// Títulos y resúmenes de sesiones (`None` si `SUMMARY_MODEL` está vacío).
+    pub summarizer: Option<Arc<Summarizer>>,
+    /// Registro de acciones con efectos (ver `infra/audit.rs`).
+    pub audit: AuditLog,
 }
 
 impl AppState {
-    pub fn new(orchestrator: Orchestrator, redis: RedisProvider) -> Self {
-        Self { orchestrator, redis }
+    pub fn new(
+        agents: VariantRouter,
+        redis: RedisProvider,
+        input_guardrails: InputGuardrails,
+        output_guardrails: OutputGuardrails,
+    ) -> Self {
+        Self {
+            agents: RwLock::new(Arc::new(agents)),
+            audit: AuditLog::from_config(&redis),
+            redis,
+            input_guardrails,
+            output_guardrails,
+            lifecycle: Lifecycle::new(),
+            provider_probes: ProviderProbes::from_config(),
+            summarizer: Summarizer::from_config().map(Arc::new),
+        }
     }
+
+    /// Versión vigente de los agentes (una por variante). Cada turno conserva
+    /// su `Arc`, de modo que una recarga no afecta a los turnos en curso.
+    pub fn agents(&self) -> Arc<VariantRouter> {
+        self.agents
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Relee la configuración de agentes y reemplaza los orquestadores de forma atómica.
+    /// Si la configuración es inválida se conserva la versión anterior.
+    pub fn reload_agents(&self) -> anyhow::Result<()> {
+        let variants = AgentsConfig::load()?;
+        let agents = Arc::new(VariantRouter::new(&variants));
+
+        *self.agents.write().unwrap_or_else(|e| e.into_inner()) = agents;
+
+        tracing::info!(variants = variants.len(), "Agents configuration reloaded");
+        Ok(())
+    }
+
+    /// Reemplaza el catálogo vigente por el importado en Redis si es otra versión.
+    pub async fn refresh_catalog(&self) -> anyhow::Result<()> {
+        let current = catalog::get();
+        if let Some(imported) = self.redis.load_catalog(current.version()).await? {
+            tracing::info!(
+                items = imported.items().len(),
+                version = imported.version(),
+                "Catalog refreshed from Redis"
+            );
+            catalog::replace(imported);
+        }
+        Ok(())
+    }
+}
+
+/// Revisa cada `CATALOG_REFRESH_INTERVAL` segundos si otra instancia importó
+/// un catálogo nuevo. No hace nada si el intervalo es 0. Termina al comenzar
+/// el apagado.
+pub fn spawn_catalog_watcher(state: Arc<AppState>) {
+    let interval = crate::config::get().agents.catalog_refresh_interval;
+    if interval == 0 {
+        return;
+    }
+
+    let lifecycle = state.lifecycle.clone();
+    lifecycle.spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+        ticker.tick().await;
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = state.lifecycle.draining() => break,
+            }
+
+            if let Err(e) = state.refresh_catalog().await {
+                tracing::error!("Failed to refresh catalog: {:#}", e);
+            }
+        }
+    });
+}
+
+/// Revisa periódicamente `AGENTS_CONFIG_DIR` y recarga los agentes cuando
+/// cambia algún archivo. No hace nada si el directorio o el intervalo no están definidos.
+/// Termina al comenzar el apagado.
+pub fn spawn_agents_watcher(state: Arc<AppState>) {
+    let interval = crate::config::get().agents.watch_interval;
+    let Some(dir) = AgentsConfig::config_dir() else {
+        return;
+    };
+    if interval == 0 {
+        return;
+    }
+
+    let lifecycle = state.lifecycle.clone();
+    lifecycle.spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+        let mut last = ConfigFingerprint::of(&dir);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = state.lifecycle.draining() => break,
+            }
+
+            let current = ConfigFingerprint::of(&dir);
+            if current == last {
+                continue;
+            }
+            last = current;
+
+            if let Err(e) = state.reload_agents() {
+                tracing::error!("Failed to reload agents configuration: {:#}", e);
+            }
+        }
+    });
 }